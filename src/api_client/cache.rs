@@ -0,0 +1,52 @@
+use log::{debug, warn};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// On disk cache of raw API responses, keyed by request URI.
+#[derive(Debug)]
+pub struct DiskCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl DiskCache {
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        DiskCache { dir, ttl }
+    }
+
+    fn path_for(&self, uri: &str) -> PathBuf {
+        let name: String = uri
+            .trim_start_matches('/')
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        self.dir.join(format!("{name}.json"))
+    }
+
+    /// Read a cached response. Entries older than the ttl are only returned when `allow_stale`
+    /// is set.
+    pub fn get(&self, uri: &str, allow_stale: bool) -> Option<bytes::Bytes> {
+        let path = self.path_for(uri);
+        let modified = path.metadata().and_then(|m| m.modified()).ok()?;
+        let age = SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default();
+
+        if !allow_stale && age > self.ttl {
+            debug!("Cache entry for {uri} is stale");
+            return None;
+        }
+
+        std::fs::read(&path).ok().map(bytes::Bytes::from)
+    }
+
+    /// Write a response to the cache. Failures are logged, a cache miss next time is harmless.
+    pub fn put(&self, uri: &str, bytes: &bytes::Bytes) {
+        let path = self.path_for(uri);
+        if let Err(e) =
+            std::fs::create_dir_all(&self.dir).and_then(|()| std::fs::write(&path, bytes))
+        {
+            warn!("Could not write cache entry {}: {e}", path.display());
+        }
+    }
+}
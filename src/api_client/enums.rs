@@ -16,12 +16,47 @@ impl std::fmt::Display for ApiGroup {
 }
 
 impl ApiGroup {
-    pub fn to_kube_group(&self) -> String {
+    /// Group name as used in `apiVersion` and `x-kubernetes-group-version-kind`, empty for core.
+    pub fn group(&self) -> &str {
         match self {
-            ApiGroup::Core(version) => format!("io.k8s.api.core.{version}"),
-            ApiGroup::Named(name, version) => format!("io.k8s.api.{name}.{version}"),
+            ApiGroup::Core(_) => "",
+            ApiGroup::Named(name, _) => name,
         }
     }
+
+    pub fn version(&self) -> &str {
+        match self {
+            ApiGroup::Core(version) | ApiGroup::Named(_, version) => version,
+        }
+    }
+
+    /// The group as written in a manifest's `apiVersion`, e.g. `v1` or `apps/v1`.
+    pub fn api_version(&self) -> String {
+        match self {
+            ApiGroup::Core(version) => version.clone(),
+            ApiGroup::Named(name, version) => format!("{name}/{version}"),
+        }
+    }
+
+    /// Sort key following Kubernetes version priority: GA before beta before alpha, then higher
+    /// versions first, e.g. `v2` > `v1` > `v1beta2` > `v1beta1` > `v1alpha1`.
+    pub fn version_priority(&self) -> (u8, u32, u32) {
+        let version = self.version().strip_prefix('v').unwrap_or_default();
+        let major_len = version
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(version.len());
+        let (major, rest) = version.split_at(major_len);
+        let major = major.parse().unwrap_or(0);
+
+        let (stability, minor) = if let Some(minor) = rest.strip_prefix("beta") {
+            (1, minor)
+        } else if let Some(minor) = rest.strip_prefix("alpha") {
+            (0, minor)
+        } else {
+            (2, rest)
+        };
+        (stability, major, minor.parse().unwrap_or(0))
+    }
 }
 
 impl<'a> From<(&'a str, &'a str)> for ApiGroup {
@@ -4,11 +4,11 @@ use std::error::Error as _;
 #[allow(clippy::enum_variant_names)]
 #[derive(thiserror::Error)]
 pub enum Error {
-    #[error("KubeError")]
+    #[error("Kubernetes error: {0}")]
     KubeError(#[from] KubeError),
-    #[error("KubeconfigError")]
+    #[error("Kubeconfig error: {0}")]
     KubeconfigError(#[from] KubeconfigError),
-    #[error("HttpError")]
+    #[error("HTTP error: {0}")]
     HttpError(#[from] http::Error),
     #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::error::Error),
@@ -18,6 +18,14 @@ pub enum Error {
     InvalidComponentsTree,
     #[error("Could not find spec for {0}")]
     SpecNotFound(String),
+    #[error("Could not find path {0}")]
+    PathNotFound(String),
+    #[error("Request for {0} failed with status {1}")]
+    RequestFailed(String, http::StatusCode),
+    #[error("{0} is not cached and schemas are offline")]
+    NotCached(String),
+    #[error("No kube context to load schemas for")]
+    NoContext,
 }
 
 impl std::fmt::Debug for Error {
@@ -4,45 +4,124 @@ use kube_client::{
     config::{Config as KubeConfig, KubeConfigOptions},
     Client as KubeClient,
 };
-use log::debug;
+use log::{debug, warn};
 use openapiv3::OpenAPI;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
 
-use crate::config::Config;
+use crate::config::{Config, Kube, SchemaSource};
 
+mod cache;
 mod enums;
 mod error;
 mod spec;
 
+use cache::DiskCache;
 pub use enums::ApiGroup;
 pub use error::Error;
-use spec::GroupSpec;
-pub use spec::QueryPath;
+pub use spec::{AdditionalSchema, GroupSpec, QueryPath, SchemaRef, SchemaType};
+
+/// Where schemas are coming from.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SchemaStatus {
+    /// Fetched from the cluster
+    Online,
+    /// Read from the cache, as long as nothing was fetched yet or the cluster can not be reached
+    Cached,
+    /// Only the cache is read
+    #[default]
+    Offline,
+}
+
+impl SchemaStatus {
+    pub fn name(self) -> &'static str {
+        match self {
+            SchemaStatus::Online => "online",
+            SchemaStatus::Cached => "cached",
+            SchemaStatus::Offline => "offline",
+        }
+    }
+}
 
 pub struct ApiClient {
-    client: KubeClient,
-    response_cache: std::collections::HashMap<String, bytes::Bytes>,
+    // `None` when schemas are offline
+    client: Option<KubeClient>,
+    // Updated by each request to the cluster
+    status: Cell<SchemaStatus>,
+    response_cache: HashMap<String, bytes::Bytes>,
+    // Parsed group specs, these are large and used for every document of that group
+    group_specs: HashMap<String, Rc<GroupSpec>>,
+    disk_cache: Option<DiskCache>,
 }
 
 pub async fn from_config(config: &Config) -> Result<ApiClient, Error> {
-    let kube_config_options = KubeConfigOptions {
-        context: Some(config.context.clone()),
-        ..KubeConfigOptions::default()
+    let kube = config.kube.as_ref().ok_or(Error::NoContext)?;
+
+    // Each context gets its own cache, clusters can serve different schemas
+    let disk_cache = match &config.schema.cache_dir {
+        Some(dir) if config.schema.cache => Some(DiskCache::new(
+            dir.join(cache_dir_name(&kube.context)),
+            Duration::from_secs(config.schema.cache_ttl),
+        )),
+        _ => None,
     };
 
-    let kube_config =
-        KubeConfig::from_custom_kubeconfig(config.kube_config.clone(), &kube_config_options)
-            .await?;
+    let client = match config.schema.source {
+        SchemaSource::Offline => None,
+        SchemaSource::Cluster => Some(kube_client(kube).await?),
+    };
 
-    let client = KubeClient::try_from(kube_config)?;
+    // Online once a request to the cluster succeeded, fresh disk cache entries need none
+    let status = match client {
+        Some(_) => SchemaStatus::Cached,
+        None => SchemaStatus::Offline,
+    };
 
     Ok(ApiClient {
         client,
-        response_cache: std::collections::HashMap::new(),
+        status: Cell::new(status),
+        response_cache: HashMap::new(),
+        group_specs: HashMap::new(),
+        disk_cache,
     })
 }
 
+fn cache_dir_name(context: &str) -> String {
+    context
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+async fn kube_client(kube: &Kube) -> Result<KubeClient, Error> {
+    let kube_config_options = KubeConfigOptions {
+        context: Some(kube.context.clone()),
+        ..KubeConfigOptions::default()
+    };
+
+    let kube_config =
+        KubeConfig::from_custom_kubeconfig(kube.config.clone(), &kube_config_options).await?;
+
+    Ok(KubeClient::try_from(kube_config)?)
+}
+
 impl ApiClient {
-    pub async fn get_group_spec(&mut self, group: &ApiGroup) -> Result<GroupSpec, Error> {
+    pub fn schema_status(&self) -> SchemaStatus {
+        self.status.get()
+    }
+
+    pub async fn get_group_spec(&mut self, group: &ApiGroup) -> Result<Rc<GroupSpec>, Error> {
+        if let Some(spec) = self.group_specs.get(&group.to_string()) {
+            return Ok(spec.clone());
+        }
         debug!("Getting spec for {group}");
 
         let root_spec: spec::RootSpec = self.get_root_spec().await?;
@@ -55,34 +134,108 @@ impl ApiClient {
         let response = self.get_cached(group_spec_uri).await?;
         let openapi: OpenAPI = serde_json::from_slice(response)?;
 
-        Ok(GroupSpec::new(group.clone(), openapi))
+        let spec = Rc::new(GroupSpec::new(group.clone(), openapi));
+        self.group_specs.insert(group.to_string(), spec.clone());
+        Ok(spec)
+    }
+
+    /// Find the group that serves `kind`, for when no `apiVersion` is known. Core is searched
+    /// first, then the other groups by name with the version discovery prefers first. Groups
+    /// whose spec can not be had, e.g. uncached ones when offline, are skipped.
+    pub async fn find_kind(&mut self, kind: &str) -> Result<Rc<GroupSpec>, Error> {
+        let preferred = self.preferred_versions().await;
+        for group in self.get_root_spec().await?.groups(&preferred) {
+            let spec = match self.get_group_spec(&group).await {
+                Ok(spec) => spec,
+                Err(e) => {
+                    debug!("Skipping {group} looking for {kind}: {e}");
+                    continue;
+                }
+            };
+            if spec.get_kind(kind).is_ok() {
+                return Ok(spec);
+            }
+        }
+
+        Err(Error::SpecNotFound(kind.to_string()))
+    }
+
+    // Without discovery, e.g. offline before it was cached, versions are ranked by their names
+    async fn preferred_versions(&mut self) -> HashMap<String, String> {
+        let core = self
+            .get_cached("/api")
+            .await
+            .and_then(spec::CoreVersions::try_from);
+        let core = core.inspect_err(|e| debug!("No core discovery: {e}")).ok();
+        let groups = self
+            .get_cached("/apis")
+            .await
+            .and_then(spec::GroupList::try_from);
+        let groups = groups
+            .inspect_err(|e| debug!("No group discovery: {e}"))
+            .ok();
+        spec::preferred_versions(core, groups)
     }
 
     async fn get_root_spec(&mut self) -> Result<spec::RootSpec, Error> {
         let response = self.get_cached("/openapi/v3").await?;
-        Ok(response.into())
+        response.try_into()
     }
 
     async fn get_cached(&mut self, uri: &str) -> Result<&bytes::Bytes, Error> {
-        if let std::collections::hash_map::Entry::Vacant(entry) =
-            self.response_cache.entry(uri.to_string())
-        {
-            let request = Request::builder()
-                .method("GET")
-                .uri(uri)
-                .body(KubeBody::empty())?;
-
-            let bytes = self
-                .client
-                .send(request)
-                .await?
-                .into_body()
-                .collect_bytes()
-                .await?;
-
-            entry.insert(bytes);
+        if !self.response_cache.contains_key(uri) {
+            let bytes = self.fetch(uri).await?;
+            self.response_cache.insert(uri.to_string(), bytes);
         }
 
         Ok(self.response_cache.get(uri).unwrap())
     }
+
+    // Resolve a response from the disk cache or the cluster. A stale cache entry is still used
+    // when the cluster can not be reached.
+    async fn fetch(&self, uri: &str) -> Result<bytes::Bytes, Error> {
+        let Some(client) = &self.client else {
+            return self
+                .disk_cache
+                .as_ref()
+                .and_then(|cache| cache.get(uri, true))
+                .ok_or(Error::NotCached(uri.to_string()));
+        };
+
+        if let Some(bytes) = self.disk_cache.as_ref().and_then(|c| c.get(uri, false)) {
+            debug!("Using cached response for {uri}");
+            return Ok(bytes);
+        }
+
+        let request = Request::builder()
+            .method("GET")
+            .uri(uri)
+            .body(KubeBody::empty())?;
+
+        let response = match client.send(request).await {
+            Ok(response) => response,
+            Err(e) => {
+                if let Some(bytes) = self.disk_cache.as_ref().and_then(|c| c.get(uri, true)) {
+                    warn!("Request for {uri} failed, using stale cache: {e}");
+                    self.status.set(SchemaStatus::Cached);
+                    return Ok(bytes);
+                }
+                Err(e)?
+            }
+        };
+
+        // Error responses are not cached, they would be served until the entry expires
+        if !response.status().is_success() {
+            return Err(Error::RequestFailed(uri.to_string(), response.status()));
+        }
+
+        let bytes = response.into_body().collect_bytes().await?;
+        self.status.set(SchemaStatus::Online);
+
+        if let Some(cache) = &self.disk_cache {
+            cache.put(uri, &bytes);
+        }
+
+        Ok(bytes)
+    }
 }
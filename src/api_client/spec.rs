@@ -1,6 +1,8 @@
+use indexmap::IndexMap;
 use log::debug;
-use openapiv3::OpenAPI;
+use openapiv3::{AdditionalProperties, OpenAPI, ReferenceOr, Schema, SchemaKind, Type};
 use serde::Deserialize;
+use std::borrow::Borrow;
 use std::collections::HashMap;
 
 use super::{ApiGroup, Error};
@@ -16,13 +18,100 @@ pub struct RootSpec {
     paths: HashMap<String, PathSpec>,
 }
 
-impl From<&bytes::Bytes> for RootSpec {
-    fn from(b: &bytes::Bytes) -> Self {
-        serde_json::from_slice(b).unwrap()
+impl TryFrom<&bytes::Bytes> for RootSpec {
+    type Error = Error;
+
+    fn try_from(b: &bytes::Bytes) -> Result<Self, Self::Error> {
+        Ok(serde_json::from_slice(b)?)
+    }
+}
+
+/// Groups served by the cluster, from `/apis` discovery.
+#[derive(Deserialize, Debug)]
+pub struct GroupList {
+    groups: Vec<GroupDiscovery>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GroupDiscovery {
+    name: String,
+    preferred_version: Option<GroupVersion>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GroupVersion {
+    version: String,
+}
+
+/// Versions of the core group, from `/api` discovery. The first one is preferred.
+#[derive(Deserialize, Debug)]
+pub struct CoreVersions {
+    versions: Vec<String>,
+}
+
+impl TryFrom<&bytes::Bytes> for GroupList {
+    type Error = Error;
+
+    fn try_from(b: &bytes::Bytes) -> Result<Self, Self::Error> {
+        Ok(serde_json::from_slice(b)?)
+    }
+}
+
+impl TryFrom<&bytes::Bytes> for CoreVersions {
+    type Error = Error;
+
+    fn try_from(b: &bytes::Bytes) -> Result<Self, Self::Error> {
+        Ok(serde_json::from_slice(b)?)
     }
 }
 
+/// Preferred version of each group by name, core being `""`.
+pub fn preferred_versions(
+    core: Option<CoreVersions>,
+    groups: Option<GroupList>,
+) -> HashMap<String, String> {
+    let core = core
+        .and_then(|core| core.versions.into_iter().next())
+        .map(|version| (String::new(), version));
+    let groups = groups
+        .into_iter()
+        .flat_map(|list| list.groups)
+        .filter_map(|group| {
+            let version = group.preferred_version?.version;
+            Some((group.name, version))
+        });
+    core.into_iter().chain(groups).collect()
+}
+
 impl RootSpec {
+    /// Groups that have a spec, core first, then by name. Versions of a group come in the order
+    /// the cluster prefers them: the version in `preferred` first, then the highest stable one.
+    pub fn groups(&self, preferred: &HashMap<String, String>) -> Vec<ApiGroup> {
+        let mut groups: Vec<ApiGroup> = self
+            .paths
+            .keys()
+            .filter_map(|path| match path.split('/').collect::<Vec<_>>()[..] {
+                ["api", version] => Some(ApiGroup::Core(version.to_string())),
+                ["apis", group, version] => {
+                    Some(ApiGroup::Named(group.to_string(), version.to_string()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        let is_preferred =
+            |g: &ApiGroup| preferred.get(g.group()).map(String::as_str) == Some(g.version());
+        groups.sort_by(|a, b| {
+            let core = |g: &ApiGroup| !matches!(g, ApiGroup::Core(_));
+            (core(a), a.group())
+                .cmp(&(core(b), b.group()))
+                .then_with(|| is_preferred(b).cmp(&is_preferred(a)))
+                .then_with(|| b.version_priority().cmp(&a.version_priority()))
+        });
+        groups
+    }
+
     pub fn get_group_path(&self, group: &ApiGroup) -> Option<&str> {
         debug!("Getting path for group: {group}");
         let path = match group {
@@ -70,6 +159,274 @@ impl QueryPath {
             child: Some(Box::new(self)),
         }
     }
+
+    /// Parse a dotted path such as `spec.template.spec`. Returns `None` for an empty path.
+    pub fn parse(path: &str) -> Option<Self> {
+        path.split('.').filter(|key| !key.is_empty()).rev().fold(
+            None,
+            |child: Option<QueryPath>, key| match child {
+                Some(child) => Some(child.with_parent(key)),
+                None => Some(QueryPath::new(key)),
+            },
+        )
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        std::iter::successors(Some(self), |p| p.child.as_deref()).map(|p| p.key.as_str())
+    }
+}
+
+type Properties<'a> = &'a IndexMap<String, ReferenceOr<Box<Schema>>>;
+
+/// The shape of a schema, as far as YAML values are concerned.
+#[derive(Debug)]
+pub enum SchemaType<'a> {
+    Object,
+    Array,
+    String(Vec<String>),
+    Integer,
+    Number,
+    Boolean,
+    IntOrString,
+    /// Resource quantities are strings, but numbers are accepted as well
+    Quantity,
+    /// Anything goes, e.g. `x-kubernetes-preserve-unknown-fields` without a type
+    Any,
+    /// Valid if any of the alternatives is valid
+    OneOf(Vec<SchemaRef<'a>>),
+}
+
+/// A schema with `$ref`s and single entry `allOf` wrappers followed.
+#[derive(Debug, Clone, Copy)]
+pub struct SchemaRef<'a> {
+    spec: &'a GroupSpec,
+    schema: &'a Schema,
+    /// Component name of the last followed reference, e.g. `io.k8s.api.core.v1.Container`
+    name: Option<&'a str>,
+    /// Description of the referencing schema, which takes precedence over the referenced one
+    description: Option<&'a str>,
+}
+
+impl<'a> SchemaRef<'a> {
+    fn new(spec: &'a GroupSpec, schema: &'a Schema, name: Option<&'a str>) -> Option<Self> {
+        let description = schema.schema_data.description.as_deref();
+        match &schema.schema_kind {
+            SchemaKind::AllOf { all_of } if all_of.len() == 1 => {
+                let inner = spec.resolve(&all_of[0])?;
+                Some(SchemaRef {
+                    description: description.or(inner.description),
+                    ..inner
+                })
+            }
+            _ => Some(SchemaRef {
+                spec,
+                schema,
+                name,
+                description,
+            }),
+        }
+    }
+
+    pub fn description(&self) -> Option<&'a str> {
+        self.description
+    }
+
+    /// Component name of the schema, e.g. `io.k8s.api.core.v1.Container`.
+    pub fn name(&self) -> Option<&'a str> {
+        self.name
+    }
+
+    fn extension(&self, name: &str) -> bool {
+        self.schema
+            .schema_data
+            .extensions
+            .get(name)
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false)
+    }
+
+    /// Unknown fields are allowed, e.g. for embedded resources and CRD fields without a schema.
+    pub fn preserves_unknown_fields(&self) -> bool {
+        self.extension("x-kubernetes-preserve-unknown-fields")
+    }
+
+    pub fn schema_type(&self) -> SchemaType<'a> {
+        if self.extension("x-kubernetes-int-or-string") {
+            return SchemaType::IntOrString;
+        }
+        if self.name.is_some_and(|n| n.ends_with(".resource.Quantity")) {
+            return SchemaType::Quantity;
+        }
+
+        let alternatives = |schemas: &'a [ReferenceOr<Schema>]| {
+            SchemaType::OneOf(
+                schemas
+                    .iter()
+                    .filter_map(|s| self.spec.resolve(s))
+                    .collect(),
+            )
+        };
+
+        match &self.schema.schema_kind {
+            SchemaKind::Type(Type::Object(_)) => SchemaType::Object,
+            SchemaKind::Type(Type::Array(_)) => SchemaType::Array,
+            SchemaKind::Type(Type::String(s)) => {
+                SchemaType::String(s.enumeration.iter().flatten().cloned().collect())
+            }
+            SchemaKind::Type(Type::Integer(_)) => SchemaType::Integer,
+            SchemaKind::Type(Type::Number(_)) => SchemaType::Number,
+            SchemaKind::Type(Type::Boolean(_)) => SchemaType::Boolean,
+            SchemaKind::OneOf { one_of: s } | SchemaKind::AnyOf { any_of: s } => alternatives(s),
+            SchemaKind::Any(any) => match any.typ.as_deref() {
+                Some("object") => SchemaType::Object,
+                Some("array") => SchemaType::Array,
+                Some("string") => SchemaType::String(
+                    any.enumeration
+                        .iter()
+                        .filter_map(|v| v.as_str().map(str::to_string))
+                        .collect(),
+                ),
+                Some("integer") => SchemaType::Integer,
+                Some("number") => SchemaType::Number,
+                Some("boolean") => SchemaType::Boolean,
+                _ if !any.properties.is_empty() => SchemaType::Object,
+                _ if any.items.is_some() => SchemaType::Array,
+                _ => SchemaType::Any,
+            },
+            SchemaKind::AllOf { .. } | SchemaKind::Not { .. } => SchemaType::Any,
+        }
+    }
+
+    // Object details are either on an object type or an untyped schema
+    fn object_parts(
+        &self,
+    ) -> Option<(
+        Properties<'a>,
+        &'a [String],
+        Option<&'a AdditionalProperties>,
+    )> {
+        match &self.schema.schema_kind {
+            SchemaKind::Type(Type::Object(o)) => {
+                Some((&o.properties, &o.required, o.additional_properties.as_ref()))
+            }
+            SchemaKind::Any(any) => Some((
+                &any.properties,
+                &any.required,
+                any.additional_properties.as_ref(),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Properties of an object schema, in schema order.
+    pub fn properties(&self) -> Vec<(&'a str, SchemaRef<'a>)> {
+        self.object_parts()
+            .map(|(properties, _, _)| {
+                properties
+                    .iter()
+                    .filter_map(|(name, schema)| {
+                        self.spec.resolve(schema).map(|s| (name.as_str(), s))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn property(&self, name: &str) -> Option<SchemaRef<'a>> {
+        let (properties, _, _) = self.object_parts()?;
+        self.spec.resolve(properties.get(name)?)
+    }
+
+    pub fn required(&self) -> &'a [String] {
+        self.object_parts().map_or(&[], |(_, required, _)| required)
+    }
+
+    /// Schema for keys that are not listed as properties. `None` means no other keys are allowed.
+    pub fn additional_properties(&self) -> Option<AdditionalSchema<'a>> {
+        match self.object_parts()?.2? {
+            AdditionalProperties::Any(true) => Some(AdditionalSchema::Any),
+            AdditionalProperties::Any(false) => None,
+            AdditionalProperties::Schema(schema) => self
+                .spec
+                .resolve(schema.as_ref())
+                .map(AdditionalSchema::Schema),
+        }
+    }
+
+    /// Item schema of an array schema.
+    pub fn items(&self) -> Option<SchemaRef<'a>> {
+        match &self.schema.schema_kind {
+            SchemaKind::Type(Type::Array(a)) => self.spec.resolve(a.items.as_ref()?),
+            SchemaKind::Any(any) => self.spec.resolve(any.items.as_ref()?),
+            _ => None,
+        }
+    }
+
+    /// Short type description in the style of `kubectl explain`, e.g. `[]Container`,
+    /// `map[string]string` or `Object`.
+    pub fn type_name(&self) -> String {
+        let short_name = |s: &SchemaRef<'a>| s.name.map(|n| n.rsplit('.').next().unwrap_or(n));
+
+        match self.schema_type() {
+            SchemaType::Object => match (short_name(self), self.additional_properties()) {
+                (Some(name), _) => name.to_string(),
+                (None, Some(AdditionalSchema::Schema(values))) => {
+                    format!("map[string]{}", values.type_name())
+                }
+                (None, _) => "Object".to_string(),
+            },
+            SchemaType::Array => format!(
+                "[]{}",
+                self.items().map_or("Object".to_string(), |i| i.type_name())
+            ),
+            SchemaType::String(_) => "string".to_string(),
+            SchemaType::Integer => "integer".to_string(),
+            SchemaType::Number => "number".to_string(),
+            SchemaType::Boolean => "boolean".to_string(),
+            SchemaType::IntOrString => "IntOrString".to_string(),
+            SchemaType::Quantity => "Quantity".to_string(),
+            SchemaType::Any | SchemaType::OneOf(_) => "Object".to_string(),
+        }
+    }
+
+    /// Step into the schema of `key`, looking through arrays to their items.
+    pub fn child(&self, key: &str) -> Option<SchemaRef<'a>> {
+        let schema = match self.schema_type() {
+            SchemaType::Array => self.items()?,
+            _ => *self,
+        };
+        schema
+            .property(key)
+            .or_else(|| match schema.additional_properties() {
+                Some(AdditionalSchema::Schema(values)) => Some(values),
+                _ => None,
+            })
+    }
+}
+
+// `kubectl` style kind matching: `Deployment`, `deployment` and `deployments` are all accepted
+fn kind_matches(kind: &str, query: &str) -> bool {
+    if kind.eq_ignore_ascii_case(query) {
+        return true;
+    }
+
+    let kind = kind.to_ascii_lowercase();
+    let plural = if kind.ends_with(['s', 'x']) || kind.ends_with("ch") || kind.ends_with("sh") {
+        format!("{kind}es")
+    } else if let Some(stem) = kind.strip_suffix('y')
+        && !stem.ends_with(['a', 'e', 'i', 'o', 'u'])
+    {
+        format!("{stem}ies")
+    } else {
+        format!("{kind}s")
+    };
+    plural.eq_ignore_ascii_case(query)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum AdditionalSchema<'a> {
+    Any,
+    Schema(SchemaRef<'a>),
 }
 
 #[derive(Debug)]
@@ -82,35 +439,98 @@ impl GroupSpec {
     pub fn new(group: ApiGroup, openapi: OpenAPI) -> Self {
         GroupSpec { group, openapi }
     }
-}
 
-impl GroupSpec {
-    pub fn get_kind_path(
-        &self,
-        kind: &str,
-        path: &QueryPath,
-    ) -> Result<openapiv3::ReferenceOr<openapiv3::Schema>, Error> {
-        debug!("Getting spec for kind {kind} at path {path}");
+    pub fn group(&self) -> &ApiGroup {
+        &self.group
+    }
 
-        let spec_name = format!("{}.{kind}", self.group.to_kube_group());
-        let schemas = self
+    fn schemas(&self) -> Result<&IndexMap<String, ReferenceOr<Schema>>, Error> {
+        Ok(&self
             .openapi
             .components
             .as_ref()
             .ok_or(Error::InvalidComponentsTree)?
-            .schemas
-            .get(&spec_name)
-            .ok_or(Error::SpecNotFound(spec_name))?;
+            .schemas)
+    }
 
-        // TODO: Need to walk the schema based on the query path
+    fn resolve<'a, T: Borrow<Schema>>(
+        &'a self,
+        schema: &'a ReferenceOr<T>,
+    ) -> Option<SchemaRef<'a>> {
+        match schema {
+            ReferenceOr::Item(schema) => SchemaRef::new(self, schema.borrow(), None),
+            ReferenceOr::Reference { reference } => {
+                let name = reference.strip_prefix("#/components/schemas/")?;
+                match self.schemas().ok()?.get_key_value(name)? {
+                    (name, ReferenceOr::Item(schema)) => SchemaRef::new(self, schema, Some(name)),
+                    (_, reference) => self.resolve(reference),
+                }
+            }
+        }
+    }
 
-        Ok(schemas.clone())
+    // The kind of a schema if it belongs to this group and version
+    fn group_version_kind<'a>(&self, schema: &'a Schema) -> Option<&'a str> {
+        schema
+            .schema_data
+            .extensions
+            .get("x-kubernetes-group-version-kind")?
+            .as_array()?
+            .iter()
+            .find(|gvk| {
+                gvk["group"].as_str() == Some(self.group.group())
+                    && gvk["version"].as_str() == Some(self.group.version())
+            })?["kind"]
+            .as_str()
     }
 
-    #[allow(clippy::unused_self)]
-    fn _walk_path(&self, _schema: openapiv3::Schema, _path: QueryPath) -> String {
-        // TODO: Recurse through the schema to deliver some options
-        // Return type should be Vec<SelectOption>
-        "testing".to_string()
+    // The declared kind name and its schema, for the first kind `matches` accepts
+    fn find_kind(
+        &self,
+        kind: &str,
+        matches: impl Fn(&str) -> bool,
+    ) -> Result<(&str, SchemaRef<'_>), Error> {
+        let schemas = self.schemas()?;
+        let found = schemas.iter().find_map(|(name, schema)| match schema {
+            ReferenceOr::Item(item) => self
+                .group_version_kind(item)
+                .filter(|k| matches(k))
+                .and_then(|k| SchemaRef::new(self, item, Some(name)).map(|s| (k, s))),
+            ReferenceOr::Reference { .. } => None,
+        });
+
+        found.ok_or(Error::SpecNotFound(format!("{}/{kind}", self.group)))
+    }
+
+    /// Root schema of a kind typed by a user. Kinds are matched case insensitively and may be
+    /// plural, e.g. `deployments` finds `Deployment`.
+    pub fn get_kind(&self, kind: &str) -> Result<SchemaRef<'_>, Error> {
+        debug!("Getting spec for kind {kind}");
+        self.find_kind(kind, |k| kind_matches(k, kind))
+            .map(|(_, schema)| schema)
+    }
+
+    /// Root schema of the `kind` of a manifest, which the API server only accepts as declared.
+    pub fn get_kind_exact(&self, kind: &str) -> Result<SchemaRef<'_>, Error> {
+        debug!("Getting spec for kind {kind}");
+        self.find_kind(kind, |k| k == kind)
+            .map(|(_, schema)| schema)
+    }
+
+    /// The kind as declared by the schema, e.g. `Deployment` for `deployments`.
+    pub fn kind_name(&self, kind: &str) -> Result<&str, Error> {
+        self.find_kind(kind, |k| kind_matches(k, kind))
+            .map(|(name, _)| name)
+    }
+
+    /// Schema at `path` below a kind, e.g. `spec.template` of a `Deployment`.
+    pub fn get_kind_path(&self, kind: &str, path: &QueryPath) -> Result<SchemaRef<'_>, Error> {
+        debug!("Getting spec for kind {kind} at path {path}");
+
+        path.keys().try_fold(self.get_kind(kind)?, |schema, key| {
+            schema
+                .child(key)
+                .ok_or(Error::PathNotFound(format!("{kind}.{path}")))
+        })
     }
 }
@@ -1,22 +1,126 @@
-use log::{debug, info};
+use log::{debug, error, info, warn};
+use ratatui::crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+};
 use ratatui::{backend::Backend, DefaultTerminal, Frame, Terminal};
 use std::cell::RefCell;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::time::Instant;
 use tokio::time::{sleep, Duration};
 
-use crate::api_client::ApiClient;
+use crate::api_client::{ApiClient, QueryPath, SchemaStatus};
+use crate::config::{Config, ValidationConfig};
 
-use super::{components, event::handle_event, AppComponent, AppError, AppEvent, AppMode, File};
+use super::event::Pending;
+use super::file::Fragment;
+use super::keymap::Keymap;
+use super::message::{self, Message, Severity};
+use super::theme::Theme;
+use super::watcher::Watcher;
+use super::{
+    components, event::handle_event, AppComponent, AppError, AppEvent, AppMode, Buffer, File,
+    FileError, MouseAction,
+};
 
 pub type AppState = Rc<RefCell<State>>;
 
 #[derive(Default, Debug)]
 pub struct State {
     initialized: bool,
-    dirty: bool,
+    /// The screen needs drawing again
+    redraw: bool,
     quitting: bool,
-    pub file: Option<File>,
+    pub buffers: Vec<Buffer>,
+    /// Index of the buffer being edited
+    pub current: usize,
+    pub theme: Theme,
+    /// Kubernetes context and its namespace
+    pub context: String,
+    pub namespace: String,
+    pub schemas: SchemaStatus,
+    /// Yanked text by register name, `"` is the default register
+    pub registers: HashMap<char, Fragment>,
+    /// Notifications so far, the last one is shown on the message line for a while
+    pub messages: Vec<Message>,
+    /// When the message on the message line was sent, as of the last draw
+    drawn_toast: Option<Instant>,
+}
+
+impl State {
+    /// File of the buffer being edited.
+    pub fn file(&self) -> Option<&File> {
+        self.file_at(self.current)
+    }
+
+    pub fn file_mut(&mut self) -> Option<&mut File> {
+        self.file_at_mut(self.current)
+    }
+
+    /// File of the buffer at `index`, which split panes may show without it being edited.
+    pub fn file_at(&self, index: usize) -> Option<&File> {
+        self.buffers.get(index).map(|b| &b.file)
+    }
+
+    pub fn file_at_mut(&mut self, index: usize) -> Option<&mut File> {
+        self.buffers.get_mut(index).map(|b| &mut b.file)
+    }
+
+    /// Open `file` in a new buffer and edit it.
+    pub fn open(&mut self, file: File) {
+        self.buffers.push(Buffer::new(file));
+        self.current = self.buffers.len() - 1;
+    }
+
+    /// Show `text` on the message line and keep it for `:messages`. It is logged too.
+    pub fn notify(&mut self, severity: Severity, text: impl Into<String>) {
+        let text = text.into();
+        match severity {
+            Severity::Info => info!("{text}"),
+            Severity::Warning => warn!("{text}"),
+            Severity::Error => error!("{text}"),
+        }
+        if self.messages.len() == message::HISTORY {
+            self.messages.remove(0);
+        }
+        self.messages.push(Message::new(severity, text));
+        self.redraw = true;
+    }
+
+    /// Message on the message line, the last one while it is recent.
+    pub fn toast(&self) -> Option<&Message> {
+        self.messages.last().filter(|m| m.is_toast())
+    }
+
+    /// Number of buffers with changes that were not written.
+    pub fn modified_buffers(&self) -> usize {
+        self.buffers.iter().filter(|b| b.file.is_modified()).count()
+    }
+
+    /// Edit the buffer after the current one, or the one before when `reverse`. Wraps around.
+    pub fn cycle_buffer(&mut self, reverse: bool) {
+        let count = self.buffers.len().max(1);
+        self.current = if reverse {
+            (self.current + count - 1) % count
+        } else {
+            (self.current + 1) % count
+        };
+    }
+
+    /// Index of the buffer numbered `name` in the buffer list, or else the one whose path ends
+    /// with `name`.
+    pub fn find_buffer(&self, name: &str) -> Option<usize> {
+        if let Ok(number) = name.parse::<usize>() {
+            return (1..=self.buffers.len())
+                .contains(&number)
+                .then(|| number - 1);
+        }
+        self.buffers
+            .iter()
+            .position(|b| b.file.path().to_string_lossy().ends_with(name))
+    }
 }
 
 pub struct App {
@@ -24,36 +128,79 @@ pub struct App {
     state: AppState,
     components: components::Components,
     mode: AppMode,
+    keymap: Rc<Keymap>,
+    pending_keys: Pending,
+    validation: ValidationConfig,
+    /// Capture the mouse while running
+    mouse: bool,
+    /// Files of the open buffers, `None` when they cannot be watched
+    watcher: Option<Watcher>,
 }
 
 impl App {
-    pub fn new(api_client: ApiClient) -> Self {
+    pub fn new(api_client: ApiClient, config: &Config) -> Result<Self, AppError> {
+        let theme =
+            Theme::from_name(&config.theme).ok_or(AppError::UnknownTheme(config.theme.clone()))?;
+
+        let context = config.kube.as_ref().map(|kube| kube.context.clone());
+        let namespace = config
+            .kube
+            .as_ref()
+            .and_then(|kube| kube.config.contexts.iter().find(|c| c.name == kube.context))
+            .and_then(|c| c.context.as_ref()?.namespace.clone())
+            .unwrap_or_else(|| "default".to_string());
+
         let state = Rc::new(RefCell::new(State {
-            dirty: true,
+            redraw: true,
+            theme,
+            context: context.unwrap_or_default(),
+            namespace,
+            schemas: api_client.schema_status(),
             ..State::default()
         }));
 
-        let components = components::Components::new(state.clone());
+        let keymap = Rc::new(Keymap::from_config(&config.keymap, &config.editor)?);
+
+        let components = components::Components::new(state.clone(), keymap.clone(), config);
 
-        App {
+        let watcher = Watcher::new()
+            .inspect_err(|e| {
+                state.borrow_mut().notify(
+                    Severity::Warning,
+                    format!("Changes to open files will not be noticed: {e}"),
+                );
+            })
+            .ok();
+
+        Ok(App {
             api_client,
             state,
             mode: AppMode::Normal,
             components,
-        }
+            keymap,
+            pending_keys: Pending::default(),
+            validation: config.validation.clone(),
+            mouse: config.editor.mouse,
+            watcher,
+        })
     }
 
-    pub fn startup(&mut self, file: Option<PathBuf>) -> Result<DefaultTerminal, AppError> {
-        let mut state = self.state.borrow_mut();
-        if state.initialized {
+    pub fn startup(&mut self, files: Vec<File>) -> Result<DefaultTerminal, AppError> {
+        if self.state.borrow().initialized {
             return Err(AppError::AlreadyInitialized);
         }
-
-        if let Some(path) = file {
-            state.file = Some(File::from_path(path)?);
+        for file in &files {
+            self.watch(file.path());
         }
 
+        let mut state = self.state.borrow_mut();
+        state.buffers = files.into_iter().map(Buffer::new).collect();
+
         let terminal = ratatui::init();
+        if self.mouse {
+            execute!(std::io::stdout(), EnableMouseCapture)?;
+        }
+        set_panic_hook(self.mouse);
         state.initialized = true;
         Ok(terminal)
     }
@@ -66,10 +213,11 @@ impl App {
         loop {
             self.handle_event().await?;
 
-            // Needed to borrow state inside
-            let (quitting, dirty) = {
+            // Needed to borrow state inside. Messages go away from the message line on their own.
+            let (quitting, redraw) = {
                 let state = self.state.borrow();
-                (state.quitting, state.dirty)
+                let toast_gone = state.drawn_toast.is_some() && state.toast().is_none();
+                (state.quitting, state.redraw || toast_gone)
             };
 
             if quitting {
@@ -77,7 +225,7 @@ impl App {
                 break;
             }
 
-            if dirty {
+            if redraw {
                 terminal.draw(|frame| self.draw(frame))?;
             }
 
@@ -89,50 +237,231 @@ impl App {
 
     pub fn shutdown(&mut self) {
         self.state.borrow_mut().initialized = false;
+        if self.mouse
+            && let Err(e) = execute!(std::io::stdout(), DisableMouseCapture)
+        {
+            warn!("Could not release the mouse: {e}");
+        }
         ratatui::restore();
     }
 
-    fn load_file(&mut self) -> Result<(), AppError> {
+    fn watch(&mut self, path: &Path) {
+        if let Some(watcher) = &mut self.watcher
+            && let Err(e) = watcher.watch(path)
+        {
+            self.state.borrow_mut().notify(
+                Severity::Warning,
+                format!("Could not watch {}: {e}", path.display()),
+            );
+        }
+    }
+
+    // Buffers whose file another program changed since the last call
+    fn changed_files(&self) -> Vec<AppEvent> {
+        let Some(watcher) = &self.watcher else {
+            return vec![];
+        };
+        let state = self.state.borrow();
+        watcher
+            .changed()
+            .iter()
+            .filter_map(|changed| {
+                state.buffers.iter().position(|b| {
+                    std::path::absolute(b.file.path()).is_ok_and(|path| path == *changed)
+                })
+            })
+            .map(AppEvent::FileChanged)
+            .collect()
+    }
+
+    // Load the buffer at `index` from disk again when another program changed its file. Unless
+    // `force`, changes to the buffer are not thrown away without asking.
+    fn reload_file(&mut self, index: usize, force: bool) -> bool {
+        let changed = {
+            let state = self.state.borrow();
+            let Some(file) = state.file_at(index) else {
+                return false;
+            };
+            file.changed_on_disk()
+                .map(|disk| disk.map(|disk| (disk, file.text(), file.is_modified())))
+                .map_err(|e| format!("Could not read {}: {e}", file.path().display()))
+        };
+        let changed = match changed {
+            Ok(changed) => changed,
+            Err(e) => {
+                self.state.borrow_mut().notify(Severity::Error, e);
+                return true;
+            }
+        };
+        // Nothing to do when the file is what was last loaded or written, e.g. after `:w`
+        let Some((disk, text, modified)) = changed else {
+            return false;
+        };
+
+        let path = self.state.borrow().buffers[index].file.path().to_path_buf();
+        if modified && !force {
+            self.state.borrow_mut().current = index;
+            self.mode = AppMode::Command;
+            self.components.conflict(
+                index,
+                format!(
+                    "{} changed on disk, but the buffer has unsaved changes",
+                    path.display()
+                ),
+                &text,
+                &disk,
+            );
+            return true;
+        }
+
+        let message = match File::from_text(path, &disk) {
+            Ok(file) => {
+                let message = format!("Reloaded {}", file.path().display());
+                self.components.reload(index, file);
+                (Severity::Info, message)
+            }
+            Err(e) => (Severity::Error, format!("Could not reload file: {e}")),
+        };
+        self.state.borrow_mut().notify(message.0, message.1);
+        true
+    }
+
+    // Load the file into its buffer if it is already open, or else into a new one. Unless
+    // `force`, changes to the open buffer are not thrown away without asking.
+    fn load_file(&mut self, force: bool) -> Result<(), AppError> {
         let path = PathBuf::from("./examples/long.yaml");
-        self.state.borrow_mut().file = Some(File::from_path(path)?);
+        let mut state = self.state.borrow_mut();
+        let Some(index) = state.buffers.iter().position(|b| b.file.path() == path) else {
+            state.open(File::from_path(path.clone())?);
+            drop(state);
+            self.watch(&path);
+            return Ok(());
+        };
+
+        state.current = index;
+        if !force && state.buffers[index].file.is_modified() {
+            drop(state);
+            self.mode = AppMode::Command;
+            self.components.confirm(
+                components::Guard::Load,
+                format!("{} has unsaved changes", path.display()),
+            );
+        } else {
+            drop(state);
+            self.components.reload(index, File::from_path(path)?);
+        }
         Ok(())
     }
 
-    fn write_file(&self) {
+    fn write_file(&mut self, force: bool) {
+        let mut state = self.state.borrow_mut();
+        let index = state.current;
+        let Some(file) = state.file_mut() else {
+            return;
+        };
+        let path = file.path().display().to_string();
+        match file.write(force) {
+            Ok(()) => state.notify(Severity::Info, format!("Wrote {path}")),
+            // Asked like when the change is seen by the watcher, overwriting writes with force
+            Err(FileError::ChangedOnDisk(_)) => {
+                drop(state);
+                self.reload_file(index, false);
+            }
+            Err(e) => state.notify(Severity::Error, format!("Could not write {path}: {e}")),
+        }
+    }
+
+    fn write_all(&self) {
         let mut state = self.state.borrow_mut();
-        if let Some(file) = &mut state.file {
-            file.write();
+        let errors = state
+            .buffers
+            .iter_mut()
+            .filter(|b| b.file.is_modified())
+            .filter_map(|b| {
+                let e = b.file.write(false).err()?;
+                Some(format!("Could not write {}: {e}", b.file.path().display()))
+            })
+            .collect::<Vec<_>>();
+        for e in errors {
+            state.notify(Severity::Error, e);
         }
     }
 
     async fn handle_event(&mut self) -> std::io::Result<()> {
-        if let Some(event) = handle_event(&self.mode)? {
-            self.state.borrow_mut().dirty =
-                self.handle_app_events(&event).await || self.handle_component_events(&event);
+        let mut events = self.changed_files();
+        events.extend(handle_event(
+            &self.mode,
+            &self.keymap,
+            &mut self.pending_keys,
+        )?);
+        if !events.is_empty() {
+            let mut redraw = false;
+            // Components push events like quitting on `:q`, or the answer to a dialog
+            while !events.is_empty() {
+                for event in events {
+                    redraw |= self.handle_app_events(&event).await
+                        || self.handle_component_events(&event);
+                }
+                events = self.components.take_events();
+            }
+            // Leave command mode once nothing takes the keys, e.g. after picking a buffer
+            if self.mode == AppMode::Command && !self.components.is_prompting() {
+                self.mode = AppMode::Normal;
+            }
+
+            let mut state = self.state.borrow_mut();
+            state.redraw |= redraw;
+            state.schemas = self.api_client.schema_status();
         }
         Ok(())
     }
 
     async fn handle_app_events(&mut self, event: &AppEvent) -> bool {
         match event {
+            // Components see mode changes, e.g. to close the command line
             AppEvent::ChangeMode(m) => {
                 self.mode = m.clone();
+                self.handle_component_events(event);
                 true
             }
-            AppEvent::Load => {
+            AppEvent::Search { .. }
+            | AppEvent::CommandLine
+            | AppEvent::BufferList
+            | AppEvent::Help => {
+                self.mode = AppMode::Command;
+                self.handle_component_events(event);
+                true
+            }
+            // Commands like `:ls` open a picker and stay in command mode
+            AppEvent::Submit if self.mode == AppMode::Command => {
+                self.handle_component_events(event);
+                true
+            }
+            AppEvent::Operator(_) if self.mode == AppMode::Visual => {
+                self.handle_component_events(event);
+                self.mode = AppMode::Normal;
+                true
+            }
+            AppEvent::Mouse { action, .. } => self.handle_mouse(event, *action),
+            AppEvent::Load { force } => {
                 // TODO: This should load a modal, not the file
-                match self.load_file() {
-                    Ok(()) => {}
-                    Err(_e) => {
-                        todo!()
-                    }
+                if let Err(e) = self.load_file(*force) {
+                    self.state
+                        .borrow_mut()
+                        .notify(Severity::Error, format!("Could not load file: {e}"));
                 }
                 true
             }
-            AppEvent::Write => {
-                self.write_file();
+            AppEvent::Write { force } => {
+                self.write_file(*force);
                 true
             }
+            AppEvent::WriteAll => {
+                self.write_all();
+                true
+            }
+            AppEvent::FileChanged(index) => self.reload_file(*index, false),
+            AppEvent::Reload(index) => self.reload_file(*index, true),
             AppEvent::DumpDebug => {
                 info!("App State: {:#?}", self.state.borrow());
                 true
@@ -141,38 +470,142 @@ impl App {
             AppEvent::LoadSpec => {
                 // Load a core object spec
                 let group = "v1".into();
-                let spec = self.api_client.get_group_spec(&group).await.unwrap();
-                let path = crate::api_client::QueryPath::new("containers").with_parent("spec");
-                let opts = spec.get_kind_path("Pod", &path);
-                debug!("Spec: {opts:#?}");
+                let path = QueryPath::parse("spec.containers").expect("Path is not empty");
+                match self.api_client.get_group_spec(&group).await {
+                    Ok(spec) => debug!("Spec: {:#?}", spec.get_kind_path("Pod", &path)),
+                    Err(e) => self.state.borrow_mut().notify(
+                        Severity::Error,
+                        format!("Could not load spec for {group}: {e}"),
+                    ),
+                }
 
                 // Load a group object spec
                 let group = ("apps", "v1").into();
-                let spec = self.api_client.get_group_spec(&group).await.unwrap();
-                let path = crate::api_client::QueryPath::new("containers")
-                    .with_parent("spec")
-                    .with_parent("template")
-                    .with_parent("spec");
-                let opts = spec.get_kind_path("Deployment", &path);
-                debug!("Spec: {opts:#?}");
+                let path =
+                    QueryPath::parse("spec.template.spec.containers").expect("Path is not empty");
+                match self.api_client.get_group_spec(&group).await {
+                    Ok(spec) => debug!("Spec: {:#?}", spec.get_kind_path("Deployment", &path)),
+                    Err(e) => self.state.borrow_mut().notify(
+                        Severity::Error,
+                        format!("Could not load spec for {group}: {e}"),
+                    ),
+                }
 
                 true
             }
-            AppEvent::Exit => {
-                self.state.borrow_mut().quitting = true;
+            AppEvent::Validate => self.validate().await,
+            // Pasted text is checked against the schema of where it landed
+            AppEvent::Paste { .. } => {
+                self.handle_component_events(event);
+                if self.validate().await {
+                    self.notify_paste_problems();
+                }
+                true
+            }
+            AppEvent::Exit { force } => {
+                self.exit(*force);
                 true
             }
             _ => false,
         }
     }
 
+    // Quit, unless buffers have unsaved changes and it is not `force`d. Then ask first.
+    fn exit(&mut self, force: bool) {
+        let modified = self.state.borrow().modified_buffers();
+        if force || modified == 0 {
+            self.state.borrow_mut().quitting = true;
+        } else {
+            self.mode = AppMode::Command;
+            self.components.confirm(
+                components::Guard::Quit,
+                format!("{modified} buffer(s) have unsaved changes, save before quitting?"),
+            );
+        }
+    }
+
+    // Clicks can pick from a picker, which ends command mode. Double clicks select the node under
+    // the mouse.
+    fn handle_mouse(&mut self, event: &AppEvent, action: MouseAction) -> bool {
+        let handled = self.handle_component_events(event);
+        if handled && action == MouseAction::DoubleClick && self.mode == AppMode::Normal {
+            let visual = AppEvent::ChangeMode(AppMode::Visual);
+            self.mode = AppMode::Visual;
+            self.handle_component_events(&visual);
+        }
+        handled
+    }
+
+    // Validate the file and show the problems in the editor. Returns false without a file.
+    async fn validate(&mut self) -> bool {
+        let Some(file) = self.state.borrow().file().cloned() else {
+            return false;
+        };
+
+        let result = file.validate(&mut self.api_client, &self.validation).await;
+        let mut state = self.state.borrow_mut();
+        match result {
+            Ok(diagnostics) => {
+                let message = format!("Validation found {} problem(s)", diagnostics.len());
+                if let Some(file) = state.file_mut() {
+                    file.set_diagnostics(diagnostics);
+                }
+                state.notify(Severity::Info, message);
+            }
+            Err(e) => state.notify(Severity::Error, format!("Could not validate file: {e}")),
+        }
+        true
+    }
+
+    // Warn when the last paste brought problems along
+    fn notify_paste_problems(&self) {
+        let mut state = self.state.borrow_mut();
+        let problems = state.file().and_then(|file| {
+            let pasted = file.last_paste()?;
+            let diagnostics = file.diagnostics().iter();
+            Some(
+                diagnostics
+                    .filter(|d| pasted.contains_range(d.range))
+                    .count(),
+            )
+        });
+        if let Some(problems) = problems
+            && problems > 0
+        {
+            state.notify(
+                Severity::Warning,
+                format!("Pasted text has {problems} problem(s) where it landed"),
+            );
+        }
+    }
+
     fn handle_component_events(&mut self, event: &AppEvent) -> bool {
-        // TODO: Allow components to push events too
         self.components.handle_event(&self.mode, event)
     }
 
     fn draw(&mut self, frame: &mut Frame) {
         self.components.draw(&self.mode, frame, frame.area());
-        self.state.borrow_mut().dirty = false;
+        let mut state = self.state.borrow_mut();
+        state.redraw = false;
+        state.drawn_toast = state.toast().map(|m| m.time);
     }
 }
+
+// Give the terminal back before a panic is reported, or raw mode garbles the report and the
+// alternate screen hides it. Unsaved changes cannot be written from here.
+fn set_panic_hook(mouse: bool) {
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if mouse {
+            let _ = execute!(std::io::stdout(), DisableMouseCapture);
+        }
+        ratatui::restore();
+        error!("{info}");
+        eprintln!(
+            "m7s crashed and unsaved changes are lost, sorry! Please report it at {}/issues with \
+             the details below and the log.\n",
+            env!("CARGO_PKG_REPOSITORY")
+        );
+        hook(info);
+    }));
+}
@@ -0,0 +1,31 @@
+use super::File;
+
+/// A file open in the editor, along with where it was last viewed.
+#[derive(Debug)]
+pub struct Buffer {
+    pub file: File,
+    pub view: View,
+}
+
+impl Buffer {
+    pub fn new(file: File) -> Self {
+        Buffer {
+            file,
+            view: View::default(),
+        }
+    }
+
+    /// Label in buffer lists, `+` marks unsaved changes.
+    pub fn label(&self) -> String {
+        let modified = if self.file.is_modified() { " [+]" } else { "" };
+        format!("{}{modified}", self.file.path().display())
+    }
+}
+
+/// Cursor and scroll position of a view onto a buffer.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct View {
+    pub cursor: u32,
+    pub vertical_scroll: usize,
+    pub horizontal_scroll: usize,
+}
@@ -0,0 +1,10 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use std::io::{self, Write};
+
+/// Copy `text` to the system clipboard with the OSC 52 escape sequence. The terminal does the
+/// copying, so this also works over ssh and in tmux with `set-clipboard on`.
+pub fn copy(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()
+}
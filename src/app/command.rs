@@ -0,0 +1,161 @@
+use super::file::{Query, QueryError};
+
+/// A command typed after `:`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Move the cursor to the key at a path
+    Goto(Query),
+    /// Pick a buffer from the list of open ones
+    Buffers,
+    /// Edit the buffer with a number or path
+    Buffer(String),
+    NextBuffer,
+    PreviousBuffer,
+    /// Split the focused pane, the new one above the other or beside it when `vertical`. It
+    /// shows the buffer with a number or path, if given.
+    Split {
+        vertical: bool,
+        buffer: Option<String>,
+    },
+    /// Close the focused pane
+    Close,
+    /// Close every pane but the focused one
+    Only,
+    Write,
+    /// Quit, throwing away unsaved changes when `force`
+    Quit {
+        force: bool,
+    },
+    /// Write the buffer and quit
+    WriteQuit,
+    /// List the notifications shown so far
+    Messages,
+}
+
+/// Name, arguments and description of a command, for completion and help.
+#[derive(Debug, Clone, Copy)]
+pub struct CommandInfo {
+    pub name: &'static str,
+    pub args: &'static str,
+    pub description: &'static str,
+}
+
+pub const COMMANDS: [CommandInfo; 14] = [
+    CommandInfo {
+        name: "goto",
+        args: "PATH",
+        description: "move the cursor to a key, e.g. `spec.template.spec.containers[web].ports`",
+    },
+    CommandInfo {
+        name: "ls",
+        args: "",
+        description: "pick one of the open buffers",
+    },
+    CommandInfo {
+        name: "buffer",
+        args: "N|NAME",
+        description: "edit a buffer by its number in `:ls` or the end of its path",
+    },
+    CommandInfo {
+        name: "bnext",
+        args: "",
+        description: "edit the next buffer",
+    },
+    CommandInfo {
+        name: "bprevious",
+        args: "",
+        description: "edit the previous buffer",
+    },
+    CommandInfo {
+        name: "split",
+        args: "[N|NAME]",
+        description: "split the pane, showing the same or another buffer below",
+    },
+    CommandInfo {
+        name: "vsplit",
+        args: "[N|NAME]",
+        description: "split the pane, showing the same or another buffer to the right",
+    },
+    CommandInfo {
+        name: "close",
+        args: "",
+        description: "close the pane",
+    },
+    CommandInfo {
+        name: "only",
+        args: "",
+        description: "close every other pane",
+    },
+    CommandInfo {
+        name: "w",
+        args: "",
+        description: "write the buffer",
+    },
+    CommandInfo {
+        name: "q",
+        args: "",
+        description: "quit, asking first when there are unsaved changes",
+    },
+    CommandInfo {
+        name: "q!",
+        args: "",
+        description: "quit, throwing away unsaved changes",
+    },
+    CommandInfo {
+        name: "wq",
+        args: "",
+        description: "write the buffer and quit",
+    },
+    CommandInfo {
+        name: "messages",
+        args: "",
+        description: "list the errors, warnings and other messages shown so far",
+    },
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum CommandError {
+    #[error("Unknown command `{0}`")]
+    Unknown(String),
+    #[error("`{0}` expects {1}")]
+    MissingArgument(&'static str, &'static str),
+    #[error(transparent)]
+    InvalidPath(#[from] QueryError),
+}
+
+impl Command {
+    pub fn parse(text: &str) -> Result<Self, CommandError> {
+        let text = text.trim();
+        let (name, args) = text.split_once(' ').unwrap_or((text, ""));
+        let args = args.trim();
+
+        let info = COMMANDS
+            .iter()
+            .find(|info| info.name == name)
+            .ok_or_else(|| CommandError::Unknown(name.to_string()))?;
+        // Optional arguments are in brackets
+        if args.is_empty() && !info.args.is_empty() && !info.args.starts_with('[') {
+            return Err(CommandError::MissingArgument(info.name, info.args));
+        }
+
+        match info.name {
+            "goto" => Ok(Command::Goto(Query::parse(args)?)),
+            "ls" => Ok(Command::Buffers),
+            "buffer" => Ok(Command::Buffer(args.to_string())),
+            "bnext" => Ok(Command::NextBuffer),
+            "bprevious" => Ok(Command::PreviousBuffer),
+            "split" | "vsplit" => Ok(Command::Split {
+                vertical: info.name == "vsplit",
+                buffer: (!args.is_empty()).then(|| args.to_string()),
+            }),
+            "close" => Ok(Command::Close),
+            "only" => Ok(Command::Only),
+            "w" => Ok(Command::Write),
+            "q" => Ok(Command::Quit { force: false }),
+            "q!" => Ok(Command::Quit { force: true }),
+            "wq" => Ok(Command::WriteQuit),
+            "messages" => Ok(Command::Messages),
+            _ => unreachable!("Every command in COMMANDS is parsed"),
+        }
+    }
+}
@@ -1,28 +1,162 @@
 use ratatui::{
-    layout::Rect,
-    style::{Color, Stylize},
-    text::Line,
+    layout::{Constraint, Layout, Rect},
+    text::{Line, Span},
+    widgets::Block,
     Frame,
 };
 
-use crate::app::{AppComponent, AppMode, AppState};
+use crate::app::{AppComponent, AppMode, AppState, State};
+use crate::config::{AirlineConfig, AirlineSegment, Severity};
 
-#[derive(Default)]
-pub struct Airline {}
+pub struct Airline {
+    state: AppState,
+    config: AirlineConfig,
+    cursor: u32,
+}
 
 impl Airline {
-    pub fn new(_state: AppState) -> Self {
-        Self {}
+    pub fn new(state: AppState, config: AirlineConfig) -> Self {
+        Self {
+            state,
+            config,
+            cursor: 0,
+        }
+    }
+
+    /// Show the position and path of `cursor`.
+    pub fn follow(&mut self, cursor: u32) {
+        self.cursor = cursor;
+    }
+
+    // Spans of the segments, separated by a space. Segments with nothing to show are skipped.
+    fn segments(
+        &self,
+        state: &State,
+        mode: &AppMode,
+        segments: &[AirlineSegment],
+    ) -> Vec<Span<'static>> {
+        let mut spans: Vec<Span<'static>> = vec![];
+        for segment in segments {
+            let segment = self.segment(state, mode, *segment);
+            if !segment.is_empty() {
+                spans.push(" ".into());
+                spans.extend(segment);
+            }
+        }
+        spans
+    }
+
+    fn segment(
+        &self,
+        state: &State,
+        mode: &AppMode,
+        segment: AirlineSegment,
+    ) -> Vec<Span<'static>> {
+        let theme = &state.theme;
+        let file = state.file();
+
+        match segment {
+            AirlineSegment::Mode => {
+                vec![Span::styled(
+                    format!(" {} ", mode.display_text()),
+                    theme.airline_mode,
+                )]
+            }
+            AirlineSegment::File => vec![
+                file.map_or_else(
+                    || "[No file]".to_string(),
+                    |f| f.path().display().to_string(),
+                )
+                .into(),
+            ],
+            AirlineSegment::Modified => match file {
+                Some(file) if file.is_modified() => vec!["[+]".into()],
+                _ => vec![],
+            },
+            AirlineSegment::Resource => match file.and_then(|f| f.path_at(self.cursor)) {
+                Some((Some(details), _)) => vec![Span::styled(
+                    format!(" {} ", details.display_name()),
+                    theme.airline_mode,
+                )],
+                _ => vec![],
+            },
+            AirlineSegment::Path => match file.and_then(|f| f.path_at(self.cursor)) {
+                Some((_, path)) => vec![path.breadcrumb().into()],
+                None => vec![],
+            },
+            AirlineSegment::Search => match file.and_then(|f| f.search_status(self.cursor)) {
+                Some((pattern, Some(current), total)) => {
+                    vec![format!("/{pattern} [{current}/{total}]").into()]
+                }
+                Some((pattern, None, total)) => vec![format!("/{pattern} [{total}]").into()],
+                None => vec![],
+            },
+            AirlineSegment::Diagnostics => {
+                let diagnostics = file.map_or(&[][..], |f| f.diagnostics());
+                let mut spans = vec![];
+                for severity in [Severity::Error, Severity::Warning] {
+                    let count = diagnostics
+                        .iter()
+                        .filter(|d| d.severity == severity)
+                        .count();
+                    if count > 0 {
+                        if !spans.is_empty() {
+                            spans.push(" ".into());
+                        }
+                        spans.push(Span::styled(
+                            format!(
+                                " {count} {}{} ",
+                                severity.name(),
+                                if count == 1 { "" } else { "s" }
+                            ),
+                            theme.diagnostic(severity),
+                        ));
+                    }
+                }
+                spans
+            }
+            AirlineSegment::Context => vec![state.context.clone().into()],
+            AirlineSegment::Namespace => vec![format!("ns:{}", state.namespace).into()],
+            AirlineSegment::Schemas => vec![format!("schemas:{}", state.schemas.name()).into()],
+            AirlineSegment::Position => match file {
+                Some(file) => {
+                    let (line, column) = file.position(self.cursor);
+                    vec![format!("{}:{}", line + 1, column + 1).into()]
+                }
+                None => vec![],
+            },
+            AirlineSegment::Percent => match file {
+                Some(file) => {
+                    let (line, _) = file.position(self.cursor);
+                    let lines = file.text().lines().count().max(1);
+                    vec![format!("{}%", (line + 1).min(lines) * 100 / lines).into()]
+                }
+                None => vec![],
+            },
+        }
     }
 }
 
 impl AppComponent for Airline {
     fn draw(&mut self, mode: &AppMode, frame: &mut Frame, area: Rect) {
-        let airline_message = vec![
-            format!(" {} ", mode.display_text()).bold().bg(Color::Green),
-            " ".into(),
-            "File: example.yaml".fg(Color::Black),
-        ];
-        frame.render_widget(Line::from(airline_message).bg(Color::Indexed(54)), area);
+        let state = self.state.borrow();
+        let left = self.segments(&state, mode, &self.config.left);
+        let mut right = self.segments(&state, mode, &self.config.right);
+        right.push(" ".into());
+
+        // The left side starts at the edge, without the separating space
+        let left = left.into_iter().skip(1).collect::<Vec<_>>();
+
+        // The right side is kept whole, the left side is cut off before it
+        let right = Line::from(right);
+        let [left_area, right_area] = Layout::horizontal([
+            Constraint::Min(0),
+            Constraint::Length(u16::try_from(right.width()).unwrap_or(u16::MAX)),
+        ])
+        .areas(area);
+
+        frame.render_widget(Block::new().style(state.theme.airline), area);
+        frame.render_widget(Line::from(left), left_area);
+        frame.render_widget(right, right_area);
     }
 }
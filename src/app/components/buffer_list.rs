@@ -0,0 +1,152 @@
+use ratatui::{
+    crossterm::event::{KeyCode, KeyModifiers},
+    layout::{Constraint, Flex, Layout, Position, Rect},
+    style::{Modifier, Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::app::{AppComponent, AppEvent, AppMode, AppState, MouseAction};
+
+/// Picker of the open buffers, like `:ls`. Typing narrows the list down to buffers whose path
+/// contains the text.
+pub struct BufferList {
+    state: AppState,
+    pub visible: bool,
+    filter: String,
+    /// Index into the buffers that match the filter
+    selected: usize,
+    /// Where the buffers were last listed, for clicks
+    list_area: Rect,
+}
+
+impl BufferList {
+    pub fn new(state: AppState) -> Self {
+        Self {
+            state,
+            visible: false,
+            filter: String::new(),
+            selected: 0,
+            list_area: Rect::default(),
+        }
+    }
+
+    /// Show the picker with the current buffer selected.
+    pub fn open(&mut self) {
+        self.visible = true;
+        self.filter.clear();
+        self.selected = self.state.borrow().current;
+    }
+
+    pub fn close(&mut self) {
+        self.visible = false;
+    }
+
+    // Indices of the buffers whose path contains the filter
+    fn matches(&self) -> Vec<usize> {
+        self.state
+            .borrow()
+            .buffers
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.file.path().to_string_lossy().contains(&self.filter))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    fn pick(&mut self) {
+        if let Some(index) = self.matches().get(self.selected) {
+            self.state.borrow_mut().current = *index;
+        }
+        self.close();
+    }
+}
+
+impl AppComponent for BufferList {
+    fn draw(&mut self, _mode: &AppMode, frame: &mut Frame, area: Rect) {
+        let matches = self.matches();
+        let state = self.state.borrow();
+
+        let height = u16::try_from(matches.len() + 3).unwrap_or(u16::MAX);
+        let [area] = Layout::vertical([Constraint::Length(height)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [area] = Layout::horizontal([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        let mut lines = vec![Line::from(vec![
+            Span::from(format!("> {}", self.filter)),
+            Span::from(" ").reversed(),
+        ])];
+        lines.extend(matches.iter().enumerate().map(|(row, index)| {
+            // `%` marks the buffer being edited, as in vim
+            let marker = if *index == state.current { '%' } else { ' ' };
+            let line = Line::from(format!(
+                "{:>3} {marker} {}",
+                index + 1,
+                state.buffers[*index].label()
+            ));
+            if row == self.selected {
+                line.style(Style::default().add_modifier(Modifier::REVERSED))
+            } else {
+                line
+            }
+        }));
+
+        // Below the border and the filter
+        self.list_area = Rect {
+            y: area.y + 2,
+            height: area.height.saturating_sub(3),
+            ..area
+        };
+
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .border_style(state.theme.airline_mode)
+            .title(" Buffers ");
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(Text::from(lines)).block(block), area);
+    }
+
+    fn handle_event(&mut self, _mode: &AppMode, event: &AppEvent) -> bool {
+        if !self.visible {
+            return false;
+        }
+        match event {
+            AppEvent::Raw(key) => match key.code {
+                KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+                KeyCode::Down => {
+                    self.selected = (self.selected + 1).min(self.matches().len().saturating_sub(1));
+                }
+                KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.filter.push(c);
+                    self.selected = 0;
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                    self.selected = 0;
+                }
+                _ => return false,
+            },
+            AppEvent::Submit => self.pick(),
+            // Clicking a buffer picks it, clicking elsewhere closes the list
+            AppEvent::Mouse {
+                action: MouseAction::Click,
+                column,
+                row,
+            } => {
+                if self.list_area.contains(Position::new(*column, *row)) {
+                    self.selected = usize::from(row - self.list_area.y);
+                    self.pick();
+                } else {
+                    self.close();
+                }
+            }
+            AppEvent::ChangeMode(_) => self.close(),
+            _ => return false,
+        }
+        true
+    }
+}
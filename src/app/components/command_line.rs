@@ -0,0 +1,230 @@
+use ratatui::{
+    crossterm::event::{KeyCode, KeyModifiers},
+    layout::{Position, Rect},
+    style::Stylize,
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+
+use crate::app::command::COMMANDS;
+use crate::app::file::{split_last_segment, SearchScope};
+use crate::app::{AppComponent, AppEvent, AppMode, AppState, MouseAction};
+
+/// What the command line is being typed for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Prompt {
+    Search { backward: bool },
+    Command,
+}
+
+impl Prompt {
+    fn symbol(self) -> &'static str {
+        match self {
+            Prompt::Command => ":",
+            Prompt::Search { backward: false } => "/",
+            Prompt::Search { backward: true } => "?",
+        }
+    }
+}
+
+/// The line that text is typed into in command mode.
+pub struct CommandLine {
+    state: AppState,
+    prompt: Option<Prompt>,
+    text: String,
+    /// Kept between searches
+    scope: SearchScope,
+    /// Candidates of the last completion that did not settle on one
+    completions: Vec<String>,
+    /// Where each candidate was last drawn, for clicks
+    completion_areas: Vec<Rect>,
+}
+
+impl CommandLine {
+    pub fn new(state: AppState) -> Self {
+        Self {
+            state,
+            prompt: None,
+            text: String::new(),
+            scope: SearchScope::default(),
+            completions: vec![],
+            completion_areas: vec![],
+        }
+    }
+
+    pub fn open(&mut self, prompt: Prompt) {
+        self.prompt = Some(prompt);
+        self.text.clear();
+        self.completions.clear();
+    }
+
+    pub fn close(&mut self) {
+        self.prompt = None;
+        self.completions.clear();
+    }
+
+    /// Whether completion candidates are listed below the prompt.
+    pub fn has_completions(&self) -> bool {
+        !self.completions.is_empty()
+    }
+
+    // Complete the command name, the path of `goto` or the buffer of `buffer`. A single
+    // candidate is taken, several are listed and their common prefix is taken.
+    fn complete(&mut self) {
+        let candidates: Vec<String> = match self.text.split_once(' ') {
+            None => COMMANDS
+                .iter()
+                .filter(|info| info.name.starts_with(&self.text))
+                .map(|info| format!("{} ", info.name))
+                .collect(),
+            Some(("goto", path)) => self
+                .state
+                .borrow()
+                .file()
+                .map(|f| f.path_completions(path.trim_start()))
+                .unwrap_or_default()
+                .into_iter()
+                .map(|path| format!("goto {path}"))
+                .collect(),
+            Some(("buffer", name)) => self
+                .state
+                .borrow()
+                .buffers
+                .iter()
+                .map(|b| b.file.path().display().to_string())
+                .filter(|path| path.starts_with(name.trim_start()))
+                .map(|path| format!("buffer {path}"))
+                .collect(),
+            Some(_) => vec![],
+        };
+
+        if let Some(first) = candidates.first() {
+            let common = candidates.iter().fold(first.as_str(), |common, candidate| {
+                let len = common
+                    .char_indices()
+                    .zip(candidate.chars())
+                    .find(|((_, a), b)| a != b)
+                    .map_or(common.len().min(candidate.len()), |((i, _), _)| i);
+                &common[..len]
+            });
+            if common.len() > self.text.len() {
+                self.text = common.to_string();
+            }
+        }
+        self.completions = if candidates.len() > 1 {
+            candidates
+        } else {
+            vec![]
+        };
+    }
+
+    pub fn prompt(&self) -> Option<Prompt> {
+        self.prompt
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn scope(&self) -> SearchScope {
+        self.scope
+    }
+}
+
+impl AppComponent for CommandLine {
+    fn draw(&mut self, _mode: &AppMode, frame: &mut Frame, area: Rect) {
+        let Some(prompt) = self.prompt else {
+            return;
+        };
+        let state = self.state.borrow();
+
+        let mut line = vec![
+            Span::from(prompt.symbol()),
+            Span::from(self.text.clone()),
+            Span::from(" ").reversed(),
+        ];
+        if matches!(prompt, Prompt::Search { .. }) && self.scope != SearchScope::All {
+            line.push(Span::styled(
+                format!("  [{}]", self.scope.name()),
+                state.theme.gutter_cursor,
+            ));
+        }
+
+        // Command names are shown as they are, paths by their last segment
+        let completions =
+            self.completions
+                .iter()
+                .map(|candidate| match candidate.split_once(' ') {
+                    Some((name, "")) => Span::from(format!("{name}  ")),
+                    Some(("goto", path)) => {
+                        let (_, last) = split_last_segment(path);
+                        Span::from(format!("{}  ", last.strip_prefix('.').unwrap_or(last)))
+                    }
+                    Some((_, arg)) => Span::from(format!("{arg}  ")),
+                    None => Span::from(format!("{candidate}  ")),
+                });
+
+        // Candidates are wrapped here rather than by the paragraph, to know where each is
+        let mut lines = vec![Line::from(line), Line::default()];
+        let mut x = area.x;
+        self.completion_areas.clear();
+        for span in completions {
+            let width = u16::try_from(span.width()).unwrap_or(u16::MAX);
+            if x > area.x && x.saturating_add(width) > area.right() {
+                lines.push(Line::default());
+                x = area.x;
+            }
+            let y = area.y + u16::try_from(lines.len() - 1).unwrap_or(u16::MAX);
+            self.completion_areas.push(Rect::new(x, y, width, 1));
+            lines
+                .last_mut()
+                .expect("Lines are not empty")
+                .push_span(span);
+            x = x.saturating_add(width);
+        }
+
+        frame.render_widget(Paragraph::new(lines), area);
+    }
+
+    fn handle_event(&mut self, _mode: &AppMode, event: &AppEvent) -> bool {
+        if self.prompt.is_none() {
+            return false;
+        }
+        match event {
+            AppEvent::Raw(key) => match key.code {
+                KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.text.push(c);
+                    self.completions.clear();
+                }
+                KeyCode::Backspace => {
+                    self.text.pop();
+                    self.completions.clear();
+                }
+                _ => return false,
+            },
+            AppEvent::SearchScope => self.scope = self.scope.next(),
+            // Clicking a candidate takes it
+            AppEvent::Mouse {
+                action: MouseAction::Click,
+                column,
+                row,
+            } => {
+                let position = Position::new(*column, *row);
+                let Some(candidate) = self
+                    .completion_areas
+                    .iter()
+                    .position(|area| area.contains(position))
+                    .and_then(|index| self.completions.get(index))
+                else {
+                    return false;
+                };
+                self.text = candidate.clone();
+                self.completions.clear();
+            }
+            AppEvent::Complete if self.prompt == Some(Prompt::Command) => self.complete(),
+            _ => return false,
+        }
+        true
+    }
+}
@@ -0,0 +1,248 @@
+use ratatui::{
+    crossterm::event::KeyCode,
+    layout::{Constraint, Flex, Layout, Position, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+use similar::{ChangeTag, TextDiff};
+
+use crate::app::theme::Theme;
+use crate::app::{AppComponent, AppEvent, AppMode, AppState, MouseAction};
+
+/// What would throw away unsaved changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Guard {
+    Quit,
+    /// Loading a file over the buffer it is open in
+    Load,
+    /// Reloading the buffer at the index, after another program changed its file
+    Reload(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Answer {
+    Save,
+    Discard,
+    Cancel,
+}
+
+/// Answers in the order they are shown. Each is also picked by its first letter.
+const ANSWERS: [(Answer, &str); 3] = [
+    (Answer::Save, "Save"),
+    (Answer::Discard, "Discard"),
+    (Answer::Cancel, "Cancel"),
+];
+
+/// Answers to a file changing on disk. Saving writes the buffer over it, keeping goes on editing
+/// without writing.
+const RELOAD_ANSWERS: [(Answer, &str); 3] = [
+    (Answer::Discard, "Reload"),
+    (Answer::Save, "Overwrite"),
+    (Answer::Cancel, "Keep"),
+];
+
+/// Lines of context around each change in a diff.
+const DIFF_CONTEXT: usize = 3;
+
+/// Dialog asking what to do with unsaved changes, along with a diff when their file changed.
+pub struct Confirm {
+    state: AppState,
+    question: Option<(Guard, String)>,
+    diff: Vec<Line<'static>>,
+    scroll: usize,
+    selected: usize,
+    /// Answer that the app has not acted on yet
+    answer: Option<(Guard, Answer)>,
+    /// Where each answer was last drawn, for clicks
+    answer_areas: Vec<Rect>,
+}
+
+impl Confirm {
+    pub fn new(state: AppState) -> Self {
+        Self {
+            state,
+            question: None,
+            diff: vec![],
+            scroll: 0,
+            selected: 0,
+            answer: None,
+            answer_areas: vec![],
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.question.is_some()
+    }
+
+    pub fn ask(&mut self, guard: Guard, message: String) {
+        self.question = Some((guard, message));
+        self.diff.clear();
+        self.scroll = 0;
+        self.selected = 0;
+    }
+
+    /// Ask, showing how `old` differs from `new`.
+    pub fn ask_with_diff(&mut self, guard: Guard, message: String, old: &str, new: &str) {
+        self.ask(guard, message);
+        self.diff = diff(&self.state.borrow().theme, old, new);
+    }
+
+    pub fn take_answer(&mut self) -> Option<(Guard, Answer)> {
+        self.answer.take()
+    }
+
+    fn answer(&mut self, answer: Answer) {
+        if let Some((guard, _)) = self.question.take() {
+            self.answer = Some((guard, answer));
+        }
+    }
+
+    fn answers(&self) -> &'static [(Answer, &'static str); 3] {
+        match self.question {
+            Some((Guard::Reload(_), _)) => &RELOAD_ANSWERS,
+            _ => &ANSWERS,
+        }
+    }
+}
+
+// Changed lines of `new` against `old`, in groups with some context around them
+fn diff(theme: &Theme, old: &str, new: &str) -> Vec<Line<'static>> {
+    let diff = TextDiff::from_lines(old, new);
+    let mut lines = vec![];
+    for (index, group) in diff.grouped_ops(DIFF_CONTEXT).iter().enumerate() {
+        if index > 0 {
+            lines.push(Line::from("...").style(theme.gutter));
+        }
+        for change in group.iter().flat_map(|op| diff.iter_changes(op)) {
+            let (sign, style) = match change.tag() {
+                ChangeTag::Delete => ('-', theme.diff_removed),
+                ChangeTag::Insert => ('+', theme.diff_added),
+                ChangeTag::Equal => (' ', Style::default()),
+            };
+            let text = change.value().trim_end_matches(['\n', '\r']);
+            lines.push(Line::from(format!("{sign} {text}")).style(style));
+        }
+    }
+    lines
+}
+
+impl AppComponent for Confirm {
+    fn draw(&mut self, _mode: &AppMode, frame: &mut Frame, area: Rect) {
+        let Some((guard, message)) = &self.question else {
+            return;
+        };
+
+        let height = if self.diff.is_empty() {
+            Constraint::Length(6)
+        } else {
+            Constraint::Percentage(80)
+        };
+        let [area] = Layout::vertical([height]).flex(Flex::Center).areas(area);
+        let width = if self.diff.is_empty() { 50 } else { 80 };
+        let [area] = Layout::horizontal([Constraint::Percentage(width)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        let state = self.state.borrow();
+        let title = match guard {
+            Guard::Quit | Guard::Load => " Unsaved changes ",
+            Guard::Reload(_) => " File changed on disk ",
+        };
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .border_style(state.theme.airline_mode)
+            .title(title);
+        let inner = block.inner(area);
+        let [message_area, diff_area, answers_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(inner);
+
+        // Answers are one space apart
+        let mut spans = vec![];
+        let mut x = answers_area.x;
+        self.answer_areas.clear();
+        for (index, (_, label)) in self.answers().iter().enumerate() {
+            let text = format!(" [{}]{} ", &label[..1], &label[1..]);
+            let width = u16::try_from(text.len()).unwrap_or(u16::MAX);
+            self.answer_areas
+                .push(Rect::new(x, answers_area.y, width, 1));
+            x = x.saturating_add(width + 1);
+
+            let style = if index == self.selected {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            spans.push(Span::styled(text, style));
+            spans.push(Span::from(" "));
+        }
+
+        self.scroll = self.scroll.min(
+            self.diff
+                .len()
+                .saturating_sub(usize::from(diff_area.height).max(1)),
+        );
+        let diff = self
+            .diff
+            .iter()
+            .skip(self.scroll)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+        frame.render_widget(
+            Paragraph::new(message.clone()).wrap(Wrap { trim: false }),
+            message_area,
+        );
+        frame.render_widget(Paragraph::new(Text::from(diff)), diff_area);
+        frame.render_widget(Paragraph::new(Line::from(spans)), answers_area);
+    }
+
+    fn handle_event(&mut self, _mode: &AppMode, event: &AppEvent) -> bool {
+        if !self.is_open() {
+            return false;
+        }
+        match event {
+            AppEvent::Raw(key) => match key.code {
+                KeyCode::Left => self.selected = self.selected.saturating_sub(1),
+                KeyCode::Right => {
+                    self.selected = (self.selected + 1).min(self.answers().len() - 1);
+                }
+                KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+                KeyCode::Down => self.scroll += 1,
+                KeyCode::Char(c) => {
+                    if let Some((answer, _)) = self
+                        .answers()
+                        .iter()
+                        .find(|(_, label)| label.starts_with(c.to_ascii_uppercase()))
+                    {
+                        self.answer(*answer);
+                    }
+                }
+                _ => return false,
+            },
+            AppEvent::Complete => self.selected = (self.selected + 1) % self.answers().len(),
+            AppEvent::Submit => self.answer(self.answers()[self.selected].0),
+            AppEvent::ScrollY(d) => self.scroll = self.scroll.saturating_add_signed(d.into()),
+            AppEvent::ChangeMode(_) => self.answer(Answer::Cancel),
+            AppEvent::Mouse {
+                action: MouseAction::Click,
+                column,
+                row,
+            } => {
+                let position = Position::new(*column, *row);
+                if let Some(index) = self.answer_areas.iter().position(|a| a.contains(position)) {
+                    self.answer(self.answers()[index].0);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+}
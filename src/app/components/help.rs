@@ -0,0 +1,226 @@
+use ratatui::{
+    crossterm::event::{KeyCode, KeyModifiers},
+    layout::{Constraint, Flex, Layout, Position, Rect},
+    style::Stylize,
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::app::command::COMMANDS;
+use crate::app::keymap::Keymap;
+use crate::app::{AppComponent, AppEvent, AppMode, AppState, MouseAction};
+
+/// How the editor uses the schemas of the cluster, for the help overlay.
+const SCHEMA_FEATURES: [(&str, &str); 6] = [
+    (
+        "validation",
+        "documents are checked against the schema of their apiVersion and kind, problems are \
+         underlined",
+    ),
+    (
+        "paste",
+        "pasted entries are checked against the schema of where they land",
+    ),
+    (
+        "outline",
+        "lists the documents of the file as Kind/name (namespace)",
+    ),
+    (
+        "airline",
+        "shows the kind and path of the cursor, and whether schemas are online, cached or offline",
+    ),
+    (
+        "explain",
+        "`m7s explain KIND.PATH` prints the documentation of a field",
+    ),
+    (
+        "offline",
+        "with --offline or `source = \"offline\"`, schemas are only read from the cache",
+    ),
+];
+
+/// Modes in the order they are listed.
+const MODES: [AppMode; 4] = [
+    AppMode::Normal,
+    AppMode::Visual,
+    AppMode::Input,
+    AppMode::Command,
+];
+
+struct Section {
+    title: String,
+    /// Keys or command, and what it does
+    entries: Vec<(String, String)>,
+}
+
+/// Overlay listing the bindings of every mode, the `:` commands and the schema features. It is
+/// generated from the keymap and the command table, so it follows user bindings. Typing narrows
+/// it down to entries containing the text.
+pub struct Help {
+    state: AppState,
+    pub visible: bool,
+    sections: Vec<Section>,
+    filter: String,
+    scroll: usize,
+    /// Lines that fit in the overlay when it was last drawn, for paging
+    height: usize,
+    area: Rect,
+}
+
+impl Help {
+    pub fn new(state: AppState, keymap: &Keymap) -> Self {
+        let mut sections: Vec<Section> = MODES
+            .iter()
+            .map(|mode| Section {
+                title: format!("{} mode", mode.display_text().to_lowercase()),
+                entries: keymap
+                    .help(mode)
+                    .into_iter()
+                    .map(|(keys, action)| (keys.join("/"), action.description().to_string()))
+                    .collect(),
+            })
+            .collect();
+        sections.push(Section {
+            title: "commands".to_string(),
+            entries: COMMANDS
+                .iter()
+                .map(|info| {
+                    let command = format!(":{} {}", info.name, info.args);
+                    (command.trim_end().to_string(), info.description.to_string())
+                })
+                .collect(),
+        });
+        sections.push(Section {
+            title: "schemas".to_string(),
+            entries: SCHEMA_FEATURES
+                .iter()
+                .map(|(name, description)| ((*name).to_string(), (*description).to_string()))
+                .collect(),
+        });
+
+        Self {
+            state,
+            visible: false,
+            sections,
+            filter: String::new(),
+            scroll: 0,
+            height: 0,
+            area: Rect::default(),
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.visible = true;
+        self.filter.clear();
+        self.scroll = 0;
+    }
+
+    pub fn close(&mut self) {
+        self.visible = false;
+    }
+
+    fn scroll_by(&mut self, delta: isize) {
+        self.scroll = self.scroll.saturating_add_signed(delta);
+    }
+
+    // Sections with the entries matching the filter, sections without any are left out
+    fn lines(&self) -> Vec<Line<'static>> {
+        let filter = self.filter.to_lowercase();
+        let mut lines = vec![];
+        for section in &self.sections {
+            let entries = section
+                .entries
+                .iter()
+                .filter(|(keys, description)| {
+                    keys.to_lowercase().contains(&filter)
+                        || description.to_lowercase().contains(&filter)
+                })
+                .collect::<Vec<_>>();
+            if entries.is_empty() {
+                continue;
+            }
+
+            let width = entries.iter().map(|(keys, _)| keys.chars().count()).max();
+            if !lines.is_empty() {
+                lines.push(Line::default());
+            }
+            lines.push(Line::from(section.title.clone()).bold().underlined());
+            lines.extend(entries.into_iter().map(|(keys, description)| {
+                Line::from(vec![
+                    Span::from(format!("  {keys:<width$}  ", width = width.unwrap_or(0))).bold(),
+                    Span::from(description.clone()),
+                ])
+            }));
+        }
+        lines
+    }
+}
+
+impl AppComponent for Help {
+    fn draw(&mut self, _mode: &AppMode, frame: &mut Frame, area: Rect) {
+        let [area] = Layout::vertical([Constraint::Percentage(80)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [area] = Layout::horizontal([Constraint::Percentage(80)])
+            .flex(Flex::Center)
+            .areas(area);
+        self.area = area;
+
+        let lines = self.lines();
+        // The borders and the filter take three lines
+        self.height = usize::from(area.height.saturating_sub(3));
+        self.scroll = self
+            .scroll
+            .min(lines.len().saturating_sub(self.height.max(1)));
+
+        let mut text = vec![Line::from(vec![
+            Span::from(format!("/{}", self.filter)),
+            Span::from(" ").reversed(),
+        ])];
+        text.extend(lines.into_iter().skip(self.scroll));
+
+        let state = self.state.borrow();
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .border_style(state.theme.airline_mode)
+            .title(" Help ");
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(Text::from(text)).block(block), area);
+    }
+
+    fn handle_event(&mut self, _mode: &AppMode, event: &AppEvent) -> bool {
+        if !self.visible {
+            return false;
+        }
+        let page = isize::try_from(self.height.max(1)).unwrap_or(isize::MAX);
+        match event {
+            AppEvent::Raw(key) => match key.code {
+                KeyCode::Up => self.scroll_by(-1),
+                KeyCode::Down => self.scroll_by(1),
+                KeyCode::PageUp => self.scroll_by(-page),
+                KeyCode::PageDown => self.scroll_by(page),
+                KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.filter.push(c);
+                    self.scroll = 0;
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                    self.scroll = 0;
+                }
+                _ => return false,
+            },
+            AppEvent::ScrollY(d) => self.scroll_by(d.into()),
+            // Clicking outside closes the overlay
+            AppEvent::Mouse {
+                action: MouseAction::Click,
+                column,
+                row,
+            } if !self.area.contains(Position::new(*column, *row)) => self.close(),
+            AppEvent::Mouse { .. } => {}
+            AppEvent::Submit | AppEvent::ChangeMode(_) => self.close(),
+            _ => return false,
+        }
+        true
+    }
+}
@@ -1,32 +1,72 @@
 use ratatui::{
     layout::Rect,
-    text::{Line, Text},
-    widgets::Paragraph,
+    style::Stylize,
+    text::{Line, Span, Text},
+    widgets::{Paragraph, Wrap},
     Frame,
 };
+use std::rc::Rc;
 
+use crate::app::command::COMMANDS;
+use crate::app::keymap::Keymap;
 use crate::app::{AppComponent, AppMode, AppState};
 
-#[derive(Default)]
-pub struct Info {}
+/// Message line. It shows the last notification for a while, and hints otherwise.
+pub struct Info {
+    state: AppState,
+    keymap: Rc<Keymap>,
+}
 
 impl Info {
-    pub fn new(_state: AppState) -> Self {
-        Self {}
+    pub fn new(state: AppState, keymap: Rc<Keymap>) -> Self {
+        Self { state, keymap }
     }
 }
 
 impl AppComponent for Info {
     fn draw(&mut self, mode: &AppMode, frame: &mut Frame, area: Rect) {
-        let message = match mode {
-            AppMode::Normal => vec![
-                "(Enter) to enter input mode, ".into(),
-                "(q)uit, ".into(),
-                "<arrows> to navigate".into(),
-            ],
-            AppMode::Input => vec!["<ESC> to go back to normal mode.".into()],
-            AppMode::Command => vec![":".into()],
-        };
-        frame.render_widget(Paragraph::new(Text::from(Line::from(message))), area);
+        let state = self.state.borrow();
+        if let Some(message) = state.toast() {
+            let line = Line::from(vec![
+                Span::from(format!("{}: ", message.severity)).bold(),
+                Span::from(message.text.clone()),
+            ]);
+            frame.render_widget(
+                Paragraph::new(line.style(state.theme.message(message.severity)))
+                    .wrap(Wrap { trim: false }),
+                area,
+            );
+            return;
+        }
+
+        // Hints are generated from the keymap so that they follow user bindings
+        let message = self
+            .keymap
+            .help(mode)
+            .into_iter()
+            .flat_map(|(keys, action)| {
+                [
+                    Span::from(keys.join("/")).bold(),
+                    Span::from(format!(" {}  ", action.description())),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        // Commands are listed too, so that they can be found without the docs
+        let commands = COMMANDS
+            .iter()
+            .filter(|_| *mode == AppMode::Command)
+            .flat_map(|info| {
+                [
+                    Span::from(format!(":{} {}", info.name, info.args)).bold(),
+                    Span::from(format!(" {}  ", info.description)),
+                ]
+            });
+        let message = message.into_iter().chain(commands).collect::<Vec<_>>();
+
+        frame.render_widget(
+            Paragraph::new(Text::from(Line::from(message))).wrap(Wrap { trim: false }),
+            area,
+        );
     }
 }
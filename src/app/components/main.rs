@@ -1,15 +1,26 @@
 use ratatui::{
-    layout::{Constraint, Layout, Rect},
-    style::{Color, Stylize},
+    layout::{Constraint, Layout, Position, Rect},
     text::{Line, Text},
     widgets::{
         Block, Borders, Padding, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState,
     },
     Frame,
 };
+use rowan::TextRange;
 
-use crate::app::file::Direction;
-use crate::app::{AppComponent, AppEvent, AppMode, AppState, Delta};
+use crate::app::clipboard;
+use crate::app::file::{Direction, Fragment, SearchScope};
+use crate::app::{
+    AppComponent, AppEvent, AppMode, AppState, Delta, FoldAction, MouseAction, Operator, Query,
+    Severity, View,
+};
+
+/// Scrollbar being dragged with the mouse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Drag {
+    Vertical,
+    Horizontal,
+}
 
 #[derive(Default)]
 struct CursorState {
@@ -27,10 +38,23 @@ pub struct Main {
     vertical_scroll: usize,
     horizontal_scroll: usize,
     viewport: (u16, u16),
+    /// Where the file was last drawn, including the scrollbars
+    content_area: Rect,
+    drag: Option<Drag>,
+    /// Ranges selected in visual mode, each around the one before it. The last one is shown.
+    selection: Vec<TextRange>,
+    /// Spaces per indentation level
+    indent: usize,
+    /// Register of the next yank or paste
+    register: char,
+    /// Buffer being shown, its view is stored in it when another one is shown
+    buffer: usize,
+    /// Whether this pane has focus, `None` while it is the only one
+    pub focus: Option<bool>,
 }
 
 impl Main {
-    pub fn new(state: AppState) -> Self {
+    pub fn new(state: AppState, indent: usize) -> Self {
         Self {
             state,
             cursor: CursorState::default(),
@@ -39,7 +63,62 @@ impl Main {
             vertical_scroll: 0,
             horizontal_scroll: 0,
             viewport: (0, 0),
+            content_area: Rect::default(),
+            drag: None,
+            selection: vec![],
+            indent,
+            register: '"',
+            buffer: 0,
+            focus: None,
+        }
+    }
+
+    /// Another view onto the same buffer, at the same position.
+    pub fn split(&self) -> Self {
+        let mut main = Main::new(self.state.clone(), self.indent);
+        main.buffer = self.buffer;
+        main.cursor.byte_offset = self.cursor.byte_offset;
+        main.cursor.line = self.cursor.line;
+        main.scroll_to(Some(self.vertical_scroll), Some(self.horizontal_scroll));
+        main
+    }
+
+    pub fn buffer(&self) -> usize {
+        self.buffer
+    }
+
+    /// Show the current buffer where it was last left, if it changed since the last call. Only
+    /// the pane with focus follows the current buffer.
+    pub fn follow_buffer(&mut self) {
+        let current = self.state.borrow().current;
+        if current == self.buffer {
+            return;
         }
+
+        let view = View {
+            cursor: self.cursor.byte_offset,
+            vertical_scroll: self.vertical_scroll,
+            horizontal_scroll: self.horizontal_scroll,
+        };
+        let restored = {
+            let mut state = self.state.borrow_mut();
+            if let Some(buffer) = state.buffers.get_mut(self.buffer) {
+                buffer.view = view;
+            }
+            state
+                .buffers
+                .get(current)
+                .map(|b| b.view)
+                .unwrap_or_default()
+        };
+
+        self.buffer = current;
+        self.selection.clear();
+        self.set_cursor(restored.cursor);
+        self.scroll_to(
+            Some(restored.vertical_scroll),
+            Some(restored.horizontal_scroll),
+        );
     }
 
     fn set_cursor(&mut self, byte_offset: u32) {
@@ -47,12 +126,24 @@ impl Main {
         self.cursor.line = self
             .state
             .borrow()
-            .file
-            .as_ref()
+            .file_at(self.buffer)
             .expect("File is loaded")
             .line_at_cursor(self.cursor.byte_offset);
     }
 
+    pub fn cursor(&self) -> u32 {
+        self.cursor.byte_offset
+    }
+
+    /// Move the cursor to the first selectable token on the line of `byte_offset`.
+    pub fn jump_to(&mut self, byte_offset: u32) {
+        let line = match self.state.borrow().file_at(self.buffer) {
+            Some(file) => file.line_at_cursor(byte_offset),
+            None => return,
+        };
+        self.move_cursor_to_line(line);
+    }
+
     fn cursor_visible(&self) -> bool {
         self.cursor.line >= self.vertical_scroll
             && self.cursor.line
@@ -66,7 +157,7 @@ impl Main {
 impl Main {
     fn move_cursor_x(&mut self, dx: &Delta) {
         // Do nothing if the file is not loaded
-        if self.state.borrow().file.is_none() {
+        if self.state.borrow().file_at(self.buffer).is_none() {
             return;
         }
 
@@ -79,15 +170,14 @@ impl Main {
         self.cursor.byte_offset = self
             .state
             .borrow()
-            .file
-            .as_ref()
+            .file_at(self.buffer)
             .expect("File is loaded")
             .navigate_dir(self.cursor.byte_offset, &dir);
     }
 
     fn move_cursor_y(&mut self, dy: &Delta) {
         // Do nothing if the file is not loaded
-        if self.state.borrow().file.is_none() {
+        if self.state.borrow().file_at(self.buffer).is_none() {
             return;
         }
 
@@ -102,8 +192,7 @@ impl Main {
             };
             self.state
                 .borrow()
-                .file
-                .as_ref()
+                .file_at(self.buffer)
                 .expect("File is loaded")
                 .navigate_dir(self.cursor.byte_offset, &dir)
         } else {
@@ -118,13 +207,317 @@ impl Main {
             };
             self.state
                 .borrow()
-                .file
-                .as_ref()
+                .file_at(self.buffer)
                 .expect("File is loaded")
                 .first_selectable_at_line(line)
         });
 
-        // Scroll the view if the cursor left the viewport
+        self.scroll_to_cursor();
+    }
+
+    fn move_cursor_to_line(&mut self, line: usize) {
+        // Do nothing if the file is not loaded
+        if self.state.borrow().file_at(self.buffer).is_none() {
+            return;
+        }
+
+        let cursor = self
+            .state
+            .borrow()
+            .file_at(self.buffer)
+            .expect("File is loaded")
+            .first_selectable_at_line(line);
+        self.set_cursor(cursor);
+        self.scroll_to_cursor();
+    }
+
+    // Start the selection over from the token under the cursor
+    fn select_token(&mut self) {
+        self.selection = self
+            .state
+            .borrow()
+            .file_at(self.buffer)
+            .map(|f| f.selection_at(self.cursor.byte_offset))
+            .into_iter()
+            .collect();
+    }
+
+    fn expand_selection(&mut self) {
+        let state = self.state.borrow();
+        let expanded = match (state.file_at(self.buffer), self.selection.last()) {
+            (Some(file), Some(selection)) => file.expand_selection(*selection),
+            _ => None,
+        };
+        self.selection.extend(expanded);
+    }
+
+    fn shrink_selection(&mut self) {
+        if self.selection.len() > 1 {
+            self.selection.pop();
+        }
+    }
+
+    // Apply `operator` to the selection, which ends visual mode
+    fn apply(&mut self, operator: Operator) {
+        let Some(selection) = self.selection.pop() else {
+            return;
+        };
+        self.selection.clear();
+
+        let mut state = self.state.borrow_mut();
+        let Some(file) = state.file_at_mut(self.buffer) else {
+            return;
+        };
+        let result = match operator {
+            Operator::Delete => file.delete_selection(selection),
+            Operator::Yank => {
+                let fragment = file.yank_selection(selection);
+                drop(state);
+                self.store(fragment);
+                return;
+            }
+            Operator::Comment => file.comment(selection),
+            Operator::Indent => file.indent(selection, self.indent, false),
+            Operator::Dedent => file.indent(selection, self.indent, true),
+        };
+        drop(state);
+
+        match result {
+            Ok(cursor) => {
+                self.set_cursor(cursor);
+                self.scroll_to_cursor();
+            }
+            Err(e) => self
+                .state
+                .borrow_mut()
+                .notify(Severity::Error, format!("Could not edit selection: {e}")),
+        }
+    }
+
+    fn yank(&mut self) {
+        let fragment = self
+            .state
+            .borrow()
+            .file_at(self.buffer)
+            .and_then(|f| f.yank(self.cursor.byte_offset));
+        if let Some(fragment) = fragment {
+            self.store(fragment);
+        }
+    }
+
+    // Keep `fragment` in the picked register and the default one, and copy it to the clipboard
+    fn store(&mut self, fragment: Fragment) {
+        let register = std::mem::replace(&mut self.register, '"');
+        if let Err(e) = clipboard::copy(fragment.text()) {
+            self.state.borrow_mut().notify(
+                Severity::Warning,
+                format!("Could not copy to the clipboard: {e}"),
+            );
+        }
+        log::info!(
+            "Yanked {} line(s) into \"{register}",
+            fragment.text().lines().count()
+        );
+
+        let mut state = self.state.borrow_mut();
+        state.registers.insert('"', fragment.clone());
+        state.registers.insert(register, fragment);
+    }
+
+    fn paste(&mut self, child: bool) {
+        let register = std::mem::replace(&mut self.register, '"');
+        let mut state = self.state.borrow_mut();
+        let Some(fragment) = state.registers.get(&register).cloned() else {
+            state.notify(Severity::Warning, format!("Register \"{register} is empty"));
+            return;
+        };
+        let Some(file) = state.file_at_mut(self.buffer) else {
+            return;
+        };
+        let result = file.paste(&fragment, self.cursor.byte_offset, child, self.indent);
+
+        match result {
+            Ok(cursor) => {
+                self.cursor.byte_offset = cursor;
+                self.cursor.line = file.line_at_cursor(cursor);
+            }
+            Err(e) => state.notify(Severity::Error, format!("Could not paste: {e}")),
+        }
+    }
+
+    fn copy_path(&self) {
+        let Some((_, path)) = self
+            .state
+            .borrow()
+            .file_at(self.buffer)
+            .and_then(|f| f.path_at(self.cursor.byte_offset))
+        else {
+            return;
+        };
+
+        let message = match clipboard::copy(&path.to_string()) {
+            Ok(()) => (Severity::Info, format!("Copied path {path}")),
+            Err(e) => (Severity::Error, format!("Could not copy path: {e}")),
+        };
+        self.state.borrow_mut().notify(message.0, message.1);
+    }
+
+    // A click puts the cursor on the nearest token, or grabs a scrollbar when it is on one
+    fn handle_mouse(&mut self, action: MouseAction, position: Position) {
+        let area = self.content_area;
+        match action {
+            MouseAction::Click | MouseAction::DoubleClick => {
+                self.drag = if position.x + 1 == area.right() && position.y < area.bottom() {
+                    Some(Drag::Vertical)
+                } else if position.y + 1 == area.bottom() && position.x >= area.x {
+                    Some(Drag::Horizontal)
+                } else {
+                    None
+                };
+                if self.drag.is_some() {
+                    self.drag_to(position);
+                } else {
+                    self.click(position);
+                }
+            }
+            MouseAction::Drag => self.drag_to(position),
+            MouseAction::Release => self.drag = None,
+        }
+    }
+
+    fn click(&mut self, position: Position) {
+        // Clicks on the line numbers go to the start of the line
+        let line =
+            self.vertical_scroll + usize::from(position.y.saturating_sub(self.content_area.y));
+        let column =
+            self.horizontal_scroll + usize::from(position.x.saturating_sub(self.content_area.x));
+        let cursor = self
+            .state
+            .borrow()
+            .file_at(self.buffer)
+            .map(|f| f.selectable_at(line, column));
+        if let Some(cursor) = cursor {
+            self.set_cursor(cursor);
+        }
+    }
+
+    // Scroll to where the dragged scrollbar is, in proportion to its length
+    fn drag_to(&mut self, position: Position) {
+        let area = self.content_area;
+        let (line_count, max_width) = self
+            .state
+            .borrow()
+            .file_at(self.buffer)
+            .map_or((1, 1), |f| (f.line_count, f.max_width));
+
+        match self.drag {
+            Some(Drag::Vertical) => {
+                // The last row is taken by the other scrollbar
+                let length = usize::from(area.height.saturating_sub(2).max(1));
+                let offset = usize::from(position.y.saturating_sub(area.y)).min(length);
+                self.scroll_to(Some(line_count.saturating_sub(1) * offset / length), None);
+            }
+            Some(Drag::Horizontal) => {
+                let length = usize::from(area.width.saturating_sub(2).max(1));
+                let offset = usize::from(position.x.saturating_sub(area.x)).min(length);
+                self.scroll_to(None, Some(max_width.saturating_sub(1) * offset / length));
+            }
+            None => {}
+        }
+    }
+
+    /// Move the cursor to the key at `query`.
+    pub fn goto(&mut self, query: &Query) {
+        let found = self
+            .state
+            .borrow_mut()
+            .file_at_mut(self.buffer)
+            .and_then(|f| f.goto(query, self.cursor.byte_offset));
+        match found {
+            Some(cursor) => {
+                self.set_cursor(cursor);
+                self.scroll_to_cursor();
+            }
+            None => self
+                .state
+                .borrow_mut()
+                .notify(Severity::Warning, format!("Path not found: {query}")),
+        }
+    }
+
+    /// Put the cursor back on `query` after the file was loaded again, or on the same line when
+    /// the path is gone.
+    pub fn reload(&mut self, query: Option<&Query>) {
+        self.selection.clear();
+        let found = query.and_then(|query| {
+            self.state
+                .borrow_mut()
+                .file_at_mut(self.buffer)?
+                .goto(query, self.cursor.byte_offset)
+        });
+        match found {
+            Some(cursor) => {
+                self.set_cursor(cursor);
+                self.scroll_to_cursor();
+            }
+            None => self.move_cursor_to_line(self.cursor.line),
+        }
+    }
+
+    /// Search from `from`, moving the cursor to the first match. The cursor goes back to `from`
+    /// while nothing matches.
+    pub fn search(&mut self, pattern: &str, backward: bool, scope: SearchScope, from: u32) {
+        let found = self
+            .state
+            .borrow_mut()
+            .file_at_mut(self.buffer)
+            .and_then(|f| f.search(pattern, backward, scope, from));
+
+        if self.state.borrow().file_at(self.buffer).is_some() {
+            self.set_cursor(found.unwrap_or(from));
+            self.scroll_to_cursor();
+        }
+    }
+
+    /// Drop the search and put the cursor back at `origin`.
+    pub fn clear_search(&mut self, origin: u32) {
+        if let Some(file) = self.state.borrow_mut().file_at_mut(self.buffer) {
+            file.clear_search();
+        } else {
+            return;
+        }
+        self.set_cursor(origin);
+        self.scroll_to_cursor();
+    }
+
+    fn search_next(&mut self, reverse: bool) {
+        let found = self
+            .state
+            .borrow()
+            .file_at(self.buffer)
+            .and_then(|f| f.next_match(self.cursor.byte_offset, reverse));
+
+        if let Some(cursor) = found {
+            self.set_cursor(cursor);
+            self.scroll_to_cursor();
+        }
+    }
+
+    fn fold(&mut self, action: FoldAction) {
+        let cursor = self
+            .state
+            .borrow_mut()
+            .file_at_mut(self.buffer)
+            .and_then(|f| f.fold(self.cursor.byte_offset, action));
+
+        if let Some(cursor) = cursor {
+            self.set_cursor(cursor);
+            self.scroll_to_cursor();
+        }
+    }
+
+    // Scroll the view if the cursor left the viewport
+    fn scroll_to_cursor(&mut self) {
         if self.cursor.line < self.vertical_scroll {
             self.scroll_to(Some(self.cursor.line), None);
         } else if self.cursor.line
@@ -172,8 +565,7 @@ impl Main {
         let (file_width, file_length) = self
             .state
             .borrow()
-            .file
-            .as_ref()
+            .file_at(self.buffer)
             .map_or((1, 1), |f| (f.max_width, f.line_count));
 
         if horizontal_scroll >= file_width {
@@ -191,13 +583,25 @@ impl Main {
 impl Main {
     #[allow(clippy::cast_possible_truncation)]
     fn draw_content(&mut self, _mode: &AppMode, frame: &mut Frame, area: Rect) {
-        if let Some(file) = &self.state.borrow().file {
-            let (content, max_line) = file.render(self.cursor.byte_offset.try_into().unwrap());
+        let state = self.state.borrow();
+        if let Some(file) = state.file_at(self.buffer) {
+            let (content, max_line) = file.render(
+                self.cursor.byte_offset.try_into().unwrap(),
+                self.selection.last().copied(),
+                &state.theme,
+            );
 
             self.vertical_scroll_state = self.vertical_scroll_state.content_length(content.len());
             self.horizontal_scroll_state = self.horizontal_scroll_state.content_length(max_line);
 
-            let block = Block::new().borders(Borders::RIGHT | Borders::BOTTOM);
+            let mut block = Block::new().borders(Borders::RIGHT | Borders::BOTTOM);
+            // Split panes are told apart by their file, the one with focus is highlighted
+            if let Some(focused) = self.focus {
+                block = block.title_bottom(format!(" {} ", file.path().display()));
+                if focused {
+                    block = block.border_style(state.theme.airline_mode);
+                }
+            }
 
             let paragraph = Paragraph::new(Text::from(content))
                 .scroll((self.vertical_scroll as u16, self.horizontal_scroll as u16))
@@ -222,17 +626,23 @@ impl Main {
 
     #[allow(clippy::cast_possible_truncation)]
     fn draw_line_numbers(&self, _mode: &AppMode, frame: &mut Frame, area: Rect, line_count: usize) {
-        let block = Block::new()
-            .bg(Color::Indexed(22))
-            .padding(Padding::right(1));
+        let theme = &self.state.borrow().theme;
+        let block = Block::new().style(theme.gutter).padding(Padding::right(1));
 
+        // Lines in folds are skipped, so the numbers are those in the file
+        let numbers = self
+            .state
+            .borrow()
+            .file_at(self.buffer)
+            .map(crate::app::File::line_numbers)
+            .unwrap_or_default();
         let top = self.vertical_scroll;
         let lines: Vec<Line<'_>> = (top..line_count)
             .map(|i| {
-                let line_no = i.saturating_add(1);
+                let line_no = numbers.get(i).copied().unwrap_or(i).saturating_add(1);
                 let mut line = Line::from(format!("{line_no}").to_string());
                 if i == self.cursor.line {
-                    line = line.bg(Color::Indexed(236));
+                    line = line.style(theme.gutter_cursor);
                 }
                 line
             })
@@ -252,8 +662,7 @@ impl AppComponent for Main {
         let (line_count, max_width) = self
             .state
             .borrow()
-            .file
-            .as_ref()
+            .file_at(self.buffer)
             .map_or((1, 1), |f| (f.line_count, f.max_width));
 
         self.vertical_scroll_state = self.vertical_scroll_state.content_length(line_count);
@@ -277,14 +686,42 @@ impl AppComponent for Main {
             main_content.width.saturating_sub(1),
         );
 
-        frame.render_widget(Block::new().bg(Color::Indexed(22)), line_numbers);
+        frame.render_widget(
+            Block::new().style(self.state.borrow().theme.gutter),
+            line_numbers,
+        );
+        self.content_area = main_content;
         self.draw_content(mode, frame, main_content);
         self.draw_line_numbers(mode, frame, line_numbers, line_count);
     }
 
     fn handle_event(&mut self, mode: &AppMode, event: &AppEvent) -> bool {
+        self.follow_buffer();
         match event {
+            AppEvent::ChangeMode(AppMode::Visual) => self.select_token(),
+            AppEvent::ChangeMode(_) => self.selection.clear(),
+            AppEvent::ExpandSelection => self.expand_selection(),
+            AppEvent::ShrinkSelection => self.shrink_selection(),
+            AppEvent::Operator(operator) => self.apply(*operator),
+            AppEvent::Register(name) => self.register = *name,
+            AppEvent::Yank => self.yank(),
+            AppEvent::Paste { child } => {
+                self.paste(*child);
+                self.scroll_to_cursor();
+            }
             AppEvent::CursorY(d) => self.move_cursor_y(d),
+            AppEvent::CursorTop => self.move_cursor_to_line(0),
+            AppEvent::CursorBottom => {
+                let line_count = self
+                    .state
+                    .borrow()
+                    .file_at(self.buffer)
+                    .map_or(0, |f| f.line_count);
+                self.move_cursor_to_line(line_count);
+            }
+            AppEvent::Fold(action) => self.fold(*action),
+            AppEvent::CopyPath => self.copy_path(),
+            AppEvent::SearchNext { reverse } => self.search_next(*reverse),
             AppEvent::ScrollX(d) => {
                 self.scroll(0, d.into());
             }
@@ -294,15 +731,33 @@ impl AppComponent for Main {
             AppEvent::CursorX(d) => {
                 self.move_cursor_x(d);
             }
-            AppEvent::Info => self
-                .state
-                .borrow()
-                .file
-                .as_ref()
-                .expect("")
-                .info(self.cursor.byte_offset),
+            AppEvent::Mouse {
+                action,
+                column,
+                row,
+            } => self.handle_mouse(*action, Position::new(*column, *row)),
+            AppEvent::Info => {
+                if let Some(file) = self.state.borrow().file_at(self.buffer) {
+                    file.info(self.cursor.byte_offset);
+                }
+            }
             _ => return false,
         }
+
+        // The selection starts over wherever the cursor moves to
+        if *mode == AppMode::Visual
+            && matches!(
+                event,
+                AppEvent::CursorX(_)
+                    | AppEvent::CursorY(_)
+                    | AppEvent::Mouse {
+                        action: MouseAction::Click,
+                        ..
+                    }
+            )
+        {
+            self.select_token();
+        }
         true
     }
 }
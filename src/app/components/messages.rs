@@ -0,0 +1,120 @@
+use ratatui::{
+    crossterm::event::KeyCode,
+    layout::{Constraint, Flex, Layout, Position, Rect},
+    style::Stylize,
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::app::{AppComponent, AppEvent, AppMode, AppState, MouseAction};
+
+/// Overlay listing the notifications shown so far, like `:messages`. The latest are at the
+/// bottom, which is where it opens.
+pub struct Messages {
+    state: AppState,
+    pub visible: bool,
+    scroll: usize,
+    /// Lines that fit in the overlay when it was last drawn, for paging
+    height: usize,
+    area: Rect,
+}
+
+impl Messages {
+    pub fn new(state: AppState) -> Self {
+        Self {
+            state,
+            visible: false,
+            scroll: usize::MAX,
+            height: 0,
+            area: Rect::default(),
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.visible = true;
+        // Clamped to the last page when drawn
+        self.scroll = usize::MAX;
+    }
+
+    pub fn close(&mut self) {
+        self.visible = false;
+    }
+
+    fn scroll_by(&mut self, delta: isize) {
+        self.scroll = self.scroll.saturating_add_signed(delta);
+    }
+}
+
+impl AppComponent for Messages {
+    fn draw(&mut self, _mode: &AppMode, frame: &mut Frame, area: Rect) {
+        let [area] = Layout::vertical([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [area] = Layout::horizontal([Constraint::Percentage(80)])
+            .flex(Flex::Center)
+            .areas(area);
+        self.area = area;
+
+        let state = self.state.borrow();
+        let mut lines = state
+            .messages
+            .iter()
+            .map(|message| {
+                Line::from(vec![
+                    Span::from(format!("{:<7} ", message.severity)).bold(),
+                    Span::from(message.text.clone()),
+                ])
+                .style(state.theme.message(message.severity))
+            })
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            lines.push(Line::from("No messages").italic());
+        }
+
+        self.height = usize::from(area.height.saturating_sub(2));
+        self.scroll = self
+            .scroll
+            .min(lines.len().saturating_sub(self.height.max(1)));
+        let lines = lines.into_iter().skip(self.scroll).collect::<Vec<_>>();
+
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .border_style(state.theme.airline_mode)
+            .title(" Messages ");
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(Text::from(lines))
+                .wrap(Wrap { trim: false })
+                .block(block),
+            area,
+        );
+    }
+
+    fn handle_event(&mut self, _mode: &AppMode, event: &AppEvent) -> bool {
+        if !self.visible {
+            return false;
+        }
+        let page = isize::try_from(self.height.max(1)).unwrap_or(isize::MAX);
+        match event {
+            AppEvent::Raw(key) => match key.code {
+                KeyCode::Up => self.scroll_by(-1),
+                KeyCode::Down => self.scroll_by(1),
+                KeyCode::PageUp => self.scroll_by(-page),
+                KeyCode::PageDown => self.scroll_by(page),
+                _ => return false,
+            },
+            AppEvent::ScrollY(d) => self.scroll_by(d.into()),
+            // Clicking outside closes the overlay
+            AppEvent::Mouse {
+                action: MouseAction::Click,
+                column,
+                row,
+            } if !self.area.contains(Position::new(*column, *row)) => self.close(),
+            AppEvent::Mouse { .. } => {}
+            AppEvent::Submit | AppEvent::ChangeMode(_) => self.close(),
+            _ => return false,
+        }
+        true
+    }
+}
@@ -1,33 +1,358 @@
 use ratatui::{
-    layout::{Constraint, Layout, Rect},
+    layout::{Constraint, Direction, Layout, Position, Rect},
     Frame,
 };
+use std::rc::Rc;
 
 mod airline;
+mod buffer_list;
+mod command_line;
+mod confirm;
+mod help;
 mod info;
 mod main;
+mod messages;
+mod outline;
+mod panes;
 
 pub use airline::Airline;
+pub use buffer_list::BufferList;
+pub use command_line::{CommandLine, Prompt};
+pub use confirm::{Answer, Confirm, Guard};
+pub use help::Help;
 pub use info::Info;
 pub use main::Main;
+pub use messages::Messages;
+pub use outline::Outline;
+pub use panes::Panes;
 
-use super::{AppComponent, AppEvent, AppMode, AppState};
+use crate::config::Config;
+
+use super::command::Command;
+
+use super::{
+    keymap::Keymap, AppComponent, AppEvent, AppMode, AppState, File, MouseAction, PaneFocus,
+    Query, Severity,
+};
 
-#[derive(Default)]
 pub struct Components {
-    main: Main,
+    state: AppState,
+    /// Views of the split panes, laid out by `panes`
+    mains: Vec<Main>,
+    panes: Panes,
+    focused: usize,
+    /// Where each view was last drawn, to move focus by direction
+    areas: Vec<(usize, Rect)>,
+    outline: Outline,
     airline: Airline,
+    command_line: CommandLine,
+    buffer_list: BufferList,
+    help: Help,
+    messages: Messages,
+    confirm: Confirm,
     info: Info,
+    /// Cursor when the command line was opened, searches start from it
+    origin: u32,
+    /// Events for the app to handle after the current one, e.g. quitting on `:q`
+    events: Vec<AppEvent>,
 }
 
 impl Components {
-    pub fn new(state: AppState) -> Self {
+    pub fn new(state: AppState, keymap: Rc<Keymap>, config: &Config) -> Self {
         Self {
-            main: Main::new(state.clone()),
-            airline: Airline::new(state.clone()),
-            info: Info::new(state.clone()),
+            state: state.clone(),
+            mains: vec![Main::new(state.clone(), config.editor.indent)],
+            panes: Panes::View(0),
+            focused: 0,
+            areas: vec![],
+            outline: Outline::new(state.clone()),
+            airline: Airline::new(state.clone(), config.airline.clone()),
+            command_line: CommandLine::new(state.clone()),
+            buffer_list: BufferList::new(state.clone()),
+            help: Help::new(state.clone(), &keymap),
+            messages: Messages::new(state.clone()),
+            confirm: Confirm::new(state.clone()),
+            info: Info::new(state, keymap),
+            origin: 0,
+            events: vec![],
+        }
+    }
+
+    pub fn take_events(&mut self) -> Vec<AppEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Ask what to do with unsaved changes before going ahead with `guard`.
+    pub fn confirm(&mut self, guard: Guard, message: String) {
+        self.confirm.ask(guard, message);
+    }
+
+    /// Ask what to do with the changes to buffer `index` now that its file changed on disk,
+    /// showing how `text` differs from what is on disk.
+    pub fn conflict(&mut self, index: usize, message: String, text: &str, disk: &str) {
+        self.confirm
+            .ask_with_diff(Guard::Reload(index), message, text, disk);
+    }
+
+    /// Replace the file of buffer `index` with `file`, keeping cursors on the same key paths.
+    pub fn reload(&mut self, index: usize, file: File) {
+        let paths = self.paths(index);
+        match self.state.borrow_mut().buffers.get_mut(index) {
+            Some(buffer) => buffer.file = file,
+            None => return,
+        }
+        self.restore(index, paths, None);
+    }
+
+    /// Key paths under the cursors of the views of buffer `index`, then of where the buffer was
+    /// left, to put the cursors back with [`Components::restore`] once its text changed.
+    fn paths(&self, index: usize) -> (Vec<Option<Query>>, Option<Query>) {
+        let state = self.state.borrow();
+        let Some(buffer) = state.buffers.get(index) else {
+            return (vec![], None);
+        };
+        let path_at = |cursor| buffer.file.path_at(cursor).map(|(_, query)| query);
+        let queries = self
+            .mains
+            .iter()
+            .map(|main| {
+                (main.buffer() == index)
+                    .then(|| path_at(main.cursor()))
+                    .flatten()
+            })
+            .collect();
+        (queries, path_at(buffer.view.cursor))
+    }
+
+    // Offsets are stale once the text of a buffer changed, views other than `skip` go back to
+    // their key paths
+    fn restore(
+        &mut self,
+        index: usize,
+        (queries, view): (Vec<Option<Query>>, Option<Query>),
+        skip: Option<usize>,
+    ) {
+        if let Some(buffer) = self.state.borrow_mut().buffers.get_mut(index) {
+            // Where the buffer was left, for when it is shown again
+            buffer.view.cursor = view
+                .and_then(|query| buffer.file.goto(&query, buffer.view.cursor))
+                .unwrap_or_default();
+        }
+        for (i, (main, query)) in self.mains.iter_mut().zip(queries).enumerate() {
+            if main.buffer() == index && skip != Some(i) {
+                main.reload(query.as_ref());
+            }
+        }
+    }
+
+    fn revision(&self, index: usize) -> Option<u64> {
+        self.state.borrow().file_at(index).map(File::revision)
+    }
+
+    // Go ahead with what the dialog was guarding, saving first when asked to
+    fn answered(&mut self, guard: Guard, answer: Answer) {
+        let ahead = match guard {
+            Guard::Quit => AppEvent::Exit { force: true },
+            Guard::Load => AppEvent::Load { force: true },
+            Guard::Reload(index) => AppEvent::Reload(index),
+        };
+        match (guard, answer) {
+            (_, Answer::Cancel) => {}
+            (_, Answer::Discard) => self.events.push(ahead),
+            // Writing may fail, so the guard is checked again rather than forced
+            (Guard::Quit, Answer::Save) => {
+                self.events.push(AppEvent::WriteAll);
+                self.events.push(AppEvent::Exit { force: false });
+            }
+            (Guard::Load, Answer::Save) => {
+                self.events.push(AppEvent::Write { force: false });
+                self.events.push(AppEvent::Load { force: false });
+            }
+            // The buffer was made current when asking, writing it replaces the file on disk
+            (Guard::Reload(_), Answer::Save) => self.events.push(AppEvent::Write { force: true }),
+        }
+    }
+
+    fn focus(&mut self, view: usize) {
+        self.focused = view;
+        // Editing follows focus to the buffer of the pane
+        self.state.borrow_mut().current = self.mains[view].buffer();
+    }
+
+    fn split(&mut self, direction: Direction, buffer: Option<&str>) {
+        let buffer = match buffer.map(|name| self.state.borrow().find_buffer(name)) {
+            Some(Some(index)) => Some(index),
+            Some(None) => {
+                self.state.borrow_mut().notify(
+                    Severity::Warning,
+                    format!("No buffer `{}`", buffer.unwrap_or_default()),
+                );
+                return;
+            }
+            None => None,
+        };
+
+        let main = self.mains[self.focused].split();
+        self.mains.push(main);
+        let new = self.mains.len() - 1;
+        self.panes.split(self.focused, new, direction);
+        self.focus(new);
+        if let Some(index) = buffer {
+            self.state.borrow_mut().current = index;
+        }
+    }
+
+    fn focus_pane(&mut self, direction: PaneFocus) {
+        let view = match direction {
+            PaneFocus::Next => Some((self.focused + 1) % self.mains.len()),
+            _ => panes::neighbour(&self.areas, self.focused, direction),
+        };
+        if let Some(view) = view {
+            self.focus(view);
+        }
+    }
+
+    fn close_pane(&mut self) {
+        if self.mains.len() == 1 {
+            self.state
+                .borrow_mut()
+                .notify(Severity::Warning, "Cannot close the last pane");
+            return;
+        }
+        self.mains.remove(self.focused);
+        self.panes.remove(self.focused);
+        self.focus(self.focused.min(self.mains.len() - 1));
+    }
+
+    fn only_pane(&mut self) {
+        let main = self.mains.swap_remove(self.focused);
+        self.mains = vec![main];
+        self.panes = Panes::View(0);
+        self.focused = 0;
+    }
+
+    fn draw_panes(&mut self, mode: &AppMode, frame: &mut Frame, area: Rect) {
+        self.areas = self.panes.areas(area);
+        let split = self.mains.len() > 1;
+        for (view, area) in &self.areas {
+            let main = &mut self.mains[*view];
+            main.focus = split.then_some(*view == self.focused);
+            main.draw(mode, frame, *area);
         }
     }
+
+    // Overlays take clicks while they are open. Otherwise a click focuses the pane under it,
+    // and drags go to the pane they started in.
+    fn handle_mouse(&mut self, mode: &AppMode, event: &AppEvent, action: MouseAction) -> bool {
+        if self.help.visible {
+            return self.help.handle_event(mode, event);
+        }
+        if self.messages.visible {
+            return self.messages.handle_event(mode, event);
+        }
+        if self.buffer_list.visible {
+            return self.buffer_list.handle_event(mode, event);
+        }
+        if self.command_line.prompt().is_some() {
+            return self.command_line.handle_event(mode, event);
+        }
+
+        if let AppEvent::Mouse { column, row, .. } = event
+            && matches!(action, MouseAction::Click | MouseAction::DoubleClick)
+        {
+            let position = Position::new(*column, *row);
+            let Some((view, _)) = self.areas.iter().find(|(_, area)| area.contains(position))
+            else {
+                return false;
+            };
+            self.focus(*view);
+        }
+        self.mains[self.focused].handle_event(mode, event)
+    }
+
+    /// Whether text is still being typed, e.g. into a picker opened by a command.
+    pub fn is_prompting(&self) -> bool {
+        self.command_line.prompt().is_some()
+            || self.buffer_list.visible
+            || self.help.visible
+            || self.messages.visible
+            || self.confirm.is_open()
+    }
+
+    fn run(&mut self, command: &Command) {
+        match command {
+            Command::Goto(query) => self.mains[self.focused].goto(query),
+            Command::Buffers => self.buffer_list.open(),
+            Command::Buffer(name) => {
+                let mut state = self.state.borrow_mut();
+                match state.find_buffer(name) {
+                    Some(index) => state.current = index,
+                    None => self
+                        .state
+                        .borrow_mut()
+                        .notify(Severity::Warning, format!("No buffer `{name}`")),
+                }
+            }
+            Command::NextBuffer => self.state.borrow_mut().cycle_buffer(false),
+            Command::PreviousBuffer => self.state.borrow_mut().cycle_buffer(true),
+            Command::Split { vertical, buffer } => {
+                let direction = if *vertical {
+                    Direction::Horizontal
+                } else {
+                    Direction::Vertical
+                };
+                self.split(direction, buffer.as_deref());
+            }
+            Command::Close => self.close_pane(),
+            Command::Only => self.only_pane(),
+            Command::Write => self.events.push(AppEvent::Write { force: false }),
+            Command::Quit { force } => self.events.push(AppEvent::Exit { force: *force }),
+            Command::WriteQuit => {
+                self.events.push(AppEvent::Write { force: false });
+                self.events.push(AppEvent::Exit { force: false });
+            }
+            Command::Messages => self.messages.open(),
+        }
+    }
+
+    fn handle_command_line(&mut self, mode: &AppMode, event: &AppEvent) -> bool {
+        let Some(prompt) = self.command_line.prompt() else {
+            return false;
+        };
+        let changed = self.command_line.handle_event(mode, event);
+
+        match (prompt, event) {
+            (Prompt::Command, AppEvent::Submit) => {
+                match Command::parse(self.command_line.text()) {
+                    Ok(command) => self.run(&command),
+                    Err(e) => self
+                        .state
+                        .borrow_mut()
+                        .notify(Severity::Error, e.to_string()),
+                }
+                self.command_line.close();
+            }
+            (Prompt::Command, AppEvent::ChangeMode(_)) | (_, AppEvent::Submit) => {
+                self.command_line.close();
+            }
+            // Leaving command mode without submitting cancels
+            (Prompt::Search { .. }, AppEvent::ChangeMode(_)) => {
+                self.command_line.close();
+                self.mains[self.focused].clear_search(self.origin);
+            }
+            // Searches are incremental, the cursor follows what is typed
+            (Prompt::Search { backward }, _) if changed => {
+                self.mains[self.focused].search(
+                    self.command_line.text(),
+                    backward,
+                    self.command_line.scope(),
+                    self.origin,
+                );
+            }
+            _ => return changed,
+        }
+        true
+    }
 }
 
 impl AppComponent for Components {
@@ -40,13 +365,145 @@ impl AppComponent for Components {
 
         let [body_area, airline_area, info_area] = layout.areas(area);
 
-        self.main.draw(mode, frame, body_area);
+        // Buffers may have been switched by a command, the cursor is the one of the new buffer
+        self.mains[self.focused].follow_buffer();
+
+        if self.outline.visible {
+            let [outline_area, main_area] =
+                Layout::horizontal([Constraint::Length(32), Constraint::Min(1)]).areas(body_area);
+            self.outline.follow(self.mains[self.focused].cursor());
+            self.outline.draw(mode, frame, outline_area);
+            self.draw_panes(mode, frame, main_area);
+        } else {
+            self.draw_panes(mode, frame, body_area);
+        }
+        if self.buffer_list.visible {
+            self.buffer_list.draw(mode, frame, body_area);
+        }
+        if self.help.visible {
+            self.help.draw(mode, frame, body_area);
+        }
+        if self.messages.visible {
+            self.messages.draw(mode, frame, body_area);
+        }
+        self.confirm.draw(mode, frame, body_area);
+        self.airline.follow(self.mains[self.focused].cursor());
         self.airline.draw(mode, frame, airline_area);
-        self.info.draw(mode, frame, info_area);
+        if *mode == AppMode::Command && self.command_line.prompt().is_none() {
+            self.info.draw(mode, frame, info_area);
+        } else if *mode == AppMode::Command && self.command_line.has_completions() {
+            self.command_line.draw(mode, frame, info_area);
+        } else if *mode == AppMode::Command {
+            let [command_area, info_area] =
+                Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(info_area);
+            self.command_line.draw(mode, frame, command_area);
+            self.info.draw(mode, frame, info_area);
+        } else {
+            self.info.draw(mode, frame, info_area);
+        }
     }
 
     fn handle_event(&mut self, mode: &AppMode, event: &AppEvent) -> bool {
-        self.main.handle_event(mode, event)
+        // The dialog takes every event until it is answered
+        if self.confirm.is_open() {
+            let handled = self.confirm.handle_event(mode, event);
+            if let Some((guard, answer)) = self.confirm.take_answer() {
+                self.answered(guard, answer);
+            }
+            return handled;
+        }
+
+        if !event.edits() {
+            return self.dispatch(mode, event);
+        }
+
+        // Other views of the buffer edited by the focused one are kept on the same key paths
+        let (focused, index) = (self.focused, self.mains[self.focused].buffer());
+        let revision = self.revision(index);
+        let paths = self.paths(index);
+        let handled = self.dispatch(mode, event);
+        if self.revision(index) != revision {
+            self.restore(index, paths, Some(focused));
+        }
+        handled
+    }
+}
+
+impl Components {
+    fn dispatch(&mut self, mode: &AppMode, event: &AppEvent) -> bool {
+        match event {
+            AppEvent::ToggleOutline => {
+                self.outline.visible = !self.outline.visible;
+                self.outline.focused = self.outline.visible;
+                return true;
+            }
+            AppEvent::FocusNext => {
+                self.outline.focused = self.outline.visible && !self.outline.focused;
+                return true;
+            }
+            AppEvent::Search { backward } => {
+                self.command_line.open(Prompt::Search {
+                    backward: *backward,
+                });
+                self.origin = self.mains[self.focused].cursor();
+                return true;
+            }
+            AppEvent::CommandLine => {
+                self.command_line.open(Prompt::Command);
+                return true;
+            }
+            AppEvent::BufferList => {
+                self.buffer_list.open();
+                return true;
+            }
+            AppEvent::Help => {
+                self.help.open();
+                return true;
+            }
+            AppEvent::NextBuffer { reverse } => {
+                self.state.borrow_mut().cycle_buffer(*reverse);
+                return true;
+            }
+            AppEvent::Split(direction) => {
+                self.split(*direction, None);
+                return true;
+            }
+            AppEvent::FocusPane(direction) => {
+                self.focus_pane(*direction);
+                return true;
+            }
+            AppEvent::ClosePane => {
+                self.close_pane();
+                return true;
+            }
+            AppEvent::OnlyPane => {
+                self.only_pane();
+                return true;
+            }
+            AppEvent::Mouse { action, .. } => return self.handle_mouse(mode, event, *action),
+            _ => {}
+        }
+
+        if self.help.handle_event(mode, event)
+            || self.messages.handle_event(mode, event)
+            || self.buffer_list.handle_event(mode, event)
+        {
+            return true;
+        }
+
+        if self.handle_command_line(mode, event) {
+            return true;
+        }
+
+        // The outline gets movement first while it has focus, picking an entry moves the cursor
+        if self.outline.handle_event(mode, event) {
+            if let Some(offset) = self.outline.take_jump() {
+                self.mains[self.focused].jump_to(offset);
+            }
+            return true;
+        }
+
+        self.mains[self.focused].handle_event(mode, event)
             || self.airline.handle_event(mode, event)
             || self.info.handle_event(mode, event)
     }
@@ -0,0 +1,235 @@
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Text},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+use rowan::TextRange;
+use std::collections::HashSet;
+
+use crate::app::theme::Highlight;
+use crate::app::{AppComponent, AppEvent, AppMode, AppState, Delta};
+
+/// A document of the file and its top level keys
+struct Item {
+    label: String,
+    range: TextRange,
+    keys: Vec<(String, TextRange)>,
+}
+
+impl Item {
+    /// Where the cursor goes when the document is selected
+    fn offset(&self) -> u32 {
+        self.keys
+            .first()
+            .map_or(self.range.start(), |(_, range)| range.start())
+            .into()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    Document(usize),
+    Key(usize, usize),
+}
+
+/// Side panel listing the documents of the file as `Kind/name (namespace)`. Documents expand
+/// into their top level keys.
+pub struct Outline {
+    state: AppState,
+    pub visible: bool,
+    pub focused: bool,
+    expanded: HashSet<usize>,
+    selected: usize,
+    scroll: usize,
+    /// Offset picked in the outline that the main view has not moved to yet
+    jump: Option<u32>,
+}
+
+impl Outline {
+    pub fn new(state: AppState) -> Self {
+        Self {
+            state,
+            visible: false,
+            focused: false,
+            expanded: HashSet::new(),
+            selected: 0,
+            scroll: 0,
+            jump: None,
+        }
+    }
+
+    pub fn take_jump(&mut self) -> Option<u32> {
+        self.jump.take()
+    }
+
+    /// Select the row holding `cursor`, unless the outline is being navigated.
+    pub fn follow(&mut self, cursor: u32) {
+        if self.focused {
+            return;
+        }
+        let items = self.items();
+        let cursor = cursor.into();
+        let Some(document) = items.iter().rposition(|item| item.range.start() <= cursor) else {
+            return;
+        };
+        let key = items[document]
+            .keys
+            .iter()
+            .position(|(_, range)| range.contains_inclusive(cursor));
+        let row = match key {
+            Some(key) if self.expanded.contains(&document) => Row::Key(document, key),
+            _ => Row::Document(document),
+        };
+        if let Some(index) = self.rows(&items).iter().position(|r| *r == row) {
+            self.selected = index;
+        }
+    }
+
+    fn items(&self) -> Vec<Item> {
+        let state = self.state.borrow();
+        let Some(file) = state.file() else {
+            return vec![];
+        };
+        file.resources()
+            .into_iter()
+            .zip(file.top_level_keys())
+            .enumerate()
+            .map(|(index, ((range, details), keys))| {
+                let label = match details {
+                    Some(details) => {
+                        let label = details.display_name();
+                        match details.namespace() {
+                            Some(namespace) => format!("{label} ({namespace})"),
+                            None => label,
+                        }
+                    }
+                    None => format!("Document {}", index + 1),
+                };
+                Item { label, range, keys }
+            })
+            .collect()
+    }
+
+    fn rows(&self, items: &[Item]) -> Vec<Row> {
+        items
+            .iter()
+            .enumerate()
+            .flat_map(|(document, item)| {
+                let keys = if self.expanded.contains(&document) {
+                    item.keys.len()
+                } else {
+                    0
+                };
+                std::iter::once(Row::Document(document))
+                    .chain((0..keys).map(move |key| Row::Key(document, key)))
+            })
+            .collect()
+    }
+
+    fn select(&mut self, index: usize) {
+        let items = self.items();
+        let rows = self.rows(&items);
+        let Some(last) = rows.len().checked_sub(1) else {
+            return;
+        };
+        self.selected = index.min(last);
+        self.jump = Some(match rows[self.selected] {
+            Row::Document(document) => items[document].offset(),
+            Row::Key(document, key) => items[document].keys[key].1.start().into(),
+        });
+    }
+
+    fn expand(&mut self, expand: bool) {
+        let items = self.items();
+        let Some(row) = self.rows(&items).get(self.selected).copied() else {
+            return;
+        };
+        let document = match row {
+            Row::Document(document) | Row::Key(document, _) => document,
+        };
+        if expand {
+            self.expanded.insert(document);
+        } else {
+            self.expanded.remove(&document);
+            // The selected key may be gone, keep its document selected
+            if let Some(index) = self
+                .rows(&items)
+                .iter()
+                .position(|r| *r == Row::Document(document))
+            {
+                self.selected = index;
+            }
+        }
+    }
+}
+
+impl AppComponent for Outline {
+    fn draw(&mut self, _mode: &AppMode, frame: &mut Frame, area: Rect) {
+        let items = self.items();
+        let rows = self.rows(&items);
+
+        // Keep the selected row in view, the border takes the first line
+        let height = area.height.saturating_sub(1) as usize;
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+
+        let state = self.state.borrow();
+        let theme = &state.theme;
+        let lines: Vec<Line<'_>> = rows
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .map(|(index, row)| {
+                let line = match row {
+                    Row::Document(document) => {
+                        let marker = if self.expanded.contains(document) {
+                            "▾"
+                        } else {
+                            "▸"
+                        };
+                        Line::from(format!("{marker} {}", items[*document].label))
+                    }
+                    Row::Key(document, key) => Line::styled(
+                        format!("    {}", items[*document].keys[*key].0),
+                        theme.style(Highlight::Key),
+                    ),
+                };
+                match (index == self.selected, self.focused) {
+                    (true, true) => line.style(Style::default().add_modifier(Modifier::REVERSED)),
+                    (true, false) => line.style(theme.gutter_cursor),
+                    _ => line,
+                }
+            })
+            .collect();
+
+        let mut block = Block::new()
+            .borders(Borders::TOP | Borders::RIGHT)
+            .title(" Outline ");
+        if self.focused {
+            block = block.border_style(theme.airline_mode);
+        }
+        frame.render_widget(Paragraph::new(Text::from(lines)).block(block), area);
+    }
+
+    fn handle_event(&mut self, _mode: &AppMode, event: &AppEvent) -> bool {
+        if !self.visible || !self.focused {
+            return false;
+        }
+        match event {
+            AppEvent::CursorY(d) => {
+                self.select(self.selected.saturating_add_signed(d.into()));
+            }
+            AppEvent::CursorX(Delta::Inc(_)) => self.expand(true),
+            AppEvent::CursorX(Delta::Dec(_)) => self.expand(false),
+            AppEvent::CursorTop => self.select(0),
+            AppEvent::CursorBottom => self.select(usize::MAX),
+            _ => return false,
+        }
+        true
+    }
+}
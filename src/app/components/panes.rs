@@ -0,0 +1,109 @@
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+
+use crate::app::PaneFocus;
+
+/// Arrangement of the split panes of the Main area. Leaves hold the index of their view.
+#[derive(Debug)]
+pub enum Panes {
+    View(usize),
+    /// Panes laid out in `Direction`, sharing the space equally
+    Split(Direction, Vec<Panes>),
+}
+
+impl Panes {
+    /// Split the pane of `view`, putting `new` after it in `direction`.
+    pub fn split(&mut self, view: usize, new: usize, direction: Direction) {
+        match self {
+            Panes::View(v) if *v == view => {
+                *self = Panes::Split(direction, vec![Panes::View(view), Panes::View(new)]);
+            }
+            Panes::View(_) => {}
+            Panes::Split(d, panes) => {
+                // Panes that are already split the same way get one more pane
+                let position = panes
+                    .iter()
+                    .position(|p| matches!(p, Panes::View(v) if *v == view));
+                match position {
+                    Some(position) if *d == direction => {
+                        panes.insert(position + 1, Panes::View(new));
+                    }
+                    _ => panes.iter_mut().for_each(|p| p.split(view, new, direction)),
+                }
+            }
+        }
+    }
+
+    /// Remove the pane of `view`. Views after it move down by one index, as they do in the list
+    /// of views.
+    pub fn remove(&mut self, view: usize) {
+        let Panes::Split(_, panes) = self else {
+            return;
+        };
+        panes.retain(|p| !matches!(p, Panes::View(v) if *v == view));
+        for pane in panes.iter_mut() {
+            match pane {
+                Panes::View(v) if *v > view => *v -= 1,
+                Panes::View(_) => {}
+                Panes::Split(..) => pane.remove(view),
+            }
+        }
+        // A split of one pane is just that pane
+        if panes.len() == 1 {
+            *self = panes.pop().expect("Split has one pane");
+        }
+    }
+
+    /// Area of each view within `area`.
+    pub fn areas(&self, area: Rect) -> Vec<(usize, Rect)> {
+        match self {
+            Panes::View(view) => vec![(*view, area)],
+            Panes::Split(direction, panes) => {
+                #[allow(clippy::cast_possible_truncation)]
+                let count = panes.len() as u32;
+                let areas = Layout::default()
+                    .direction(*direction)
+                    .constraints(panes.iter().map(|_| Constraint::Ratio(1, count)))
+                    .split(area);
+                panes
+                    .iter()
+                    .zip(areas.iter())
+                    .flat_map(|(pane, area)| pane.areas(*area))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// The view to focus from `focused` in `direction`, given the areas they were last drawn in.
+/// The closest pane is taken, or of those the one lining up most with the focused one.
+pub fn neighbour(areas: &[(usize, Rect)], focused: usize, direction: PaneFocus) -> Option<usize> {
+    let (_, from) = areas.iter().find(|(view, _)| *view == focused)?;
+
+    areas
+        .iter()
+        .filter(|(view, _)| *view != focused)
+        .filter_map(|(view, to)| {
+            let (distance, overlap) = match direction {
+                PaneFocus::Left => (from.left().checked_sub(to.right())?, overlap_y(*from, *to)),
+                PaneFocus::Right => (to.left().checked_sub(from.right())?, overlap_y(*from, *to)),
+                PaneFocus::Up => (from.top().checked_sub(to.bottom())?, overlap_x(*from, *to)),
+                PaneFocus::Down => (to.top().checked_sub(from.bottom())?, overlap_x(*from, *to)),
+                PaneFocus::Next => return None,
+            };
+            Some((*view, distance, overlap))
+        })
+        .min_by_key(|(_, distance, overlap)| (*distance, std::cmp::Reverse(*overlap)))
+        .map(|(view, _, _)| view)
+}
+
+fn overlap_x(a: Rect, b: Rect) -> u16 {
+    a.right()
+        .min(b.right())
+        .saturating_sub(a.left().max(b.left()))
+}
+
+fn overlap_y(a: Rect, b: Rect) -> u16 {
+    a.bottom()
+        .min(b.bottom())
+        .saturating_sub(a.top().max(b.top()))
+}
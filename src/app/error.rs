@@ -8,6 +8,10 @@ pub enum AppError {
     IoError(#[from] std::io::Error),
     #[error("File error: {0}")]
     FileError(#[from] super::file::Error),
+    #[error("Keymap error: {0}")]
+    KeymapError(#[from] super::keymap::KeymapError),
+    #[error("Unknown theme `{0}`, expected one of: {names}", names = super::theme::Theme::NAMES.join(", "))]
+    UnknownTheme(String),
 }
 
 impl std::fmt::Debug for AppError {
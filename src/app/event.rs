@@ -1,9 +1,14 @@
 use log::debug;
-use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+    MouseEventKind,
+};
+use ratatui::layout::Direction;
 use std::io;
-use tokio::time::Duration;
+use tokio::time::{Duration, Instant};
 
-use super::AppMode;
+use super::keymap::{Action, KeyChord, KeyResult, Keymap};
+use super::{AppMode, FoldAction};
 
 #[derive(Debug)]
 pub enum Delta {
@@ -22,39 +27,166 @@ impl From<&Delta> for isize {
     }
 }
 
+/// Edits applied to the selection of visual mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Delete,
+    /// Copy to the clipboard
+    Yank,
+    /// Comment the lines out, or back in when they all are
+    Comment,
+    Indent,
+    Dedent,
+}
+
+/// Where focus moves among the split panes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaneFocus {
+    /// The pane after the focused one, wrapping around
+    Next,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// What the left mouse button did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseAction {
+    Click,
+    /// A second click on the same cell soon after the first
+    DoubleClick,
+    /// Moving with the button held
+    Drag,
+    Release,
+}
+
 #[derive(Debug)]
 pub enum AppEvent {
     ChangeMode(AppMode),
-    Exit,
+    /// Quit, asking first when buffers have unsaved changes unless `force`
+    Exit {
+        force: bool,
+    },
     Submit,
-    Load,
+    Load {
+        force: bool,
+    },
     CursorY(Delta),
     CursorX(Delta),
+    CursorTop,
+    CursorBottom,
     ScrollX(Delta),
     ScrollY(Delta),
     TerminalResize,
     LoadSpec,
+    Validate,
     Info,
-    Write,
+    /// Write the current buffer, over changes made on disk if `force`
+    Write {
+        force: bool,
+    },
+    /// Write every buffer with unsaved changes
+    WriteAll,
+    /// Another program changed the file of the buffer at the index
+    FileChanged(usize),
+    /// Load the buffer at the index from disk again, throwing away its changes
+    Reload(usize),
+    Fold(FoldAction),
+    ToggleOutline,
+    FocusNext,
+    /// Edit the next open buffer, or the previous one when `reverse`
+    NextBuffer {
+        reverse: bool,
+    },
+    /// Open the picker of open buffers
+    BufferList,
+    /// Split the focused pane, laying the two out in the direction
+    Split(Direction),
+    FocusPane(PaneFocus),
+    ClosePane,
+    /// Close every pane but the focused one
+    OnlyPane,
+    /// Open the overlay listing bindings and commands
+    Help,
+    CopyPath,
+    /// Open the search prompt
+    Search {
+        backward: bool,
+    },
+    /// Move to the next match of the last search, or the previous one when `reverse`
+    SearchNext {
+        reverse: bool,
+    },
+    SearchScope,
+    /// Open the command line for a `:` command
+    CommandLine,
+    Complete,
+    /// Select the node around the selection, or go back to the one before
+    ExpandSelection,
+    ShrinkSelection,
+    Operator(Operator),
+    /// Pick the register of the next yank or paste
+    Register(char),
+    Yank,
+    /// Paste after the entry at the cursor, or into it as a child
+    Paste {
+        child: bool,
+    },
     DumpDebug,
     Raw(KeyEvent),
+    /// The mouse at a cell of the terminal
+    Mouse {
+        action: MouseAction,
+        column: u16,
+        row: u16,
+    },
+}
+
+impl AppEvent {
+    /// Whether handling the event can change the text of the focused buffer.
+    pub fn edits(&self) -> bool {
+        matches!(self, AppEvent::Operator(_) | AppEvent::Paste { .. })
+    }
 }
 
-pub fn handle_event(mode: &AppMode) -> io::Result<Option<AppEvent>> {
+/// Keys typed so far that do not make an event yet.
+#[derive(Debug, Default)]
+pub struct Pending {
+    /// A partially typed sequence, e.g. the first `g` of `gg`
+    keys: Vec<KeyChord>,
+    /// The next key names a register, e.g. the `a` of `"ayy`
+    register: bool,
+    /// When and where the last click was, to tell double clicks
+    last_click: Option<(Instant, u16, u16)>,
+}
+
+/// Lines or columns scrolled by a turn of the mouse wheel.
+const WHEEL_STEP: usize = 3;
+
+/// Longest time between the clicks of a double click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// Poll for a terminal event and translate it through the keymap.
+///
+/// `pending` holds what was typed of a key sequence between calls.
+pub fn handle_event(
+    mode: &AppMode,
+    keymap: &Keymap,
+    pending: &mut Pending,
+) -> io::Result<Option<AppEvent>> {
     match event::poll(Duration::from_millis(10)) {
         Ok(true) => {
             let event = match event::read()? {
                 Event::Resize(_, _) => Some(AppEvent::TerminalResize),
-                Event::Key(key_event)
-                    if key_event.kind == KeyEventKind::Press && *mode == AppMode::Normal =>
-                {
-                    handle_normal_mode(key_event)
+                Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                    handle_key(mode, keymap, pending, key_event)
                 }
                 Event::Key(key_event) => match mode {
-                    AppMode::Normal => None,
-                    AppMode::Input => Some(handle_input_mode(key_event)),
-                    AppMode::Command => handle_command_mode(key_event),
+                    AppMode::Input => Some(AppEvent::Raw(key_event)),
+                    AppMode::Normal | AppMode::Command | AppMode::Visual => None,
                 },
+                Event::Mouse(mouse_event) => handle_mouse(mode, pending, mouse_event),
                 _ => None,
             };
             debug!("Generating event. Mode: {mode:?}, Event: {event:?}");
@@ -64,43 +196,71 @@ pub fn handle_event(mode: &AppMode) -> io::Result<Option<AppEvent>> {
     }
 }
 
-fn handle_normal_mode(event: KeyEvent) -> Option<AppEvent> {
-    let code = event.code;
-    let modifiers = event.modifiers;
-    match (code, modifiers) {
-        (KeyCode::Enter, _) => Some(AppEvent::ChangeMode(AppMode::Input)),
-        (KeyCode::Char('q'), _) => Some(AppEvent::Exit),
-        (KeyCode::Char('l'), KeyModifiers::CONTROL) => Some(AppEvent::Load),
-        (KeyCode::Char('s'), KeyModifiers::CONTROL) => Some(AppEvent::Write),
-        (KeyCode::Char('s'), KeyModifiers::SHIFT) => Some(AppEvent::LoadSpec),
-        (KeyCode::Char('K'), KeyModifiers::SHIFT) => Some(AppEvent::ScrollY(Delta::Dec(1))),
-        (KeyCode::Char('J'), KeyModifiers::SHIFT) => Some(AppEvent::ScrollY(Delta::Inc(1))),
-        (KeyCode::Char('H'), KeyModifiers::SHIFT) => Some(AppEvent::ScrollX(Delta::Dec(1))),
-        (KeyCode::Char('L'), KeyModifiers::SHIFT) => Some(AppEvent::ScrollX(Delta::Inc(1))),
-        (KeyCode::Up | KeyCode::Char('k'), _) => Some(AppEvent::CursorY(Delta::Dec(1))),
-        (KeyCode::Down | KeyCode::Char('j'), _) => Some(AppEvent::CursorY(Delta::Inc(1))),
-        (KeyCode::Left | KeyCode::Char('h'), _) => Some(AppEvent::CursorX(Delta::Dec(1))),
-        (KeyCode::Right | KeyCode::Char('l'), _) => Some(AppEvent::CursorX(Delta::Inc(1))),
-        (KeyCode::PageUp, KeyModifiers::SHIFT) => Some(AppEvent::ScrollX(Delta::Dec(10))),
-        (KeyCode::PageDown, KeyModifiers::SHIFT) => Some(AppEvent::ScrollX(Delta::Inc(10))),
-        (KeyCode::PageUp, _) => Some(AppEvent::ScrollY(Delta::Dec(10))),
-        (KeyCode::PageDown, _) => Some(AppEvent::ScrollY(Delta::Inc(10))),
-        (KeyCode::Char('i'), _) => Some(AppEvent::Info),
-        (KeyCode::Char('d'), KeyModifiers::CONTROL) => Some(AppEvent::DumpDebug),
-        _ => None,
+fn handle_key(
+    mode: &AppMode,
+    keymap: &Keymap,
+    pending: &mut Pending,
+    event: KeyEvent,
+) -> Option<AppEvent> {
+    // Register names are taken as typed, whatever they are bound to
+    if pending.register {
+        pending.register = false;
+        return match event.code {
+            KeyCode::Char(name) => Some(AppEvent::Register(name)),
+            _ => None,
+        };
     }
-}
 
-fn handle_input_mode(event: KeyEvent) -> AppEvent {
-    let code = event.code;
-    let is_key_press = event.kind == KeyEventKind::Press;
-    match code {
-        KeyCode::Esc if is_key_press => AppEvent::ChangeMode(AppMode::Normal),
-        KeyCode::Enter if is_key_press => AppEvent::Submit,
-        _ => AppEvent::Raw(event),
+    pending.keys.push(event.into());
+    match (keymap.resolve(mode, &mut pending.keys), mode) {
+        (KeyResult::Action(Action::SelectRegister), _) => {
+            pending.register = true;
+            None
+        }
+        (KeyResult::Action(action), _) => Some(keymap.event(action)),
+        // Unbound keys are typed text in input and command mode
+        (KeyResult::Unbound, AppMode::Input | AppMode::Command) => Some(AppEvent::Raw(event)),
+        (KeyResult::Pending | KeyResult::Unbound, _) => None,
     }
 }
 
-fn handle_command_mode(_event: KeyEvent) -> Option<AppEvent> {
-    None
+// The wheel scrolls, sideways with shift. Only the left button is used otherwise.
+fn handle_mouse(mode: &AppMode, pending: &mut Pending, event: MouseEvent) -> Option<AppEvent> {
+    let sideways = event.modifiers.contains(KeyModifiers::SHIFT);
+    let action = match event.kind {
+        MouseEventKind::ScrollDown if sideways => {
+            return Some(AppEvent::ScrollX(Delta::Inc(WHEEL_STEP)));
+        }
+        MouseEventKind::ScrollUp if sideways => {
+            return Some(AppEvent::ScrollX(Delta::Dec(WHEEL_STEP)));
+        }
+        MouseEventKind::ScrollDown => return Some(AppEvent::ScrollY(Delta::Inc(WHEEL_STEP))),
+        MouseEventKind::ScrollUp => return Some(AppEvent::ScrollY(Delta::Dec(WHEEL_STEP))),
+        MouseEventKind::ScrollRight => return Some(AppEvent::ScrollX(Delta::Inc(WHEEL_STEP))),
+        MouseEventKind::ScrollLeft => return Some(AppEvent::ScrollX(Delta::Dec(WHEEL_STEP))),
+        MouseEventKind::Down(MouseButton::Left) => {
+            let now = Instant::now();
+            // Clicks in command mode pick from a list, they never select
+            let double = *mode != AppMode::Command
+                && pending.last_click.is_some_and(|(at, column, row)| {
+                    now.duration_since(at) < DOUBLE_CLICK
+                        && column == event.column
+                        && row == event.row
+                });
+            pending.last_click = (!double).then_some((now, event.column, event.row));
+            if double {
+                MouseAction::DoubleClick
+            } else {
+                MouseAction::Click
+            }
+        }
+        MouseEventKind::Drag(MouseButton::Left) => MouseAction::Drag,
+        MouseEventKind::Up(MouseButton::Left) => MouseAction::Release,
+        _ => return None,
+    };
+    Some(AppEvent::Mouse {
+        action,
+        column: event.column,
+        row: event.row,
+    })
 }
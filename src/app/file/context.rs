@@ -0,0 +1,284 @@
+/// Where a position is in a manifest.
+///
+/// This is worked out from the text alone, so that it also works on documents that are being
+/// typed and do not parse, e.g. a key without its `:` yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Context {
+    pub kind: Option<String>,
+    pub api_version: Option<String>,
+    /// Keys from the document root to the map holding the position. Sequences are skipped, as
+    /// schemas look through arrays to their items.
+    pub parents: Vec<String>,
+    /// Keys already in that map
+    pub siblings: Vec<String>,
+    pub focus: Focus,
+    /// Byte offset where the key or value starts
+    pub start: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Focus {
+    /// On a key, or where one can be typed
+    Key(String),
+    /// After `key:`, with the value typed so far
+    Value { key: String, value: String },
+}
+
+/// A line split into its indentation, `- ` indicators and content.
+struct Line<'a> {
+    /// Column of the first `-`, or of the content when there is none
+    indent: usize,
+    /// Column of the content after any `- `
+    column: usize,
+    items: bool,
+    /// The key, if the content is `key:` or `key: value`
+    key: Option<&'a str>,
+    /// Content after `key:`, empty when the value is on the lines below
+    value: &'a str,
+    content: &'a str,
+}
+
+impl<'a> Line<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let content = line.trim_start_matches(' ');
+        if content.trim().is_empty() || content.starts_with('#') {
+            return None;
+        }
+        let indent = line.len() - content.len();
+
+        let mut column = indent;
+        let mut content = content;
+        while let Some(rest) = content
+            .strip_prefix('-')
+            .filter(|r| r.is_empty() || r.starts_with(' '))
+        {
+            let trimmed = rest.trim_start_matches(' ');
+            column += content.len() - trimmed.len();
+            content = trimmed;
+        }
+
+        let (key, value) = match split_key(content) {
+            Some((key, value)) => (Some(key), value),
+            None => (None, ""),
+        };
+        // Values can be followed by a comment
+        let value = value.split(" #").next().unwrap_or_default().trim();
+
+        Some(Line {
+            indent,
+            column,
+            items: column > indent,
+            key,
+            value,
+            content,
+        })
+    }
+
+    // A key whose value is on the lines below
+    fn opens_block(&self) -> bool {
+        self.key.is_some() && (self.value.is_empty() || self.value.starts_with(['|', '>']))
+    }
+}
+
+// `key: value` or `key:` split at the colon
+fn split_key(content: &str) -> Option<(&str, &str)> {
+    let colon = content
+        .match_indices(':')
+        .map(|(i, _)| i)
+        .find(|&i| content[i + 1..].is_empty() || content[i + 1..].starts_with(' '))?;
+    Some((unquote(content[..colon].trim()), &content[colon + 1..]))
+}
+
+fn unquote(text: &str) -> &str {
+    ['"', '\'']
+        .into_iter()
+        .find_map(|q| text.strip_prefix(q).and_then(|t| t.strip_suffix(q)))
+        .unwrap_or(text)
+}
+
+/// The context of `offset` in `text`, `None` if it is in a comment or a block scalar.
+pub fn context_at(text: &str, offset: usize) -> Option<Context> {
+    let offset = offset.min(text.len());
+    let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[offset..].find('\n').map_or(text.len(), |i| offset + i);
+    let line_text = &text[line_start..line_end];
+    let cursor = offset - line_start;
+
+    // Lines of the document holding the position, split at `---`
+    let all: Vec<(usize, &str)> = line_offsets(text).collect();
+    let current = all.iter().position(|(start, _)| *start == line_start)?;
+    let is_separator = |line: &str| line.starts_with("---") || line.starts_with("...");
+    let first = all[..current]
+        .iter()
+        .rposition(|(_, line)| is_separator(line))
+        .map_or(0, |i| i + 1);
+    let last = all[current..]
+        .iter()
+        .position(|(_, line)| is_separator(line))
+        .map_or(all.len(), |i| current + i);
+    let above = &all[first..current];
+    let below = &all[current + 1..last];
+
+    // An empty line is a key about to be typed at the cursor
+    let line = Line::parse(line_text).unwrap_or(Line {
+        indent: cursor,
+        column: cursor,
+        items: false,
+        key: None,
+        value: "",
+        content: "",
+    });
+    if line_text.trim_start().starts_with('#') || in_block_scalar(above, line.indent) {
+        return None;
+    }
+
+    let colon = line.column + key_end(line.content);
+    let (focus, start) = match line.key {
+        Some(key) if cursor > colon => {
+            let after = &line_text[colon + 1..];
+            let value_start = colon + 1 + after.len() - after.trim_start().len();
+            let value = Focus::Value {
+                key: key.to_string(),
+                value: line.value.to_string(),
+            };
+            (value, line_start + value_start.min(line_text.len()))
+        }
+        Some(key) => (Focus::Key(key.to_string()), line_start + line.column),
+        None => (
+            Focus::Key(line.content.trim().to_string()),
+            line_start + line.column.min(line_text.len()),
+        ),
+    };
+
+    let (parents, siblings) = ancestry(above, below, &line);
+
+    Some(Context {
+        kind: root_value(&all[first..last], "kind"),
+        api_version: root_value(&all[first..last], "apiVersion"),
+        parents,
+        siblings,
+        focus,
+        start,
+    })
+}
+
+fn key_end(content: &str) -> usize {
+    content
+        .match_indices(':')
+        .map(|(i, _)| i)
+        .find(|&i| content[i + 1..].is_empty() || content[i + 1..].starts_with(' '))
+        .unwrap_or(content.len())
+}
+
+fn line_offsets(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut start = 0;
+    text.split('\n').map(move |line| {
+        let line_start = start;
+        start += line.len() + 1;
+        (line_start, line)
+    })
+}
+
+// Whether a line indented by `indent` is content of a `|` or `>` scalar above it
+fn in_block_scalar(above: &[(usize, &str)], indent: usize) -> bool {
+    above
+        .iter()
+        .rev()
+        .filter_map(|(_, line)| Line::parse(line))
+        .find(|line| line.column < indent || (line.items && line.indent < indent))
+        .is_some_and(|line| line.key.is_some() && line.value.starts_with(['|', '>']))
+}
+
+// Keys of the maps holding `line`, outermost first, and the keys next to it
+fn ancestry(
+    above: &[(usize, &str)],
+    below: &[(usize, &str)],
+    line: &Line<'_>,
+) -> (Vec<String>, Vec<String>) {
+    let mut parents = vec![];
+    let mut siblings = vec![];
+    let mut column = line.column;
+    // Lines of the map holding `line` are collected until its parent is found
+    let mut same_map = true;
+    // In sequences that are not indented, the parent key is in the same column as the `- `
+    let mut in_item = line.items;
+    if line.items {
+        column = line.indent;
+        same_map = false;
+    }
+
+    for (_, text) in above.iter().rev() {
+        let Some(above) = Line::parse(text) else {
+            continue;
+        };
+
+        if above.column == column && !in_item {
+            if same_map && let Some(key) = above.key {
+                siblings.push(key.to_string());
+            }
+            // The first entry of a map in a sequence item, the map continues on this line
+            if above.items {
+                column = above.indent;
+                in_item = true;
+                same_map = false;
+            }
+            continue;
+        }
+        if in_item && above.items && above.indent == column {
+            // Another item of the same sequence
+            continue;
+        }
+        if above.column > column {
+            continue;
+        }
+
+        let Some(key) = above.key.filter(|_| above.opens_block()) else {
+            // An item whose content starts on the next line, `-` on its own
+            if above.items && above.content.is_empty() {
+                column = above.indent;
+                in_item = true;
+                same_map = false;
+                continue;
+            }
+            break;
+        };
+
+        parents.push(key.to_string());
+        same_map = false;
+        in_item = false;
+        column = above.column;
+        if above.items {
+            column = above.indent;
+            in_item = true;
+        }
+    }
+    parents.reverse();
+
+    // Keys below, until the map ends
+    for (_, text) in below {
+        let Some(below) = Line::parse(text) else {
+            continue;
+        };
+        if below.column < line.column || (below.items && below.indent < line.column) {
+            break;
+        }
+        if below.column == line.column
+            && !below.items
+            && let Some(key) = below.key
+        {
+            siblings.push(key.to_string());
+        }
+    }
+
+    (parents, siblings)
+}
+
+// A `key: value` at the root of the document
+fn root_value(lines: &[(usize, &str)], key: &str) -> Option<String> {
+    lines
+        .iter()
+        .filter_map(|(_, text)| Line::parse(text))
+        .find(|line| line.column == 0 && line.key == Some(key))
+        .map(|line| unquote(line.value).to_string())
+        .filter(|value| !value.is_empty())
+}
@@ -1,7 +1,9 @@
+use super::fold::Folds;
 use super::{SyntaxNode, TokenAtOffset};
 use rowan::{NodeOrToken, TextSize, WalkEvent};
 
-pub(crate) fn line_at_cursor(tree: &SyntaxNode, cursor: u32) -> usize {
+/// Visible line of `cursor`, lines in folds are not counted.
+pub(crate) fn line_at_cursor(tree: &SyntaxNode, folds: &Folds, cursor: u32) -> usize {
     let mut line_count = 0;
 
     for event in tree.preorder_with_tokens() {
@@ -11,6 +13,9 @@ pub(crate) fn line_at_cursor(tree: &SyntaxNode, cursor: u32) -> usize {
             if token.text_range().contains(TextSize::new(cursor)) {
                 break;
             }
+            if folds.hides(token) {
+                continue;
+            }
             let text = token.text();
             let without_newlines = text.replace('\n', "");
             let newlines = text.len() - without_newlines.len();
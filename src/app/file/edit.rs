@@ -0,0 +1,458 @@
+use rowan::NodeOrToken;
+use std::fmt::Write as _;
+use std::ops::Range;
+use yaml_parser::{SyntaxKind, SyntaxNode, SyntaxToken};
+
+use super::Error;
+use super::query::Target;
+use super::value::{Scalar, ScalarStyle, ScalarType, ValueKind};
+use crate::api_client::SchemaType;
+
+fn is_entry(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::BLOCK_MAP_ENTRY | SyntaxKind::BLOCK_SEQ_ENTRY
+    )
+}
+
+// The node two levels up, e.g. BLOCK_MAP -> BLOCK -> BLOCK_SEQ_ENTRY
+fn grandparent(node: &SyntaxNode) -> Option<SyntaxNode> {
+    node.parent().and_then(|p| p.parent())
+}
+
+/// The closest block map or sequence entry containing `token`.
+///
+/// When the entry is the only entry of a map that is itself a sequence item (e.g. `- name: x`),
+/// the sequence item is returned instead so that no empty `- ` is left behind.
+pub(crate) fn entry_at(token: &SyntaxToken) -> Option<SyntaxNode> {
+    let entry = token.parent_ancestors().find(|n| is_entry(n.kind()))?;
+
+    if entry.kind() == SyntaxKind::BLOCK_MAP_ENTRY
+        && let Some(map) = entry.parent()
+        && map.children().filter(|n| is_entry(n.kind())).count() == 1
+        && let Some(seq_entry) = grandparent(&map)
+        && seq_entry.kind() == SyntaxKind::BLOCK_SEQ_ENTRY
+    {
+        return Some(seq_entry);
+    }
+
+    Some(entry)
+}
+
+/// Byte range of `text` to remove in order to delete `entry` along with its line.
+pub(crate) fn deletion_range(text: &str, entry: &SyntaxNode) -> Range<usize> {
+    let range = entry.text_range();
+    let (start, end): (usize, usize) = (range.start().into(), range.end().into());
+
+    let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[end..].find('\n').map_or(text.len(), |i| end + i);
+    let rest_is_trivia = {
+        let rest = text[end..line_end].trim_start();
+        rest.is_empty() || rest.starts_with('#')
+    };
+
+    if text[line_start..start].trim().is_empty() {
+        // The entry starts its line, remove the whole lines it covers
+        if rest_is_trivia {
+            return line_start..(line_end + 1).min(text.len());
+        }
+        return start..end;
+    }
+
+    // The entry follows an indicator such as `- `. Pull the next entry up onto this line.
+    match entry.next_sibling().filter(|n| is_entry(n.kind())) {
+        Some(next) => start..next.text_range().start().into(),
+        None => start..end,
+    }
+}
+
+/// A replacement of a byte range of the file.
+pub(crate) type TextEdit = (Range<usize>, String);
+
+// The whole lines `range` touches, without the final line break
+fn line_range(text: &str, range: &Range<usize>) -> Range<usize> {
+    let start = text[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let end = text[range.end..]
+        .find('\n')
+        .map_or(text.len(), |i| range.end + i);
+    start..end
+}
+
+/// Comment out the lines `range` touches at their smallest indentation, or remove the comment
+/// markers when every line is already commented. Blank lines are left alone.
+pub(crate) fn comment_edit(text: &str, range: &Range<usize>) -> TextEdit {
+    let lines = line_range(text, range);
+    let content = text[lines.clone()].split('\n').collect::<Vec<_>>();
+    let filled = || content.iter().filter(|line| !line.trim().is_empty());
+
+    let commented = filled().all(|line| line.trim_start().starts_with('#'));
+    let column = filled()
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    let replaced = content
+        .iter()
+        .map(|line| {
+            if line.trim().is_empty() {
+                (*line).to_string()
+            } else if commented {
+                let indent = line.len() - line.trim_start().len();
+                let rest = &line[indent + 1..];
+                format!(
+                    "{}{}",
+                    &line[..indent],
+                    rest.strip_prefix(' ').unwrap_or(rest)
+                )
+            } else {
+                format!("{}# {}", &line[..column], &line[column..])
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    (lines, replaced)
+}
+
+/// Add `indent` spaces in front of the lines `range` touches, or remove up to that many when
+/// `dedent`. Blank lines are left alone.
+pub(crate) fn indent_edit(
+    text: &str,
+    range: &Range<usize>,
+    indent: usize,
+    dedent: bool,
+) -> TextEdit {
+    let lines = line_range(text, range);
+    let replaced = text[lines.clone()]
+        .split('\n')
+        .map(|line| {
+            if line.trim().is_empty() {
+                line.to_string()
+            } else if dedent {
+                let spaces = (line.len() - line.trim_start_matches(' ').len()).min(indent);
+                line[spaces..].to_string()
+            } else {
+                format!("{}{line}", " ".repeat(indent))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    (lines, replaced)
+}
+
+/// Edits that set `target` to `text`, or add the keys leading to it. `schema` is the type the
+/// schema asks for at the target, when known.
+///
+/// See [`value_text`] for how the value is quoted.
+pub(crate) fn set_edit(
+    source: &str,
+    target: &Target,
+    text: &str,
+    indent: usize,
+    schema: Option<&SchemaType<'_>>,
+) -> Result<TextEdit, Error> {
+    match target {
+        Target::Found(value) => match &value.kind {
+            ValueKind::Scalar(scalar) if scalar.style == ScalarStyle::Block => {
+                let token = scalar_token(&value.node, SyntaxKind::BLOCK_SCALAR_TEXT)
+                    .ok_or(Error::NotAScalar(value.type_name()))?;
+                Ok((range(&token), block_text(source, &token, text, indent)))
+            }
+            ValueKind::Scalar(scalar) => {
+                let token = scalar_token(&value.node, token_kind(scalar.style))
+                    .ok_or(Error::NotAScalar(value.type_name()))?;
+                let flow = in_flow(&value.node);
+                Ok((range(&token), value_text(text, Some(scalar), flow, schema)))
+            }
+            // An empty sequence item, `- `
+            ValueKind::Null => {
+                let end = usize::from(value.node.text_range().end());
+                Ok((
+                    end..end,
+                    format!(" {}", value_text(text, None, false, schema)),
+                ))
+            }
+            ValueKind::Map(_) | ValueKind::Seq(_) | ValueKind::Alias => {
+                Err(Error::NotAScalar(value.type_name()))
+            }
+        },
+        Target::Missing { map, keys } => {
+            let ValueKind::Map(entries) = &map.kind else {
+                return Err(Error::CanNotInsert(first_line(&map.node)));
+            };
+
+            if map.node.kind() == SyntaxKind::FLOW {
+                let r_brace = map
+                    .node
+                    .children()
+                    .filter(|node| node.kind() == SyntaxKind::FLOW_MAP)
+                    .flat_map(|node| node.children_with_tokens())
+                    .filter_map(NodeOrToken::into_token)
+                    .find(|token| token.kind() == SyntaxKind::R_BRACE)
+                    .ok_or_else(|| Error::CanNotInsert(first_line(&map.node)))?;
+
+                let new = flow_entries(keys, text, schema);
+                if let Some(last) = entries.last() {
+                    let end = usize::from(last.node.text_range().end());
+                    return Ok((end..end, format!(", {new}")));
+                }
+                let start = usize::from(r_brace.text_range().start());
+                return Ok((start..start, new));
+            }
+
+            // Maps in flow sequences, `[a: b]`, have no braces to add to
+            let Some(first) = entries
+                .first()
+                .filter(|_| map.node.kind() == SyntaxKind::BLOCK)
+            else {
+                return Err(Error::CanNotInsert(first_line(&map.node)));
+            };
+            let last = entries.last().unwrap_or(first);
+            let column = column(source, first.node.text_range().start().into());
+            let at = end_of_line(source, last.node.text_range().end().into());
+            let new = block_entries(keys, text, column, indent, schema);
+            Ok((at..at, format!("\n{new}")))
+        }
+        Target::Empty { entry, keys } => {
+            let colon = entry
+                .node
+                .children_with_tokens()
+                .filter_map(NodeOrToken::into_token)
+                .find(|token| token.kind() == SyntaxKind::COLON);
+            let at = colon.map_or(entry.key_node.text_range().end(), |c| c.text_range().end());
+            let at = usize::from(at);
+
+            let new = if keys.is_empty() {
+                format!(" {}", value_text(text, None, in_flow(&entry.node), schema))
+            } else if in_flow(&entry.node) {
+                format!(" {{{}}}", flow_entries(keys, text, schema))
+            } else {
+                let column = column(source, entry.node.text_range().start().into()) + indent;
+                format!("\n{}", block_entries(keys, text, column, indent, schema))
+            };
+            Ok((at..at, new))
+        }
+    }
+}
+
+fn range(token: &SyntaxToken) -> Range<usize> {
+    token.text_range().start().into()..token.text_range().end().into()
+}
+
+fn token_kind(style: ScalarStyle) -> SyntaxKind {
+    match style {
+        ScalarStyle::Plain => SyntaxKind::PLAIN_SCALAR,
+        ScalarStyle::SingleQuoted => SyntaxKind::SINGLE_QUOTED_SCALAR,
+        ScalarStyle::DoubleQuoted => SyntaxKind::DOUBLE_QUOTED_SCALAR,
+        ScalarStyle::Block => SyntaxKind::BLOCK_SCALAR_TEXT,
+    }
+}
+
+// The scalar token of a value, skipping anchors and tags in front of it
+fn scalar_token(node: &SyntaxNode, kind: SyntaxKind) -> Option<SyntaxToken> {
+    node.descendants_with_tokens()
+        .filter_map(NodeOrToken::into_token)
+        .find(|token| token.kind() == kind)
+}
+
+fn in_flow(node: &SyntaxNode) -> bool {
+    node.ancestors().any(|n| {
+        matches!(
+            n.kind(),
+            SyntaxKind::FLOW_MAP | SyntaxKind::FLOW_SEQ | SyntaxKind::FLOW_MAP_ENTRY
+        )
+    })
+}
+
+fn first_line(node: &SyntaxNode) -> String {
+    node.to_string()
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+pub(crate) fn column(source: &str, offset: usize) -> usize {
+    offset - source[..offset].rfind('\n').map_or(0, |i| i + 1)
+}
+
+// Past a trailing comment on the line ending at `offset`, so new lines do not split it off
+pub(crate) fn end_of_line(source: &str, offset: usize) -> usize {
+    let line_end = source[offset..]
+        .find('\n')
+        .map_or(source.len(), |i| offset + i);
+    let rest = source[offset..line_end].trim_start();
+    if rest.is_empty() || rest.starts_with('#') {
+        line_end
+    } else {
+        offset
+    }
+}
+
+fn block_entries(
+    keys: &[String],
+    text: &str,
+    column: usize,
+    indent: usize,
+    schema: Option<&SchemaType<'_>>,
+) -> String {
+    let mut entries = String::new();
+    for (depth, key) in keys.iter().enumerate() {
+        if depth > 0 {
+            entries.push('\n');
+        }
+        entries.push_str(&" ".repeat(column + depth * indent));
+        entries.push_str(&scalar_text(key, false));
+        entries.push(':');
+    }
+    entries.push(' ');
+    entries.push_str(&value_text(text, None, false, schema));
+    entries
+}
+
+fn flow_entries(keys: &[String], text: &str, schema: Option<&SchemaType<'_>>) -> String {
+    let Some((key, rest)) = keys.split_first() else {
+        return value_text(text, None, true, schema);
+    };
+    let value = if rest.is_empty() {
+        value_text(text, None, true, schema)
+    } else {
+        format!("{{{}}}", flow_entries(rest, text, schema))
+    };
+    format!("{}: {value}", scalar_text(key, true))
+}
+
+// Content lines of a `|` or `>` scalar, indented like the ones they replace
+fn block_text(source: &str, token: &SyntaxToken, text: &str, indent: usize) -> String {
+    let column = token
+        .text()
+        .split('\n')
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches(' ').len())
+        .min()
+        .unwrap_or_else(|| {
+            let start = token.text_range().start().into();
+            let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+            let line = &source[line_start..start];
+            line.len() - line.trim_start_matches(' ').len() + indent
+        });
+
+    text.trim_end_matches('\n')
+        .split('\n')
+        .map(|line| {
+            if line.is_empty() {
+                "\n".to_string()
+            } else {
+                format!("\n{}{line}", " ".repeat(column))
+            }
+        })
+        .collect()
+}
+
+/// `text` as a flow scalar, plain where that reads back as the same text.
+pub(crate) fn scalar_text(text: &str, flow: bool) -> String {
+    if is_plain_safe(text, flow) {
+        text.to_string()
+    } else {
+        double_quote(text)
+    }
+}
+
+/// `text` as a new value in place of `previous`.
+///
+/// Values already written as YAML are kept, e.g. `"5"` for a string or `[]` and `{}` for empty
+/// collections. Otherwise the value gets the type `schema` asks for, e.g. `replicas: "4"` becomes
+/// `replicas: 5`. Without a schema, it keeps the quotes of `previous` and plain strings stay
+/// strings. Strings are quoted when they would read as another type, e.g. an image tag of `1.27`.
+pub(crate) fn value_text(
+    text: &str,
+    previous: Option<&Scalar>,
+    flow: bool,
+    schema: Option<&SchemaType<'_>>,
+) -> String {
+    let typed = ScalarType::of_plain(text) != ScalarType::String;
+    let string = match schema {
+        Some(SchemaType::String(_)) => true,
+        Some(SchemaType::IntOrString) => ScalarType::of_plain(text) != ScalarType::Integer,
+        Some(
+            SchemaType::Integer
+            | SchemaType::Number
+            | SchemaType::Boolean
+            | SchemaType::Array
+            | SchemaType::Object,
+        ) => false,
+        // Only a known type changes the quoting
+        _ => previous.is_some_and(|scalar| {
+            scalar.style != ScalarStyle::Plain
+                || (typed && scalar.scalar_type() == ScalarType::String)
+        }),
+    };
+    if is_quoted(text) || (!string && is_flow_collection(text)) {
+        return text.to_string();
+    }
+
+    match previous.map(|scalar| scalar.style) {
+        Some(ScalarStyle::SingleQuoted) if (string || !typed) && !text.contains(['\n', '\\']) => {
+            format!("'{}'", text.replace('\'', "''"))
+        }
+        Some(ScalarStyle::SingleQuoted | ScalarStyle::DoubleQuoted) if string || !typed => {
+            double_quote(text)
+        }
+        _ if string && typed => double_quote(text),
+        _ => scalar_text(text, flow),
+    }
+}
+
+// A complete quoted scalar, e.g. `"5"` or `'a''s'`
+fn is_quoted(text: &str) -> bool {
+    let quote = text.chars().next().filter(|c| matches!(c, '"' | '\''));
+    quote.is_some_and(|quote| text.len() > 1 && text.ends_with(quote))
+        && yaml_parser::parse(text).is_ok()
+}
+
+// A flow sequence or map, e.g. `[]` or `{a: 1}`
+fn is_flow_collection(text: &str) -> bool {
+    ((text.starts_with('[') && text.ends_with(']'))
+        || (text.starts_with('{') && text.ends_with('}')))
+        && yaml_parser::parse(text).is_ok()
+}
+
+// Whether `text` reads back as the same string when written without quotes
+fn is_plain_safe(text: &str, flow: bool) -> bool {
+    let mut chars = text.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    // `-`, `?` and `:` are only indicators when followed by a space, e.g. `-1` is fine
+    let starts_with_indicator = match first {
+        '-' | '?' | ':' => chars.next().is_none_or(char::is_whitespace),
+        _ => ",[]{}#&*!|>'\"%@`".contains(first),
+    };
+
+    !starts_with_indicator
+        && text.trim() == text
+        && !text.chars().any(char::is_control)
+        && !text.contains(": ")
+        && !text.contains(" #")
+        && !text.ends_with(':')
+        && !(flow && text.contains([',', '[', ']', '{', '}']))
+}
+
+fn double_quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", u32::from(c));
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
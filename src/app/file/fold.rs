@@ -0,0 +1,165 @@
+use rowan::{TextRange, TextSize};
+use yaml_parser::{SyntaxKind, SyntaxNode, SyntaxToken};
+
+use super::utils::whitespace_newlines;
+
+/// What to do with the fold at the cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldAction {
+    Toggle,
+    Close,
+    Open,
+}
+
+/// A collapsed map or sequence. The first line of its entry stays visible, followed by
+/// `summary`.
+#[derive(Debug, Clone)]
+pub(crate) struct Fold {
+    /// Text that is not shown, from the end of the first line of the entry to the end of the
+    /// last line of the collection
+    pub hidden: TextRange,
+    pub summary: String,
+}
+
+/// Folds of a file, in no particular order. Folds can be nested, inner ones stay closed when the
+/// outer one is opened.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Folds(Vec<Fold>);
+
+impl Folds {
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    pub fn hides(&self, token: &SyntaxToken) -> bool {
+        let range = token.text_range();
+        self.0.iter().any(|fold| fold.hidden.contains_range(range))
+    }
+
+    /// The fold starting at `offset` whose summary is shown, folds inside other folds are not.
+    pub fn starting_at(&self, offset: TextSize) -> Option<&Fold> {
+        self.0
+            .iter()
+            .filter(|fold| fold.hidden.start() == offset)
+            .max_by_key(|fold| fold.hidden.len())
+            .filter(|fold| {
+                !self.0.iter().any(|other| {
+                    other.hidden.len() > fold.hidden.len()
+                        && other.hidden.contains_range(fold.hidden)
+                })
+            })
+    }
+
+    /// Open every fold hiding `token`.
+    pub fn reveal(&mut self, token: &SyntaxToken) {
+        let range = token.text_range();
+        self.0.retain(|fold| !fold.hidden.contains_range(range));
+    }
+
+    pub fn next_token(&self, token: &SyntaxToken) -> Option<SyntaxToken> {
+        let mut next = token.next_token();
+        while let Some(token) = &next
+            && self.hides(token)
+        {
+            next = token.next_token();
+        }
+        next
+    }
+
+    pub fn prev_token(&self, token: &SyntaxToken) -> Option<SyntaxToken> {
+        let mut prev = token.prev_token();
+        while let Some(token) = &prev
+            && self.hides(token)
+        {
+            prev = token.prev_token();
+        }
+        prev
+    }
+
+    /// Apply `action` to the innermost foldable collection around `token`. Returns whether
+    /// anything changed.
+    pub fn apply(&mut self, token: &SyntaxToken, action: FoldAction) -> bool {
+        let Some(fold) = fold_at(token) else {
+            return false;
+        };
+        let existing = self.0.iter().position(|f| f.hidden == fold.hidden);
+
+        match (existing, action) {
+            (Some(index), FoldAction::Toggle | FoldAction::Open) => {
+                self.0.remove(index);
+                true
+            }
+            (None, FoldAction::Toggle | FoldAction::Close) => {
+                self.0.push(fold);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+// Entries whose value is a block map or sequence spanning several lines, innermost first
+fn fold_at(token: &SyntaxToken) -> Option<Fold> {
+    token
+        .parent_ancestors()
+        .filter(|node| {
+            matches!(
+                node.kind(),
+                SyntaxKind::BLOCK_MAP_ENTRY | SyntaxKind::BLOCK_SEQ_ENTRY
+            )
+        })
+        .find_map(|entry| fold_entry(&entry))
+}
+
+fn fold_entry(entry: &SyntaxNode) -> Option<Fold> {
+    let block = match entry.kind() {
+        SyntaxKind::BLOCK_MAP_ENTRY => entry
+            .children()
+            .find(|node| node.kind() == SyntaxKind::BLOCK_MAP_VALUE)?
+            .first_child()?,
+        _ => entry.first_child()?,
+    };
+    let collection = block
+        .first_child()
+        .filter(|node| matches!(node.kind(), SyntaxKind::BLOCK_MAP | SyntaxKind::BLOCK_SEQ))?;
+
+    // The first line break inside the entry, the line before it stays visible
+    let start = entry
+        .descendants_with_tokens()
+        .filter_map(rowan::NodeOrToken::into_token)
+        .find(|token| whitespace_newlines(token).is_some_and(|n| n > 0))?
+        .text_range()
+        .start();
+    // Anything after the collection on its last line, e.g. a comment, is hidden with it
+    let mut end = collection.text_range().end();
+    let mut next = collection.last_token().and_then(|t| t.next_token());
+    while let Some(token) = next
+        && whitespace_newlines(&token).is_none_or(|n| n == 0)
+    {
+        end = token.text_range().end();
+        next = token.next_token();
+    }
+    if start >= end {
+        return None;
+    }
+
+    let summary = match collection.kind() {
+        SyntaxKind::BLOCK_SEQ => {
+            let items = collection
+                .children()
+                .filter(|node| node.kind() == SyntaxKind::BLOCK_SEQ_ENTRY)
+                .count();
+            if items == 1 {
+                " [1 item]".to_string()
+            } else {
+                format!(" [{items} items]")
+            }
+        }
+        _ => " {…}".to_string(),
+    };
+
+    Some(Fold {
+        hidden: TextRange::new(start, end),
+        summary,
+    })
+}
@@ -0,0 +1,301 @@
+use rowan::{NodeOrToken, TextSize};
+use std::collections::HashMap;
+use yaml_parser::{SyntaxKind, SyntaxNode, SyntaxToken};
+
+use crate::config::EditorConfig;
+
+/// Rebuild the whitespace of a file. Block collections are indented by `config.indent` per level,
+/// trailing whitespace is removed and the file ends with a single newline. Comments, scalars and
+/// blank lines between entries are kept as they are.
+pub(crate) fn format(root: &SyntaxNode, config: &EditorConfig) -> String {
+    let skipped = trailing_empty_documents(root);
+    let mut tokens: Vec<SyntaxToken> = root
+        .descendants_with_tokens()
+        .filter_map(NodeOrToken::into_token)
+        .filter(|token| {
+            !skipped
+                .iter()
+                .any(|d| d.contains(token.text_range().start()))
+        })
+        .collect();
+    // The final newline is added at the end
+    while tokens
+        .last()
+        .is_some_and(|token| token.kind() == SyntaxKind::WHITESPACE)
+    {
+        tokens.pop();
+    }
+
+    let mut formatter = Formatter {
+        source: root.to_string(),
+        config,
+        columns: HashMap::new(),
+        output: String::new(),
+    };
+
+    for (i, token) in tokens.iter().enumerate() {
+        let prev = i.checked_sub(1).and_then(|i| tokens.get(i));
+        formatter.token(token, prev, &tokens[i + 1..]);
+    }
+
+    let mut output = formatter.output;
+    output.truncate(output.trim_end_matches('\n').len());
+    output.push('\n');
+    output
+}
+
+fn is_entry(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::BLOCK_MAP_ENTRY | SyntaxKind::BLOCK_SEQ_ENTRY
+    )
+}
+
+// `---` lines at the end of a file that start documents without content or comments
+fn trailing_empty_documents(root: &SyntaxNode) -> Vec<rowan::TextRange> {
+    root.children()
+        .filter(|node| node.kind() == SyntaxKind::DOCUMENT)
+        .collect::<Vec<_>>()
+        .iter()
+        .rev()
+        .take_while(|document| {
+            document.children().next().is_none()
+                && document.children_with_tokens().all(|element| {
+                    matches!(
+                        element.kind(),
+                        SyntaxKind::DIRECTIVES_END | SyntaxKind::WHITESPACE
+                    )
+                })
+        })
+        .map(SyntaxNode::text_range)
+        .collect()
+}
+
+struct Formatter<'a> {
+    source: String,
+    config: &'a EditorConfig,
+    // Column each entry starts at in the output, by its offset in the source
+    columns: HashMap<TextSize, usize>,
+    output: String,
+}
+
+impl Formatter<'_> {
+    fn token(&mut self, token: &SyntaxToken, prev: Option<&SyntaxToken>, rest: &[SyntaxToken]) {
+        let start = token.text_range().start();
+        let column = self.output_column();
+        for entry in token
+            .parent_ancestors()
+            .take_while(|node| node.text_range().start() == start)
+            .filter(|node| is_entry(node.kind()))
+        {
+            self.columns.insert(entry.text_range().start(), column);
+        }
+
+        match token.kind() {
+            SyntaxKind::WHITESPACE => self.whitespace(token, prev, rest),
+            SyntaxKind::COMMENT => self.output.push_str(token.text().trim_end()),
+            SyntaxKind::BLOCK_SCALAR_TEXT => self.block_scalar(token),
+            _ if token.text().contains('\n') => self.multi_line(token),
+            _ => self.output.push_str(token.text()),
+        }
+    }
+
+    fn whitespace(
+        &mut self,
+        token: &SyntaxToken,
+        prev: Option<&SyntaxToken>,
+        rest: &[SyntaxToken],
+    ) {
+        let text = token.text();
+        let Some(next) = rest.first() else {
+            return;
+        };
+
+        if !text.contains('\n') {
+            // `key:   value` and `-   item` get a single space, alignment before comments is kept
+            let after_indicator =
+                prev.is_some_and(|p| matches!(p.kind(), SyntaxKind::COLON | SyntaxKind::MINUS));
+            if after_indicator && next.kind() != SyntaxKind::COMMENT {
+                self.output.push(' ');
+            } else {
+                self.output.push_str(text);
+            }
+            return;
+        }
+
+        let separator = |t: Option<&SyntaxToken>| {
+            t.is_some_and(|t| {
+                matches!(
+                    t.kind(),
+                    SyntaxKind::DIRECTIVES_END | SyntaxKind::DOCUMENT_END
+                )
+            })
+        };
+        let newlines = if self.output.is_empty() {
+            0
+        } else if separator(prev) || separator(Some(next)) {
+            1
+        } else {
+            text.matches('\n').count()
+        };
+
+        self.output.push_str(&"\n".repeat(newlines));
+        let indent = self.indent(next, rest);
+        self.output.push_str(&" ".repeat(indent));
+    }
+
+    // Indentation of a token that starts a line
+    fn indent(&self, token: &SyntaxToken, rest: &[SyntaxToken]) -> usize {
+        match token.kind() {
+            SyntaxKind::DIRECTIVES_END | SyntaxKind::DOCUMENT_END => return 0,
+            // Comments are indented like the line they precede
+            SyntaxKind::COMMENT => {
+                return rest
+                    .iter()
+                    .position(|t| !matches!(t.kind(), SyntaxKind::WHITESPACE | SyntaxKind::COMMENT))
+                    .map_or(0, |i| self.indent(&rest[i], &rest[i..]));
+            }
+            _ => {}
+        }
+
+        let start = token.text_range().start();
+        let starting_here: Vec<SyntaxNode> = token
+            .parent_ancestors()
+            .take_while(|node| node.text_range().start() == start)
+            .collect();
+
+        if let Some(entry) = starting_here
+            .iter()
+            .rev()
+            .find(|node| is_entry(node.kind()))
+        {
+            return self.entry_indent(entry);
+        }
+
+        // A value on the line after its key or `-`
+        if let Some(top) = starting_here.last() {
+            let entry = match (top.kind(), top.parent()) {
+                (SyntaxKind::BLOCK_MAP_VALUE, parent) => parent,
+                (_, Some(parent)) if parent.kind() == SyntaxKind::BLOCK_SEQ_ENTRY => Some(parent),
+                _ => None,
+            };
+            if let Some(entry) = entry {
+                return self.column(&entry) + self.config.indent;
+            }
+        }
+
+        // Continuation lines, e.g. in flow collections, move along with their entry
+        self.shifted(token, self.old_column(start))
+    }
+
+    fn entry_indent(&self, entry: &SyntaxNode) -> usize {
+        let Some(collection) = entry.parent() else {
+            return 0;
+        };
+
+        // Later entries line up with the first one
+        if let Some(first) = collection.children().find(|node| is_entry(node.kind()))
+            && first.text_range().start() != entry.text_range().start()
+        {
+            return self.column(&first);
+        }
+
+        let Some(parent) = collection.parent().and_then(|block| block.parent()) else {
+            return 0;
+        };
+        match parent.kind() {
+            SyntaxKind::BLOCK_MAP_VALUE | SyntaxKind::BLOCK_MAP_KEY => {
+                let Some(parent_entry) = parent.parent() else {
+                    return 0;
+                };
+                let flush = collection.kind() == SyntaxKind::BLOCK_SEQ
+                    && parent.kind() == SyntaxKind::BLOCK_MAP_VALUE
+                    && !self.config.indent_sequences;
+                self.column(&parent_entry) + if flush { 0 } else { self.config.indent }
+            }
+            SyntaxKind::BLOCK_SEQ_ENTRY => self.column(&parent) + self.config.indent,
+            _ => 0,
+        }
+    }
+
+    // `|` and `>` content is indented one level below its entry
+    fn block_scalar(&mut self, token: &SyntaxToken) {
+        let text = token.text();
+        let mut lines = text.split('\n');
+        self.output.push_str(lines.next().unwrap_or_default());
+        let lines: Vec<&str> = lines.collect();
+
+        let old_indent = lines
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.len() - line.trim_start_matches(' ').len())
+            .min()
+            .unwrap_or(0);
+
+        let has_indicator = token.parent().is_some_and(|scalar| {
+            scalar
+                .children_with_tokens()
+                .any(|element| element.kind() == SyntaxKind::INDENT_INDICATOR)
+        });
+        let entry = token.parent_ancestors().find(|node| is_entry(node.kind()));
+        let indent = match entry {
+            // An explicit indentation is relative to the entry, so it moves with it
+            Some(_) if has_indicator => self.shifted(token, old_indent),
+            Some(entry) => self.column(&entry) + self.config.indent,
+            None => old_indent,
+        };
+
+        for line in lines {
+            self.output.push('\n');
+            // Spaces past the indentation are content, even on otherwise empty lines
+            if line.len() > old_indent {
+                self.output.push_str(&" ".repeat(indent));
+                self.output.push_str(&line[old_indent..]);
+            }
+        }
+    }
+
+    fn multi_line(&mut self, token: &SyntaxToken) {
+        let mut lines = token.text().split('\n');
+        self.output.push_str(lines.next().unwrap_or_default());
+
+        for line in lines {
+            self.output.push('\n');
+            let content = line.trim_start_matches(' ');
+            if content.is_empty() {
+                continue;
+            }
+            let indent = self.shifted(token, line.len() - content.len());
+            self.output.push_str(&" ".repeat(indent));
+            self.output.push_str(content);
+        }
+    }
+
+    // `column` moved by as much as the entry holding `token` moved
+    fn shifted(&self, token: &SyntaxToken, column: usize) -> usize {
+        let Some(entry) = token.parent_ancestors().find(|node| is_entry(node.kind())) else {
+            return column;
+        };
+
+        let old = self.old_column(entry.text_range().start());
+        (column + self.column(&entry)).saturating_sub(old)
+    }
+
+    // Column of an entry in the output, falling back to the source
+    fn column(&self, entry: &SyntaxNode) -> usize {
+        let start = entry.text_range().start();
+        self.columns
+            .get(&start)
+            .copied()
+            .unwrap_or_else(|| self.old_column(start))
+    }
+
+    fn old_column(&self, offset: TextSize) -> usize {
+        let offset = usize::from(offset);
+        offset - self.source[..offset].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    fn output_column(&self) -> usize {
+        self.output.len() - self.output.rfind('\n').map_or(0, |i| i + 1)
+    }
+}
@@ -0,0 +1,259 @@
+use rowan::TextRange;
+use std::ops::Range;
+use yaml_parser::{SyntaxKind, SyntaxNode, SyntaxToken};
+
+use super::Error;
+use super::edit::{TextEdit, column, end_of_line};
+
+/// The shape of a fragment, which decides where it fits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Form {
+    /// `key: value` entries of a map
+    Entries,
+    /// `- value` items of a sequence
+    Items,
+    /// A scalar or flow value
+    Value,
+}
+
+impl Form {
+    fn of(node: &SyntaxNode) -> Self {
+        match node.kind() {
+            SyntaxKind::BLOCK_MAP_ENTRY | SyntaxKind::BLOCK_MAP => Form::Entries,
+            SyntaxKind::BLOCK_SEQ_ENTRY | SyntaxKind::BLOCK_SEQ => Form::Items,
+            // Wrappers take the shape of what they hold
+            SyntaxKind::BLOCK | SyntaxKind::BLOCK_MAP_VALUE => node
+                .first_child()
+                .map_or(Form::Value, |child| Form::of(&child)),
+            _ => Form::Value,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Form::Entries => "map entries",
+            Form::Items => "sequence items",
+            Form::Value => "a value",
+        }
+    }
+
+    // Name of what holds this form
+    fn container(self) -> &'static str {
+        match self {
+            Form::Entries => "a map",
+            Form::Items => "a sequence",
+            Form::Value => "a value",
+        }
+    }
+}
+
+/// Yanked YAML. The indentation of the first line is removed from every line, so that it can be
+/// indented to wherever it is pasted.
+#[derive(Debug, Clone)]
+pub struct Fragment {
+    text: String,
+    form: Form,
+}
+
+impl Fragment {
+    pub(crate) fn new(source: &str, range: TextRange, form: Form) -> Self {
+        let range: Range<usize> = range.into();
+        let column = column(source, range.start);
+        let text = source[range]
+            .split('\n')
+            .enumerate()
+            .map(|(i, line)| {
+                let spaces = (line.len() - line.trim_start_matches(' ').len()).min(column);
+                if i == 0 { line } else { &line[spaces..] }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Fragment { text, form }
+    }
+
+    pub(crate) fn of_node(source: &str, node: &SyntaxNode) -> Self {
+        Fragment::new(source, node.text_range(), Form::of(node))
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    // The text in `form`, unindented
+    fn convert(&self, form: Form) -> Result<String, Error> {
+        match (self.form, form) {
+            (from, to) if from == to => Ok(self.text.clone()),
+            (Form::Entries | Form::Value, Form::Items) => Ok(item(&self.text)),
+            // Items holding maps give up their entries
+            (Form::Items, Form::Entries) => {
+                let mut entries = vec![];
+                for item in items(&self.text) {
+                    let content = unitem(item);
+                    if !is_block_map(&content) {
+                        return Err(Error::CanNotPaste(self.form.name(), form.container()));
+                    }
+                    entries.push(content);
+                }
+                Ok(entries.join("\n"))
+            }
+            (from, to) => Err(Error::CanNotPaste(from.name(), to.container())),
+        }
+    }
+}
+
+/// What yanking at `token` takes: the entry holding it, or the sequence item when the entry is
+/// the first one on a `- ` line.
+pub(crate) fn node_at(token: &SyntaxToken) -> Option<SyntaxNode> {
+    let entry = token.parent_ancestors().find(|node| {
+        matches!(
+            node.kind(),
+            SyntaxKind::BLOCK_MAP_ENTRY | SyntaxKind::BLOCK_SEQ_ENTRY
+        )
+    })?;
+
+    // BLOCK_MAP -> BLOCK -> BLOCK_SEQ_ENTRY
+    if entry.kind() == SyntaxKind::BLOCK_MAP_ENTRY
+        && let Some(map) = entry.parent()
+        && map.first_child().as_ref() == Some(&entry)
+        && let Some(item) = map.parent().and_then(|block| block.parent())
+        && item.kind() == SyntaxKind::BLOCK_SEQ_ENTRY
+    {
+        return Some(item);
+    }
+    Some(entry)
+}
+
+/// Edit pasting `fragment` as a sibling after `target`, an entry or sequence item, or into its
+/// value when `child`. The fragment is indented to its new depth and turned into entries or items
+/// to match its neighbours.
+pub(crate) fn paste_edit(
+    source: &str,
+    target: &SyntaxNode,
+    fragment: &Fragment,
+    child: bool,
+    indent: usize,
+) -> Result<TextEdit, Error> {
+    let range: Range<usize> = target.text_range().into();
+    let target_column = column(source, range.start);
+
+    if !child {
+        let form = match target.kind() {
+            SyntaxKind::BLOCK_SEQ_ENTRY => Form::Items,
+            _ => Form::Entries,
+        };
+        let text = fragment.convert(form)?;
+        let at = end_of_line(source, range.end);
+        return Ok((at..at, format!("\n{}", indented(&text, target_column))));
+    }
+
+    let value = match target.kind() {
+        SyntaxKind::BLOCK_MAP_ENTRY => target
+            .children()
+            .find(|node| node.kind() == SyntaxKind::BLOCK_MAP_VALUE)
+            .and_then(|value| value.first_child()),
+        _ => target
+            .children()
+            .find(|node| matches!(node.kind(), SyntaxKind::BLOCK | SyntaxKind::FLOW)),
+    };
+    let content = match value {
+        Some(block) if block.kind() == SyntaxKind::BLOCK => block.first_child(),
+        value => value,
+    };
+
+    match content {
+        Some(node) if matches!(node.kind(), SyntaxKind::BLOCK_MAP | SyntaxKind::BLOCK_SEQ) => {
+            let text = fragment.convert(Form::of(&node))?;
+            let node_range: Range<usize> = node.text_range().into();
+            let at = end_of_line(source, node_range.end);
+            let column = column(source, node_range.start);
+            Ok((at..at, format!("\n{}", indented(&text, column))))
+        }
+        Some(node) => {
+            let flow = node
+                .children()
+                .any(|n| matches!(n.kind(), SyntaxKind::FLOW_MAP | SyntaxKind::FLOW_SEQ));
+            let target = if flow {
+                "a flow collection"
+            } else {
+                "a scalar"
+            };
+            Err(Error::CanNotPaste(fragment.form.name(), target))
+        }
+        // An empty value, `key:` or `- `
+        None => {
+            let text = match (target.kind(), fragment.form) {
+                (_, Form::Value) => {
+                    format!(
+                        " {}",
+                        indented(&fragment.text, target_column + indent).trim_start()
+                    )
+                }
+                (SyntaxKind::BLOCK_SEQ_ENTRY, _) => {
+                    format!(
+                        " {}",
+                        indented(&fragment.text, target_column + 2).trim_start()
+                    )
+                }
+                _ => format!("\n{}", indented(&fragment.text, target_column + indent)),
+            };
+            Ok((range.end..range.end, text))
+        }
+    }
+}
+
+fn indented(text: &str, column: usize) -> String {
+    text.split('\n')
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{}{line}", " ".repeat(column))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// `text` as a single sequence item
+fn item(text: &str) -> String {
+    format!("- {}", indented(text, 2).trim_start())
+}
+
+// Sequence items of unindented text, each starting at its `-`
+fn items(text: &str) -> Vec<&str> {
+    let mut starts = text
+        .match_indices('\n')
+        .map(|(i, _)| i + 1)
+        .filter(|i| text[*i..].starts_with('-'))
+        .collect::<Vec<_>>();
+    starts.insert(0, 0);
+    starts.push(text.len() + 1);
+    starts
+        .windows(2)
+        .map(|window| &text[window[0]..window[1] - 1])
+        .collect()
+}
+
+// Content of an item, unindented
+fn unitem(item: &str) -> String {
+    let content = item.strip_prefix('-').unwrap_or(item);
+    let content = content.strip_prefix(' ').unwrap_or(content);
+    content
+        .split('\n')
+        .enumerate()
+        .map(|(i, line)| match i {
+            0 => line,
+            _ => line.strip_prefix("  ").unwrap_or(line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn is_block_map(text: &str) -> bool {
+    yaml_parser::parse(text).is_ok_and(|root| {
+        root.descendants()
+            .find(|node| matches!(node.kind(), SyntaxKind::BLOCK | SyntaxKind::FLOW))
+            .and_then(|node| node.first_child())
+            .is_some_and(|node| node.kind() == SyntaxKind::BLOCK_MAP)
+    })
+}
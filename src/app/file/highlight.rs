@@ -0,0 +1,69 @@
+use yaml_parser::{SyntaxKind, SyntaxToken};
+
+use super::utils::ancestor_not_kind;
+use super::value::ScalarType;
+use crate::app::theme::Highlight;
+
+/// Classify a token for syntax highlighting.
+pub(crate) fn highlight(token: &SyntaxToken) -> Highlight {
+    match token.kind() {
+        SyntaxKind::COMMENT => Highlight::Comment,
+        SyntaxKind::DIRECTIVES_END | SyntaxKind::DOCUMENT_END => Highlight::DocumentMarker,
+        SyntaxKind::PERCENT
+        | SyntaxKind::DIRECTIVE_NAME
+        | SyntaxKind::YAML_VERSION
+        | SyntaxKind::DIRECTIVE_PARAM
+        | SyntaxKind::TAG_PREFIX => Highlight::Directive,
+        SyntaxKind::AMPERSAND | SyntaxKind::ASTERISK | SyntaxKind::ANCHOR_NAME => Highlight::Anchor,
+        SyntaxKind::EXCLAMATION_MARK
+        | SyntaxKind::VERBATIM_TAG
+        | SyntaxKind::TAG_CHAR
+        | SyntaxKind::TAG_HANDLE_NAMED
+        | SyntaxKind::TAG_HANDLE_SECONDARY
+        | SyntaxKind::TAG_HANDLE_PRIMARY => Highlight::Tag,
+        SyntaxKind::L_BRACE
+        | SyntaxKind::R_BRACE
+        | SyntaxKind::L_BRACKET
+        | SyntaxKind::R_BRACKET
+        | SyntaxKind::COMMA
+        | SyntaxKind::COLON
+        | SyntaxKind::MINUS
+        | SyntaxKind::QUESTION_MARK
+        | SyntaxKind::BAR
+        | SyntaxKind::GREATER_THAN
+        | SyntaxKind::PLUS
+        | SyntaxKind::INDENT_INDICATOR => Highlight::Punctuation,
+        SyntaxKind::DOUBLE_QUOTED_SCALAR | SyntaxKind::SINGLE_QUOTED_SCALAR if is_key(token) => {
+            Highlight::Key
+        }
+        SyntaxKind::DOUBLE_QUOTED_SCALAR
+        | SyntaxKind::SINGLE_QUOTED_SCALAR
+        | SyntaxKind::BLOCK_SCALAR_TEXT => Highlight::String,
+        SyntaxKind::PLAIN_SCALAR if is_key(token) => Highlight::Key,
+        SyntaxKind::PLAIN_SCALAR => plain_scalar(token.text()),
+        _ => Highlight::Text,
+    }
+}
+
+// Scalars sit in a FLOW node, whose parent tells whether it is a key
+fn is_key(token: &SyntaxToken) -> bool {
+    token
+        .parent()
+        .and_then(|node| ancestor_not_kind(node, SyntaxKind::FLOW))
+        .is_some_and(|p| {
+            matches!(
+                p.kind(),
+                SyntaxKind::BLOCK_MAP_KEY | SyntaxKind::FLOW_MAP_KEY
+            )
+        })
+}
+
+// Resolve plain scalars using the YAML 1.2 core schema
+fn plain_scalar(text: &str) -> Highlight {
+    match ScalarType::of_plain(text) {
+        ScalarType::Null => Highlight::Null,
+        ScalarType::Boolean => Highlight::Boolean,
+        ScalarType::Integer | ScalarType::Float => Highlight::Number,
+        ScalarType::String => Highlight::String,
+    }
+}
@@ -1,9 +1,9 @@
-use super::{SyntaxKind, SyntaxToken};
+use super::{SyntaxKind, SyntaxNode, SyntaxToken};
 use crate::api_client::ApiGroup;
-use std::collections::HashMap;
-use yaml_parser::ast::{AstNode, BlockMapValue, Document};
+use yaml_parser::ast::{AstNode, Document};
 
 use super::utils::parent_node_until;
+use super::value::{Value, ValueKind};
 
 #[derive(thiserror::Error, Debug)]
 pub enum KubeDetailsError {
@@ -23,6 +23,94 @@ pub enum KubeDetailsError {
 pub struct KubeDetails {
     kind: String,
     api_version: ApiGroup,
+    name: Option<String>,
+    namespace: Option<String>,
+}
+
+/// Picks documents by their kind, name and namespace. Unset fields match any document.
+#[derive(Debug, Clone, Default)]
+pub struct Selector {
+    pub kind: Option<String>,
+    pub name: Option<String>,
+    pub namespace: Option<String>,
+}
+
+impl KubeDetails {
+    /// Read `kind` and `apiVersion` from the root map of a `DOCUMENT` node.
+    pub fn from_document(node: &SyntaxNode) -> Result<Self, KubeDetailsError> {
+        let document = Document::cast(node.clone()).ok_or(KubeDetailsError::RootNodeNotDocument)?;
+        let root = Value::from_document(&document)
+            .filter(|value| matches!(value.kind, ValueKind::Map(_)))
+            .ok_or(KubeDetailsError::DocumentIsNotBlockMap)?;
+
+        let kind = root.get("kind").and_then(Value::as_str);
+        let api_version = root.get("apiVersion").and_then(Value::as_str);
+        let metadata = root.get("metadata");
+        let name = metadata.and_then(|m| m.get("name")).and_then(Value::as_str);
+        let namespace = metadata
+            .and_then(|m| m.get("namespace"))
+            .and_then(Value::as_str);
+
+        match (api_version, kind) {
+            (Some(api_version), Some(kind)) => Ok(KubeDetails {
+                kind: kind.to_string(),
+                api_version: api_version.into(),
+                name: name.map(str::to_string),
+                namespace: namespace.map(str::to_string),
+            }),
+            _ => Err(KubeDetailsError::DocumentMissingKindOrApiVersion),
+        }
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn api_version(&self) -> &ApiGroup {
+        &self.api_version
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// `Kind/name`, or just the kind for unnamed resources.
+    pub fn display_name(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{}/{name}", self.kind),
+            None => self.kind.clone(),
+        }
+    }
+}
+
+impl Selector {
+    pub fn is_empty(&self) -> bool {
+        self.kind.is_none() && self.name.is_none() && self.namespace.is_none()
+    }
+
+    /// Kinds are compared ignoring case. Documents that are not manifests only match an empty
+    /// selector.
+    pub fn matches(&self, details: Option<&KubeDetails>) -> bool {
+        let Some(details) = details else {
+            return self.is_empty();
+        };
+
+        self.kind
+            .as_ref()
+            .is_none_or(|kind| kind.eq_ignore_ascii_case(details.kind()))
+            && self
+                .name
+                .as_ref()
+                .is_none_or(|name| Some(name.as_str()) == details.name())
+            && self
+                .namespace
+                .as_ref()
+                .is_none_or(|namespace| Some(namespace.as_str()) == details.namespace())
+    }
 }
 
 impl TryFrom<&SyntaxToken> for KubeDetails {
@@ -34,37 +122,6 @@ impl TryFrom<&SyntaxToken> for KubeDetails {
         let doc_node = parent_node_until(&root, SyntaxKind::DOCUMENT)
             .ok_or(KubeDetailsError::RootNodeNotDocument)?;
 
-        let doc_entries = Document::cast(doc_node)
-            .expect("Document node should always cast to Document AST")
-            .block()
-            .ok_or(KubeDetailsError::DocumentIsNotBlockMap)?
-            .block_map()
-            .ok_or(KubeDetailsError::DocumentIsNotBlockMap)?
-            .entries()
-            .filter_map(|entry| {
-                entry
-                    .key()
-                    .map(|key| key.syntax().text().to_string())
-                    .map(|key| (key, entry.value()))
-            })
-            .collect::<HashMap<String, Option<BlockMapValue>>>();
-
-        let kind = doc_entries
-            .get("kind")
-            .and_then(|v| v.as_ref())
-            .map(|value| value.syntax().text().to_string());
-
-        let api_version = doc_entries
-            .get("apiVersion")
-            .and_then(|v| v.as_ref())
-            .map(|value| value.syntax().text().to_string());
-
-        match (api_version, kind) {
-            (Some(api_version), Some(kind)) => Ok(KubeDetails {
-                kind,
-                api_version: (*api_version).into(),
-            }),
-            _ => Err(KubeDetailsError::DocumentMissingKindOrApiVersion),
-        }
+        KubeDetails::from_document(&doc_node)
     }
 }
@@ -1,27 +1,56 @@
 use log::{debug, info};
 use ratatui::{
-    style::{Style, Stylize},
+    style::Stylize,
     text::{Line, Span},
 };
-use rowan::{
-    ast::SyntaxNodePtr as RowanSyntaxNodePtr, NodeOrToken, TextSize,
-    TokenAtOffset as RowanTokenAtOffset, WalkEvent,
-};
-use std::path::PathBuf;
-use yaml_parser::{SyntaxKind, SyntaxNode, SyntaxToken, YamlLanguage};
+use rowan::{NodeOrToken, TextRange, TextSize, TokenAtOffset as RowanTokenAtOffset, WalkEvent};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use yaml_parser::ast::{AstNode, Document, Root};
+use yaml_parser::{SyntaxKind, SyntaxNode, SyntaxToken};
+
+use super::theme::{Highlight, Theme};
+use crate::api_client::{ApiClient, Error as ApiError};
+use crate::config::{EditorConfig, Severity, ValidationConfig};
 
+mod context;
 mod cursor;
+mod edit;
+mod fold;
+mod format;
+mod fragment;
+mod highlight;
 mod kube;
 mod nav;
+mod query;
+mod search;
+mod skeleton;
 pub(crate) mod utils;
+mod validate;
+mod value;
 
+pub use context::{Context, Focus, context_at};
 use cursor::{line_at_cursor, token_at_cursor};
-use kube::KubeDetails;
+use edit::{comment_edit, deletion_range, entry_at, indent_edit, set_edit};
+pub use fold::FoldAction;
+use fold::Folds;
+pub use fragment::Fragment;
+use fragment::{Form, node_at, paste_edit};
+use highlight::highlight;
+pub use kube::{KubeDetails, Selector};
 use nav::selectable_token_in_direction;
 pub use nav::Direction;
-use utils::{ancestor_not_kind, node_dimensions, selectable_kind};
+pub use query::{Query, QueryError, split_assignment, split_last_segment};
+use search::Search;
+pub use search::SearchScope;
+pub use skeleton::skeleton;
+use query::Target;
+use utils::{node_dimensions, selectable_kind};
+pub use validate::{Diagnostic, Rule};
+use validate::Validator;
+pub(crate) use value::Value;
+use value::ValueKind;
 
-pub(crate) type SyntaxNodePtr = RowanSyntaxNodePtr<YamlLanguage>;
 pub(crate) type TokenAtOffset = RowanTokenAtOffset<SyntaxToken>;
 
 #[allow(clippy::enum_variant_names)]
@@ -29,10 +58,30 @@ pub(crate) type TokenAtOffset = RowanTokenAtOffset<SyntaxToken>;
 pub enum Error {
     #[error("File path not found: {0}")]
     PathNotFound(PathBuf),
+    #[error("File already exists: {0}")]
+    PathExists(PathBuf),
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
     #[error("YAML parse error: {0}")]
     YamlParseError(#[from] yaml_parser::SyntaxError),
+    #[error("{0}")]
+    QueryError(#[from] QueryError),
+    #[error("Path `{0}` not found in any document")]
+    QueryNotFound(String),
+    #[error("Path `{0}` matches {1} documents, select one by kind, name or namespace")]
+    AmbiguousDocument(String, usize),
+    #[error("Only scalars can be set, found {0}")]
+    NotAScalar(&'static str),
+    #[error("Can not add keys to `{0}`")]
+    CanNotInsert(String),
+    #[error("Formatting would change the content of the file")]
+    FormatChangedContent,
+    #[error("Can not paste {0} into {1}")]
+    CanNotPaste(&'static str, &'static str),
+    #[error("Nothing to paste next to at the cursor")]
+    NoPasteTarget,
+    #[error("{0} changed on disk since it was loaded")]
+    ChangedOnDisk(PathBuf),
 }
 
 // TODO: Save file
@@ -42,6 +91,17 @@ pub struct File {
     pub max_width: usize,
     pub line_count: usize,
     ast: SyntaxNode,
+    diagnostics: Vec<Diagnostic>,
+    folds: Folds,
+    /// Changed since it was loaded or last written
+    modified: bool,
+    /// Hash of the text last loaded or written, to tell changes by other programs apart
+    saved: u64,
+    /// Counts edits, offsets into the text from before one are stale
+    revision: u64,
+    search: Option<Search>,
+    /// Range of the text last pasted, to check it against the schema of where it landed
+    pasted: Option<TextRange>,
 }
 
 impl File {
@@ -52,30 +112,471 @@ impl File {
         }
         let raw = std::fs::read_to_string(&path)?;
 
-        let ast = yaml_parser::parse(&raw)?;
+        Self::from_text(path, &raw)
+    }
 
-        let (line_count, max_width) = node_dimensions(&ast);
+    /// A file that has not been written yet. `path` is where it is saved to.
+    pub fn from_text(path: PathBuf, raw: &str) -> Result<Self, Error> {
+        let ast = yaml_parser::parse(raw)?;
+
+        let folds = Folds::default();
+        let (line_count, max_width) = node_dimensions(&ast, &folds);
 
         Ok(Self {
             path,
             max_width,
             line_count,
             ast,
+            diagnostics: vec![],
+            folds,
+            modified: false,
+            saved: text_hash(raw),
+            revision: 0,
+            search: None,
+            pasted: None,
         })
     }
 
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    /// Line and column of `cursor` in the text, both counted from 0. Columns are in characters.
+    pub fn position(&self, cursor: u32) -> (usize, usize) {
+        let text = self.text();
+        let before = &text[..(cursor as usize).min(text.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        (
+            before.matches('\n').count(),
+            before[line_start..].chars().count(),
+        )
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Full text of the file.
+    pub fn text(&self) -> String {
+        self.ast.to_string()
+    }
+
+    // Replace the whole content of the file. The current tree is kept if `raw` does not parse.
+    fn reparse(&mut self, raw: &str) -> Result<(), Error> {
+        let ast = yaml_parser::parse(raw)?;
+
+        self.ast = ast;
+        self.modified = true;
+        self.revision += 1;
+        // Positions are stale after an edit
+        self.diagnostics.clear();
+        self.folds.clear();
+        self.search = None;
+        self.pasted = None;
+        self.update_dimensions();
+        Ok(())
+    }
+
+    fn update_dimensions(&mut self) {
+        let (line_count, max_width) = node_dimensions(&self.ast, &self.folds);
+        self.line_count = line_count;
+        self.max_width = max_width;
+    }
+
+    // Documents with content, comment only documents are skipped
+    fn documents(&self) -> impl Iterator<Item = (Document, Value)> {
+        Root::cast(self.ast.clone())
+            .expect("Parsed trees always have a root")
+            .documents()
+            .filter_map(|document| Value::from_document(&document).map(|value| (document, value)))
+    }
+
+    pub fn document_count(&self) -> usize {
+        self.documents().count()
+    }
+
+    /// Range of each document with content, along with its kind and name when it is a manifest.
+    pub fn resources(&self) -> Vec<(TextRange, Option<KubeDetails>)> {
+        self.documents()
+            .map(|(document, _)| {
+                let details = KubeDetails::from_document(document.syntax()).ok();
+                (document.syntax().text_range(), details)
+            })
+            .collect()
+    }
+
+    /// Path of the cursor within its document, along with the kind and name of the document when
+    /// it is a manifest.
+    pub fn path_at(&self, cursor: u32) -> Option<(Option<KubeDetails>, Query)> {
+        let offset = TextSize::from(cursor);
+        self.documents()
+            .find(|(document, _)| document.syntax().text_range().contains_inclusive(offset))
+            .map(|(document, value)| {
+                let details = KubeDetails::from_document(document.syntax()).ok();
+                (details, Query::at(&value, offset))
+            })
+    }
+
+    /// Start of the key or sequence item `query` leads to, looking in the document of `cursor`
+    /// first. Folds hiding it are opened.
+    pub fn goto(&mut self, query: &Query, cursor: u32) -> Option<u32> {
+        let cursor = TextSize::from(cursor);
+        let mut documents = self.documents().collect::<Vec<_>>();
+        // Stable, so the other documents stay in order
+        documents.sort_by_key(|(document, _)| {
+            !document.syntax().text_range().contains_inclusive(cursor)
+        });
+        let offset = documents
+            .iter()
+            .find_map(|(_, value)| query.locate(value))?;
+
+        // Items start at their `-`, move to the first token that can hold the cursor
+        let mut token = token_at_cursor(&self.ast, offset.into())?;
+        while !selectable_kind(token.kind()) {
+            token = token.next_token()?;
+        }
+        if self.folds.hides(&token) {
+            self.folds.reveal(&token);
+            self.update_dimensions();
+        }
+        Some(token.text_range().start().into())
+    }
+
+    /// Paths in any document that complete `prefix` by one segment.
+    pub fn path_completions(&self, prefix: &str) -> Vec<String> {
+        let (parent, _) = split_last_segment(prefix);
+        let Ok(parent) = Query::parse(parent) else {
+            return vec![];
+        };
+
+        let mut completions: Vec<String> = vec![];
+        for (_, value) in self.documents() {
+            for child in parent.children(&value) {
+                if child.starts_with(prefix) && !completions.contains(&child) {
+                    completions.push(child);
+                }
+            }
+        }
+        completions
+    }
+
+    /// Top level keys of each document and the range of their entries, in the same order as
+    /// [`File::resources`].
+    pub fn top_level_keys(&self) -> Vec<Vec<(String, TextRange)>> {
+        self.documents()
+            .map(|(_, value)| match value.kind {
+                ValueKind::Map(entries) => entries
+                    .into_iter()
+                    .map(|entry| (entry.key, entry.node.text_range()))
+                    .collect(),
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    /// Rebuild indentation and whitespace, keeping comments and scalar styles. Returns whether
+    /// the text changed.
+    ///
+    /// The file is left untouched if the result would read differently from the original.
+    pub fn format(&mut self, config: &EditorConfig) -> Result<bool, Error> {
+        let formatted = format::format(&self.ast, config);
+        if formatted == self.text() {
+            return Ok(false);
+        }
+
+        let content = |file: &File| -> Vec<serde_json::Value> {
+            file.documents().map(|(_, value)| value.to_json()).collect()
+        };
+        let mut result = self.clone();
+        result.reparse(&formatted)?;
+        if content(&result) != content(self) {
+            return Err(Error::FormatChangedContent);
+        }
+
+        *self = result;
+        Ok(true)
+    }
+
+    // Root values of the documents picked by `selector`
+    fn select(&self, selector: &Selector) -> Vec<(Option<KubeDetails>, Value)> {
+        self.documents()
+            .map(|(document, value)| (KubeDetails::from_document(document.syntax()).ok(), value))
+            .filter(|(details, _)| selector.matches(details.as_ref()))
+            .collect()
+    }
+
+    /// Values at `query` in every document picked by `selector`, in file order. Entries without
+    /// a value, `key:`, are returned as nulls.
+    pub(crate) fn get(&self, query: &Query, selector: &Selector) -> Vec<Value> {
+        let mut values = vec![];
+        for (_, document) in self.select(selector) {
+            for target in query.resolve(&document) {
+                match target {
+                    Target::Found(value) => values.push(value.clone()),
+                    Target::Empty { entry, keys } if keys.is_empty() => values.push(Value {
+                        node: entry.node.clone(),
+                        kind: ValueKind::Null,
+                    }),
+                    _ => {}
+                }
+            }
+        }
+        values
+    }
+
+    /// Set the scalars `query` leads to in the documents picked by `selector`, adding missing
+    /// keys. Everything else in the file, comments and formatting included, is left untouched.
+    ///
+    /// When the path resolves in several documents, those where it already exists are preferred.
+    /// It is an error if that still leaves more than one. Values are typed by the schema of the
+    /// document's kind when it can be loaded with `api_client`. Returns the number of values set.
+    pub async fn set(
+        &mut self,
+        query: &Query,
+        text: &str,
+        selector: &Selector,
+        indent: usize,
+        api_client: Option<&mut ApiClient>,
+    ) -> Result<usize, Error> {
+        let mut raw = self.ast.to_string();
+
+        let documents = self.select(selector);
+        let resolved: Vec<(Option<&KubeDetails>, Vec<Target>)> = documents
+            .iter()
+            .map(|(details, value)| (details.as_ref(), query.resolve(value)))
+            .filter(|(_, targets)| !targets.is_empty())
+            .collect();
+
+        let found = |targets: &[Target]| targets.iter().any(|t| matches!(t, Target::Found(_)));
+        let existing = resolved
+            .iter()
+            .filter(|(_, targets)| found(targets))
+            .count();
+        let mut candidates = resolved
+            .iter()
+            .filter(|(_, targets)| existing == 0 || found(targets));
+
+        let (details, targets) = match (candidates.next(), candidates.count()) {
+            (None, _) => return Err(Error::QueryNotFound(query.to_string())),
+            (Some(candidate), 0) => candidate,
+            (Some(_), others) => {
+                return Err(Error::AmbiguousDocument(query.to_string(), others + 1));
+            }
+        };
+
+        let spec = match details.zip(api_client) {
+            Some((details, api_client)) => {
+                let spec = api_client.get_group_spec(details.api_version()).await;
+                spec.inspect_err(|e| debug!("No schema to type `{query}` with: {e}"))
+                    .ok()
+            }
+            None => None,
+        };
+        let schema = spec
+            .as_ref()
+            .zip(*details)
+            .and_then(|(spec, details)| spec.get_kind_exact(details.kind()).ok())
+            .and_then(|schema| query.schema(schema))
+            .map(|schema| schema.schema_type());
+
+        let mut edits = targets
+            .iter()
+            .map(|target| set_edit(&raw, target, text, indent, schema.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Later edits first, so earlier ranges stay valid
+        edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+        for (range, replacement) in &edits {
+            raw.replace_range(range.clone(), replacement);
+        }
+
+        self.reparse(&raw)?;
+        Ok(edits.len())
+    }
+
+    /// Validate every document against the schema of its `apiVersion` and `kind`.
+    ///
+    /// Documents without a known schema are reported as [`Rule::UnknownKind`]. Errors are only
+    /// returned when schemas could not be loaded at all.
+    pub async fn validate(
+        &self,
+        api_client: &mut ApiClient,
+        config: &ValidationConfig,
+    ) -> Result<Vec<Diagnostic>, ApiError> {
+        let mut diagnostics = vec![];
+
+        for (index, (document, root)) in self.documents().enumerate() {
+            let mut validator = Validator::new(config, index);
+            // Document level problems are reported on the first key
+            let anchor = match &root.kind {
+                ValueKind::Map(entries) => entries
+                    .first()
+                    .map_or(root.node.text_range(), |e| e.key_node.text_range()),
+                _ => root.node.text_range(),
+            };
+
+            let Ok(details) = KubeDetails::from_document(document.syntax()) else {
+                let message = "document has no `apiVersion` and `kind`".to_string();
+                validator.push(Rule::UnknownKind, anchor, "", message);
+                diagnostics.extend(validator.finish());
+                continue;
+            };
+            let (kind, group) = (details.kind(), details.api_version());
+
+            let spec = match api_client.get_group_spec(group).await {
+                Ok(spec) => spec,
+                Err(ApiError::InvalidGroup(_)) => {
+                    let message = format!("no schemas found for {group}");
+                    validator.push(Rule::UnknownKind, anchor, kind, message);
+                    diagnostics.extend(validator.finish());
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            match spec.get_kind_exact(kind) {
+                Ok(schema) => validator.validate(&root, schema, kind, anchor),
+                Err(ApiError::SpecNotFound(_)) => {
+                    let message = format!("unknown kind `{kind}` in {group}");
+                    validator.push(Rule::UnknownKind, anchor, kind, message);
+                }
+                Err(e) => return Err(e),
+            }
+            diagnostics.extend(validator.finish());
+        }
+
+        Ok(diagnostics)
+    }
+
+    /// Diagnostics shown in the editor, cleared whenever the file changes.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn set_diagnostics(&mut self, diagnostics: Vec<Diagnostic>) {
+        self.diagnostics = diagnostics;
+    }
+
+    /// Search for `pattern` and return the first match after `cursor`, see
+    /// [`File::next_match`]. An empty pattern clears the search.
+    pub fn search(
+        &mut self,
+        pattern: &str,
+        backward: bool,
+        scope: SearchScope,
+        cursor: u32,
+    ) -> Option<u32> {
+        if pattern.is_empty() {
+            self.search = None;
+            return None;
+        }
+        let document = self
+            .documents()
+            .map(|(document, _)| document.syntax().text_range())
+            .find(|range| range.contains_inclusive(cursor.into()));
+        self.search = Some(Search::new(&self.ast, pattern, backward, scope, document));
+        self.next_match(cursor, false)
+    }
+
+    pub fn clear_search(&mut self) {
+        self.search = None;
+    }
+
+    /// Start of the next match from `cursor` in the direction of the search, or the other way
+    /// when `reverse`. Matches in folded text are skipped.
+    pub fn next_match(&self, cursor: u32, reverse: bool) -> Option<u32> {
+        let search = self.search.as_ref()?;
+        let backward = search.backward != reverse;
+
+        let mut offset = TextSize::from(cursor);
+        for _ in 0..search.matches.len() {
+            offset = search.find(offset, backward)?;
+            let hidden = token_at_cursor(&self.ast, offset.into())
+                .is_some_and(|token| self.folds.hides(&token));
+            if !hidden {
+                return Some(offset.into());
+            }
+        }
+        None
+    }
+
+    /// Pattern of the search, the number of the match at `cursor` (counted from 1) and the
+    /// number of matches.
+    pub fn search_status(&self, cursor: u32) -> Option<(&str, Option<usize>, usize)> {
+        let search = self.search.as_ref()?;
+        let current = search
+            .matches
+            .iter()
+            .position(|m| m.contains_inclusive(cursor.into()))
+            .map(|i| i + 1);
+        Some((&search.pattern, current, search.matches.len()))
+    }
+
     /// Generate Ratatui lines from loaded file.
     ///
     /// `cursor` is the byte position in the file which is used for highlighting active elements.
-    pub fn render(&self, cursor: usize) -> (Vec<Line<'_>>, usize) {
-        tree_to_lines(&self.ast, cursor.try_into().unwrap())
+    /// `selection` is highlighted as well.
+    pub fn render(
+        &self,
+        cursor: usize,
+        selection: Option<TextRange>,
+        theme: &Theme,
+    ) -> (Vec<Line<'_>>, usize) {
+        tree_to_lines(
+            &self.ast,
+            cursor.try_into().unwrap(),
+            selection,
+            &self.diagnostics,
+            self.search.as_ref().map_or(&[][..], |s| &s.matches),
+            &self.folds,
+            theme,
+        )
+    }
+
+    /// Fold or unfold the innermost map or sequence around `cursor`. Returns the new cursor
+    /// position when anything changed, the cursor moves out of text that was folded away.
+    pub fn fold(&mut self, cursor: u32, action: FoldAction) -> Option<u32> {
+        let token = token_at_cursor(&self.ast, cursor).expect("Should always have a token");
+        if !self.folds.apply(&token, action) {
+            return None;
+        }
+        self.update_dimensions();
+
+        if !self.folds.hides(&token) {
+            return Some(cursor);
+        }
+        // The first line of a folded entry stays visible
+        let line = self.line_at_cursor(cursor);
+        Some(self.first_selectable_at_line(line))
+    }
+
+    /// Line in the file of each visible line, 0-indexed.
+    pub fn line_numbers(&self) -> Vec<usize> {
+        let mut numbers = vec![0];
+        let mut line = 0;
+        for token in self
+            .ast
+            .descendants_with_tokens()
+            .filter_map(NodeOrToken::into_token)
+        {
+            for _ in token.text().matches('\n') {
+                line += 1;
+                if !self.folds.hides(&token) {
+                    numbers.push(line);
+                }
+            }
+        }
+        numbers
     }
 
     /// Get the line number for a specific byte position in the loaded file.
     ///
     /// `cursor` is the byte position in the file.
     pub fn line_at_cursor(&self, cursor: u32) -> usize {
-        line_at_cursor(&self.ast, cursor)
+        line_at_cursor(&self.ast, &self.folds, cursor)
     }
 
     /// Return byte position for the first selectable element on a specific line.
@@ -89,8 +590,8 @@ impl File {
         let mut selected =
             token_at_cursor(&self.ast, 0).expect("All files have at least one token");
 
-        let next = |token: &SyntaxToken| token.next_token();
-        let prev = |token: &SyntaxToken| token.prev_token();
+        let next = |token: &SyntaxToken| self.folds.next_token(token);
+        let prev = |token: &SyntaxToken| self.folds.prev_token(token);
 
         // scroll to the line
         while let Some(ref token) = next(&selected) {
@@ -127,6 +628,47 @@ impl File {
         selected.text_range().start().into()
     }
 
+    /// Return byte position of the selectable token nearest to `column` on `line`, e.g. where
+    /// the mouse was clicked. Falls back to [`File::first_selectable_at_line`] when the line has
+    /// no selectable tokens.
+    ///
+    /// `line` and `column` are 0-indexed and as displayed, so lines in folds are skipped.
+    pub fn selectable_at(&self, line: usize, column: usize) -> u32 {
+        let mut token = token_at_cursor(&self.ast, 0);
+        let (mut current_line, mut current_column) = (0, 0);
+        let mut nearest: Option<(usize, u32)> = None;
+
+        while let Some(current) = token {
+            let width = current.text().chars().count();
+            if current_line == line && selectable_kind(current.kind()) {
+                let end = current_column + width.saturating_sub(1);
+                let distance = if column < current_column {
+                    current_column - column
+                } else {
+                    column.saturating_sub(end)
+                };
+                if nearest.is_none_or(|(nearest, _)| distance < nearest) {
+                    nearest = Some((distance, current.text_range().start().into()));
+                }
+            }
+
+            for c in current.text().chars() {
+                if c == '\n' {
+                    current_line += 1;
+                    current_column = 0;
+                } else {
+                    current_column += 1;
+                }
+            }
+            if current_line > line {
+                break;
+            }
+            token = self.folds.next_token(&current);
+        }
+
+        nearest.map_or_else(|| self.first_selectable_at_line(line), |(_, offset)| offset)
+    }
+
     /// Given a current position in a file, find the next cursor position in the given direction.
     ///
     /// `y` directions seek based on the number of lines first, then try to find the first
@@ -138,43 +680,197 @@ impl File {
         let current_token = token_at_cursor(&self.ast, current_cursor)
             .expect("Cursor should always be at a valid token");
 
-        selectable_token_in_direction(&current_token, direction)
+        selectable_token_in_direction(&current_token, direction, &self.folds)
             .text_range()
             .start()
             .into()
     }
 
+    /// Range of the token at `cursor`, where a selection starts.
+    pub fn selection_at(&self, cursor: u32) -> TextRange {
+        token_at_cursor(&self.ast, cursor)
+            .expect("Should always have a token")
+            .text_range()
+    }
+
+    /// Range of the closest node around `selection` that is larger than it, e.g. the entry of a
+    /// scalar, then the map of the entry, then the entry of that map.
+    pub fn expand_selection(&self, selection: TextRange) -> Option<TextRange> {
+        let parent = match self.ast.covering_element(selection) {
+            NodeOrToken::Node(node) => node,
+            NodeOrToken::Token(token) => token.parent()?,
+        };
+        parent
+            .ancestors()
+            .map(|node| node.text_range())
+            .find(|range| *range != selection && range.contains_range(selection))
+    }
+
+    /// Delete `selection`. Entries are deleted along with their lines. Returns the new cursor
+    /// position.
+    pub fn delete_selection(&mut self, selection: TextRange) -> Result<u32, Error> {
+        let raw = self.ast.to_string();
+        let range = match self.selected_node(selection) {
+            Some(node) => {
+                // The only entry of a sequence item goes along with its `- `
+                let node = match node.first_token() {
+                    Some(token) if node.kind() == SyntaxKind::BLOCK_MAP_ENTRY => {
+                        entry_at(&token).unwrap_or(node)
+                    }
+                    _ => node,
+                };
+                deletion_range(&raw, &node)
+            }
+            None => selection.into(),
+        };
+        self.replace(range, "")
+    }
+
+    // The outermost node spanning exactly `selection`, wrappers share the range of the entry they
+    // are in. `None` when a token is selected.
+    fn selected_node(&self, selection: TextRange) -> Option<SyntaxNode> {
+        match self.ast.covering_element(selection) {
+            NodeOrToken::Node(node) if node.text_range() == selection => node
+                .ancestors()
+                .take_while(|n| n.text_range() == selection)
+                .last(),
+            _ => None,
+        }
+    }
+
+    /// The entry or sequence item at `cursor`. On a `- key: value` line that is the item.
+    pub fn yank(&self, cursor: u32) -> Option<Fragment> {
+        let token = token_at_cursor(&self.ast, cursor)?;
+        node_at(&token).map(|node| Fragment::of_node(&self.text(), &node))
+    }
+
+    pub fn yank_selection(&self, selection: TextRange) -> Fragment {
+        match self.selected_node(selection) {
+            Some(node) => Fragment::of_node(&self.text(), &node),
+            None => Fragment::new(&self.text(), selection, Form::Value),
+        }
+    }
+
+    /// Paste `fragment` after the entry or sequence item at `cursor`, or into its value when
+    /// `child`. Returns the new cursor position, on the pasted text.
+    pub fn paste(
+        &mut self,
+        fragment: &Fragment,
+        cursor: u32,
+        child: bool,
+        indent: usize,
+    ) -> Result<u32, Error> {
+        let token = token_at_cursor(&self.ast, cursor).expect("Should always have a token");
+        let target = node_at(&token).ok_or(Error::NoPasteTarget)?;
+
+        let mut raw = self.ast.to_string();
+        let (range, text) = paste_edit(&raw, &target, fragment, child, indent)?;
+        raw.replace_range(range.clone(), &text);
+        self.reparse(&raw)?;
+
+        let start = range.start + text.len() - text.trim_start().len();
+        self.pasted = Some(TextRange::at(
+            start.try_into().unwrap(),
+            TextSize::of(text.trim_start()),
+        ));
+        let line = self.line_at_cursor(start.try_into().unwrap());
+        Ok(self.first_selectable_at_line(line))
+    }
+
+    /// Range of the text last pasted, until the file changes again.
+    pub fn last_paste(&self) -> Option<TextRange> {
+        self.pasted
+    }
+
+    /// Comment out the lines of `selection`, or uncomment them if they all are comments.
+    /// Returns the new cursor position.
+    pub fn comment(&mut self, selection: TextRange) -> Result<u32, Error> {
+        let (range, text) = comment_edit(&self.ast.to_string(), &selection.into());
+        self.replace(range, &text)
+    }
+
+    /// Indent the lines of `selection` by `indent` spaces, or dedent them. Returns the new cursor
+    /// position.
+    pub fn indent(
+        &mut self,
+        selection: TextRange,
+        indent: usize,
+        dedent: bool,
+    ) -> Result<u32, Error> {
+        let (range, text) = indent_edit(&self.ast.to_string(), &selection.into(), indent, dedent);
+        self.replace(range, &text)
+    }
+
+    // Replace `range` of the text and return the first selectable position on the line where it
+    // started
+    fn replace(&mut self, range: std::ops::Range<usize>, text: &str) -> Result<u32, Error> {
+        let mut raw = self.ast.to_string();
+        raw.replace_range(range.clone(), text);
+
+        // Trees need at least one token
+        if raw.is_empty() {
+            raw.push('\n');
+        }
+        self.reparse(&raw)?;
+
+        let line = self.line_at_cursor(range.start.try_into().unwrap());
+        Ok(self.first_selectable_at_line(line))
+    }
+
     pub fn info(&self, cursor: u32) {
         let token = token_at_cursor(&self.ast, cursor).expect("Should always have a token");
 
-        let kube_details: KubeDetails = (&token).try_into().unwrap();
+        let kube_details: Result<KubeDetails, _> = (&token).try_into();
 
         info!("Kubernetes Details: {kube_details:?}");
         info!("Cursor: {cursor:?}");
         info!("Token: {token:?}");
     }
 
-    /// Write the file to disk in the same location.
+    /// Write the file to disk in the same location. Changes other programs made since it was
+    /// loaded or last written are not written over unless `force`.
     ///
     /// Note: This function abi will change.
-    pub fn write(&self) {
-        // todo: make this call falliable and take a Option PathBuf for a new location if desired.
+    pub fn write(&mut self, force: bool) -> Result<(), Error> {
+        // todo: take a Option PathBuf for a new location if desired.
         let output = self.ast.to_string();
+        // Files not written yet, e.g. from `m7s new`, have nothing on disk to lose
+        if !force && self.path.exists() && self.changed_on_disk()?.is_some() {
+            Err(Error::ChangedOnDisk(self.path.clone()))?;
+        }
 
-        std::fs::write(&self.path, output).unwrap();
+        std::fs::write(&self.path, &output)?;
+        self.modified = false;
+        self.saved = text_hash(&output);
+        Ok(())
     }
-}
 
-// Applies the given styling for a specific syntax kind.
-fn styled_span(s: String, kind: SyntaxKind, active: bool) -> Span<'static> {
-    let mut span = Span::from(s);
+    /// Text on disk when it is not what was last loaded or written, e.g. after `git checkout`.
+    pub fn changed_on_disk(&self) -> Result<Option<String>, Error> {
+        let raw = std::fs::read_to_string(&self.path)?;
+        Ok((text_hash(&raw) != self.saved).then_some(raw))
+    }
+}
 
-    let style = |kind: SyntaxKind| match kind {
-        SyntaxKind::BLOCK_MAP_KEY => Style::default().bold().fg(ratatui::style::Color::Yellow),
-        _ => Style::default(),
-    };
+fn text_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
 
-    span = span.style(style(kind));
+// Applies the theme styling for a highlight group.
+fn styled_span(
+    s: String,
+    highlight: Highlight,
+    active: bool,
+    severity: Option<Severity>,
+    theme: &Theme,
+) -> Span<'static> {
+    let mut span = Span::from(s).style(theme.style(highlight));
+
+    if let Some(severity) = severity {
+        span = span.patch_style(theme.diagnostic(severity));
+    }
 
     // Change the highlight if this is the active element
     if active {
@@ -184,43 +880,111 @@ fn styled_span(s: String, kind: SyntaxKind, active: bool) -> Span<'static> {
     span
 }
 
+// Spans of `text`, which starts at `start` in the file, split so that search matches are styled
+// on their own.
+fn split_matches(
+    text: &str,
+    start: TextSize,
+    matches: &[TextRange],
+    span: impl Fn(&str, bool) -> Span<'static>,
+) -> Vec<Span<'static>> {
+    let range = TextRange::at(start, TextSize::of(text));
+    let mut spans = vec![];
+    let mut position = 0;
+
+    for found in matches.iter().filter_map(|m| m.intersect(range)) {
+        if found.is_empty() {
+            continue;
+        }
+        let from = usize::from(found.start() - start);
+        let to = usize::from(found.end() - start);
+        if from > position {
+            spans.push(span(&text[position..from], false));
+        }
+        spans.push(span(&text[from..to], true));
+        position = to;
+    }
+    if position < text.len() || spans.is_empty() {
+        spans.push(span(&text[position..], false));
+    }
+    spans
+}
+
 // This is the main render function. It walks the CST from rowan and returns Ratatui lines along
 // with the maximum width of any line (this is helpful for x scrolling and saves recalculation).
-fn tree_to_lines(tree: &SyntaxNode, cursor: u32) -> (Vec<Line<'_>>, usize) {
+fn tree_to_lines<'a>(
+    tree: &'a SyntaxNode,
+    cursor: u32,
+    selection: Option<TextRange>,
+    diagnostics: &[Diagnostic],
+    matches: &[TextRange],
+    folds: &Folds,
+    theme: &Theme,
+) -> (Vec<Line<'a>>, usize) {
     let mut lines = Vec::new();
     let mut max_width = 0;
 
     let mut pending_line = vec![];
-    let mut last_node = None;
 
     for event in tree.preorder_with_tokens() {
         match event {
             WalkEvent::Enter(element) => match element {
                 NodeOrToken::Node(node) => {
                     debug!("++node: {node:?}");
-                    last_node = Some(SyntaxNodePtr::new(&node));
                 }
                 NodeOrToken::Token(token) => {
                     debug!("++token: {token:?} {:?}", token.text());
 
-                    let active_token = token.text_range().contains(TextSize::new(cursor));
+                    // Folded text is replaced by a summary of what it holds
+                    if let Some(fold) = folds.starting_at(token.text_range().start()) {
+                        pending_line.push(styled_span(
+                            fold.summary.clone(),
+                            Highlight::Comment,
+                            false,
+                            None,
+                            theme,
+                        ));
+                    }
+                    if folds.hides(&token) {
+                        continue;
+                    }
 
-                    let parent_kind = ancestor_not_kind(
-                        last_node
-                            .expect("Tokens always have parent Nodes")
-                            .to_node(tree),
-                        SyntaxKind::FLOW,
-                    )
-                    .unwrap()
-                    .kind();
+                    let active_token = token.text_range().contains(TextSize::new(cursor));
+                    let selected = selection.is_some_and(|s| s.contains_range(token.text_range()));
+
+                    let highlight = highlight(&token);
+                    // Errors take precedence over warnings on the same token
+                    let severity = diagnostics
+                        .iter()
+                        .filter(|d| {
+                            d.range
+                                .intersect(token.text_range())
+                                .is_some_and(|r| !r.is_empty())
+                        })
+                        .map(|d| d.severity)
+                        .min_by_key(|s| *s != Severity::Error);
 
                     let mut split_newlines = token.text().split('\n').peekable();
+                    let mut start = token.text_range().start();
+                    let span = |text: &str, matched: bool| {
+                        let mut span =
+                            styled_span(text.to_string(), highlight, active_token, severity, theme);
+                        if selected {
+                            span = span.patch_style(theme.selection);
+                        }
+                        if matched {
+                            span.patch_style(theme.search_match)
+                        } else {
+                            span
+                        }
+                    };
 
                     // Get the first element, it'll always have some value
                     let tok = split_newlines
                         .next()
                         .expect("Whitespace elements should always have some value");
-                    pending_line.push(styled_span(tok.to_string(), parent_kind, active_token));
+                    pending_line.extend(split_matches(tok, start, matches, span));
+                    start += TextSize::of(tok);
 
                     for line in split_newlines {
                         let line_len = pending_line.len();
@@ -229,18 +993,16 @@ fn tree_to_lines(tree: &SyntaxNode, cursor: u32) -> (Vec<Line<'_>>, usize) {
                         }
                         lines.push(Line::from(pending_line.clone()));
                         pending_line.clear();
-                        pending_line.push(styled_span(line.to_string(), parent_kind, active_token));
+                        // Skip the line break
+                        start += TextSize::of('\n');
+                        pending_line.extend(split_matches(line, start, matches, span));
+                        start += TextSize::of(line);
                     }
                 }
             },
             WalkEvent::Leave(element) => match element {
                 NodeOrToken::Node(node) => {
                     debug!("--node {node:?}");
-                    last_node = if let Some(parent) = node.parent() {
-                        Some(SyntaxNodePtr::new(&parent))
-                    } else {
-                        None
-                    };
                 }
                 NodeOrToken::Token(token) => {
                     debug!("--token {:?}", token.kind());
@@ -1,5 +1,6 @@
 use yaml_parser::SyntaxToken;
 
+use super::fold::Folds;
 use super::utils::{first_selectable_in_line, selectable_kind, whitespace_newlines};
 
 #[derive(Debug)]
@@ -11,13 +12,13 @@ pub enum Direction {
 }
 
 // Handles vertical movements from a token
-fn selectable_y(token: &SyntaxToken, dir: &Direction) -> SyntaxToken {
+fn selectable_y(token: &SyntaxToken, dir: &Direction, folds: &Folds) -> SyntaxToken {
     let mut selected = token.clone();
     let mut newlines = 0;
     let next_token = |token: SyntaxToken| -> Option<SyntaxToken> {
         match dir {
-            Direction::Up(_) => token.prev_token(),
-            Direction::Down(_) => token.next_token(),
+            Direction::Up(_) => folds.prev_token(&token),
+            Direction::Down(_) => folds.next_token(&token),
             _ => unreachable!(),
         }
     };
@@ -60,8 +61,8 @@ fn selectable_y(token: &SyntaxToken, dir: &Direction) -> SyntaxToken {
     // Fringe case, might have moved too far. Need to go backwards until we find something
     if !selectable_kind(selected.kind()) {
         while let Some(ref prev) = match dir {
-            Direction::Up(_) => selected.next_token(),
-            Direction::Down(_) => selected.prev_token(),
+            Direction::Up(_) => folds.next_token(&selected),
+            Direction::Down(_) => folds.prev_token(&selected),
             _ => unreachable!(),
         } {
             if selectable_kind(prev.kind()) {
@@ -75,12 +76,12 @@ fn selectable_y(token: &SyntaxToken, dir: &Direction) -> SyntaxToken {
 }
 
 // Handles horizontal movements from a token
-fn selectable_x(token: &SyntaxToken, dir: &Direction) -> SyntaxToken {
+fn selectable_x(token: &SyntaxToken, dir: &Direction, folds: &Folds) -> SyntaxToken {
     let mut selected = token.clone();
     let next_token = |token: &SyntaxToken| -> Option<SyntaxToken> {
         match dir {
-            Direction::Left(_) => token.prev_token(),
-            Direction::Right(_) => token.next_token(),
+            Direction::Left(_) => folds.prev_token(token),
+            Direction::Right(_) => folds.next_token(token),
             _ => unreachable!(),
         }
     };
@@ -116,15 +117,19 @@ fn selectable_x(token: &SyntaxToken, dir: &Direction) -> SyntaxToken {
     selected
 }
 
-// Assumption: The current token is always selectable
-pub(crate) fn selectable_token_in_direction(token: &SyntaxToken, dir: &Direction) -> SyntaxToken {
+// Assumption: The current token is always selectable. Tokens in folds are skipped.
+pub(crate) fn selectable_token_in_direction(
+    token: &SyntaxToken,
+    dir: &Direction,
+    folds: &Folds,
+) -> SyntaxToken {
     match dir {
         Direction::Up(n) | Direction::Down(n) | Direction::Left(n) | Direction::Right(n)
             if *n == 0 =>
         {
             token.clone()
         }
-        Direction::Up(_) | Direction::Down(_) => selectable_y(token, dir),
-        Direction::Left(_) | Direction::Right(_) => selectable_x(token, dir),
+        Direction::Up(_) | Direction::Down(_) => selectable_y(token, dir, folds),
+        Direction::Left(_) | Direction::Right(_) => selectable_x(token, dir, folds),
     }
 }
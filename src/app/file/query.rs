@@ -0,0 +1,458 @@
+use rowan::{TextRange, TextSize};
+use std::fmt::Write;
+use yaml_parser::SyntaxKind;
+
+use super::value::{Entry, Value, ValueKind};
+use crate::api_client::{AdditionalSchema, SchemaRef, SchemaType};
+
+/// One step of a [`Query`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// Value of a map key, e.g. `spec` or `["app.kubernetes.io/name"]`
+    Key(String),
+    /// Sequence item by position, e.g. `[0]`
+    Index(usize),
+    /// Sequence items that are maps holding `key: value`, e.g. `[name=web]`, or `[web]` for
+    /// `name`
+    Match(String, String),
+    /// Every item of a sequence or value of a map, `[*]`
+    All,
+}
+
+/// A path into a document, e.g. `spec.template.spec.containers[name=web].image`.
+///
+/// Keys containing `.` or `[` can be quoted in brackets, e.g.
+/// `metadata.labels["app.kubernetes.io/name"]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Invalid path `{path}`: {reason}")]
+pub struct QueryError {
+    path: String,
+    reason: &'static str,
+}
+
+/// Where a query ends up in a document.
+#[derive(Debug)]
+pub(crate) enum Target<'a> {
+    /// The path exists
+    Found(&'a Value),
+    /// `keys` are missing from `map`, starting with the first one
+    Missing { map: &'a Value, keys: Vec<String> },
+    /// The path reaches an entry without a value, e.g. `key:`. `keys` are the ones below it.
+    Empty { entry: &'a Entry, keys: Vec<String> },
+}
+
+impl Query {
+    /// Parse a query. An empty query, or `.`, is the document root.
+    pub fn parse(path: &str) -> Result<Self, QueryError> {
+        let error = |reason| QueryError {
+            path: path.to_string(),
+            reason,
+        };
+
+        let mut segments = vec![];
+        let mut chars = path.strip_prefix('.').unwrap_or(path).chars().peekable();
+        // A key is expected at the start and after each `.`
+        let mut expect_key = true;
+
+        while let Some(&c) = chars.peek() {
+            match c {
+                '[' => {
+                    chars.next();
+                    let inner = bracket(&mut chars).ok_or(error("unclosed `[`"))?;
+                    segments.push(bracket_segment(&inner).ok_or(error("invalid `[...]`"))?);
+                    expect_key = false;
+                }
+                '.' if !expect_key => {
+                    chars.next();
+                    expect_key = true;
+                }
+                _ if expect_key => {
+                    let mut key = String::new();
+                    while let Some(&c) = chars.peek() {
+                        if c == '.' || c == '[' {
+                            break;
+                        }
+                        key.push(c);
+                        chars.next();
+                    }
+                    if key.is_empty() {
+                        return Err(error("empty key"));
+                    }
+                    segments.push(Segment::Key(key));
+                    expect_key = false;
+                }
+                _ => return Err(error("expected `.` or `[`")),
+            }
+        }
+
+        if expect_key && !segments.is_empty() {
+            return Err(error("trailing `.`"));
+        }
+
+        Ok(Query {
+            source: path.to_string(),
+            segments,
+        })
+    }
+
+    /// Path to the innermost key or sequence item of `root` holding `offset`, e.g.
+    /// `.spec.containers[0].image`. The path is written so that yq and [`Query::parse`] read it
+    /// back.
+    pub(crate) fn at(root: &Value, offset: TextSize) -> Self {
+        let mut segments = vec![];
+        let mut value = Some(root);
+
+        while let Some(current) = value {
+            value = match &current.kind {
+                ValueKind::Map(entries) => entries
+                    .iter()
+                    .find(|entry| entry.node.text_range().contains(offset))
+                    .and_then(|entry| {
+                        segments.push(Segment::Key(entry.key.clone()));
+                        entry.value.as_ref()
+                    }),
+                ValueKind::Seq(items) => items
+                    .iter()
+                    .enumerate()
+                    .find(|(_, item)| item_range(item).contains(offset))
+                    .map(|(index, item)| {
+                        segments.push(Segment::Index(index));
+                        item
+                    }),
+                _ => None,
+            };
+        }
+
+        let source = segments.iter().fold(String::new(), |mut source, segment| {
+            match segment {
+                Segment::Key(key) if is_plain_key(key) => {
+                    source.push('.');
+                    source.push_str(key);
+                }
+                Segment::Key(key) => {
+                    let _ = write!(source, "[{key:?}]");
+                }
+                Segment::Index(index) => {
+                    let _ = write!(source, "[{index}]");
+                }
+                Segment::Match(..) | Segment::All => {}
+            }
+            source
+        });
+
+        Query {
+            source: if source.is_empty() {
+                ".".to_string()
+            } else {
+                source
+            },
+            segments,
+        }
+    }
+
+    /// Schema of the values the query leads to, from `schema` of the document's kind.
+    pub(crate) fn schema<'a>(&self, schema: SchemaRef<'a>) -> Option<SchemaRef<'a>> {
+        self.segments.iter().try_fold(schema, |schema, segment| {
+            match (segment, schema.schema_type()) {
+                (Segment::Key(key), _) => schema.child(key),
+                (_, SchemaType::Array) => schema.items(),
+                // `[*]` on a map is each of its values
+                _ => match schema.additional_properties()? {
+                    AdditionalSchema::Schema(values) => Some(values),
+                    AdditionalSchema::Any => None,
+                },
+            }
+        })
+    }
+
+    /// The path for display, e.g. `spec › containers[0] › image`.
+    pub fn breadcrumb(&self) -> String {
+        let mut crumbs: Vec<String> = vec![];
+        for segment in &self.segments {
+            match (segment, crumbs.last_mut()) {
+                (Segment::Key(key), _) => crumbs.push(key.clone()),
+                (Segment::Index(index), Some(last)) => {
+                    let _ = write!(last, "[{index}]");
+                }
+                (Segment::Index(index), None) => crumbs.push(format!("[{index}]")),
+                (Segment::Match(..) | Segment::All, _) => {}
+            }
+        }
+        crumbs.join(" › ")
+    }
+
+    /// Start of the key, or sequence item, the query ends at in `root`. The first one is used
+    /// when `[*]` or `[name=web]` pick several.
+    pub(crate) fn locate(&self, root: &Value) -> Option<TextSize> {
+        locate(root, &self.segments)
+    }
+
+    /// The paths one segment below this one in `root`, e.g. `spec.replicas` and
+    /// `spec.template` for `spec`. Sequence items are named by their `name` when they have one.
+    pub(crate) fn children(&self, root: &Value) -> Vec<String> {
+        let parent = self.source.strip_prefix('.').unwrap_or(&self.source);
+        let mut children = vec![];
+        for target in self.resolve(root) {
+            let Target::Found(value) = target else {
+                continue;
+            };
+            match &value.kind {
+                ValueKind::Map(entries) => {
+                    children.extend(entries.iter().map(|entry| match &entry.key {
+                        key if !is_plain_key(key) => format!("{parent}[{key:?}]"),
+                        key if parent.is_empty() => key.clone(),
+                        key => format!("{parent}.{key}"),
+                    }));
+                }
+                ValueKind::Seq(items) => {
+                    children.extend(items.iter().enumerate().map(|(index, item)| {
+                        match item.get("name").and_then(Value::as_str) {
+                            Some(name) if is_plain_key(name) => format!("{parent}[{name}]"),
+                            _ => format!("{parent}[{index}]"),
+                        }
+                    }));
+                }
+                _ => {}
+            }
+        }
+        children
+    }
+
+    /// Every place in `root` the query leads to. Missing keys are reported so that they can be
+    /// added, missing sequence items are not.
+    pub(crate) fn resolve<'a>(&self, root: &'a Value) -> Vec<Target<'a>> {
+        let mut targets = vec![];
+        resolve(root, &self.segments, &mut targets);
+        targets
+    }
+}
+
+impl std::fmt::Display for Query {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Split `PATH=VALUE` on the first `=` outside of brackets.
+pub fn split_assignment(assignment: &str) -> Result<(&str, &str), QueryError> {
+    let mut depth = 0;
+    let mut quote = None;
+
+    for (i, c) in assignment.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) if depth > 0 => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('[', None) => depth += 1,
+            (']', None) => depth -= 1,
+            ('=', None) if depth == 0 => return Ok((&assignment[..i], &assignment[i + 1..])),
+            _ => {}
+        }
+    }
+
+    Err(QueryError {
+        path: assignment.to_string(),
+        reason: "expected `PATH=VALUE`",
+    })
+}
+
+/// Split a partly typed path before its last segment, e.g. `spec.tem` into `spec` and `tem`.
+pub fn split_last_segment(path: &str) -> (&str, &str) {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut split = 0;
+
+    for (i, c) in path.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) if depth > 0 => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('[', None) => {
+                depth += 1;
+                split = i;
+            }
+            (']', None) => depth -= 1,
+            ('.', None) if depth == 0 => split = i,
+            _ => {}
+        }
+    }
+
+    (&path[..split], &path[split..])
+}
+
+// Range of a sequence item, block items start at their `-`
+fn item_range(item: &Value) -> TextRange {
+    item.node
+        .parent()
+        .filter(|parent| parent.kind() == SyntaxKind::BLOCK_SEQ_ENTRY)
+        .unwrap_or_else(|| item.node.clone())
+        .text_range()
+}
+
+fn locate(value: &Value, segments: &[Segment]) -> Option<TextSize> {
+    fn item(item: &Value) -> (TextSize, Option<&Value>) {
+        (item_range(item).start(), Some(item))
+    }
+
+    let (segment, rest) = segments.split_first()?;
+    let children: Vec<(TextSize, Option<&Value>)> = match (segment, &value.kind) {
+        (Segment::Key(key), ValueKind::Map(entries)) => entries
+            .iter()
+            .filter(|entry| entry.key == *key)
+            .map(|entry| (entry.key_node.text_range().start(), entry.value.as_ref()))
+            .collect(),
+        (Segment::Index(index), ValueKind::Seq(items)) => {
+            items.get(*index).map(item).into_iter().collect()
+        }
+        (Segment::Match(key, expected), ValueKind::Seq(items)) => items
+            .iter()
+            .filter(|item| item.get(key).and_then(Value::as_str) == Some(expected))
+            .map(item)
+            .collect(),
+        (Segment::All, ValueKind::Seq(items)) => items.iter().map(item).collect(),
+        (Segment::All, ValueKind::Map(entries)) => entries
+            .iter()
+            .map(|entry| (entry.key_node.text_range().start(), entry.value.as_ref()))
+            .collect(),
+        _ => vec![],
+    };
+
+    children.into_iter().find_map(|(start, child)| {
+        if rest.is_empty() {
+            Some(start)
+        } else {
+            locate(child?, rest)
+        }
+    })
+}
+
+// Keys that can be written after a `.` without quoting
+fn is_plain_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+// Content up to the matching `]`, quotes may contain `]`
+fn bracket(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Option<String> {
+    let mut inner = String::new();
+    let mut quote = None;
+
+    for c in chars.by_ref() {
+        match (c, quote) {
+            (']', None) => return Some(inner),
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            _ => {}
+        }
+        inner.push(c);
+    }
+
+    None
+}
+
+fn bracket_segment(inner: &str) -> Option<Segment> {
+    let inner = inner.trim();
+    if inner == "*" {
+        return Some(Segment::All);
+    }
+    if let Ok(index) = inner.parse() {
+        return Some(Segment::Index(index));
+    }
+    if let Some(key) = unquote(inner) {
+        return Some(Segment::Key(key.to_string()));
+    }
+    // `[web]` is short for `[name=web]`
+    if is_plain_key(inner) {
+        return Some(Segment::Match("name".to_string(), inner.to_string()));
+    }
+
+    let (key, value) = inner.split_once('=')?;
+    let (key, value) = (key.trim(), value.trim());
+    if key.is_empty() {
+        return None;
+    }
+    Some(Segment::Match(
+        unquote(key).unwrap_or(key).to_string(),
+        unquote(value).unwrap_or(value).to_string(),
+    ))
+}
+
+fn unquote(text: &str) -> Option<&str> {
+    ['"', '\'']
+        .into_iter()
+        .find_map(|q| text.strip_prefix(q).and_then(|t| t.strip_suffix(q)))
+}
+
+// The remaining segments as keys, `None` if any of them is not a key
+fn keys(segments: &[Segment]) -> Option<Vec<String>> {
+    segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Key(key) => Some(key.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn resolve<'a>(value: &'a Value, segments: &[Segment], targets: &mut Vec<Target<'a>>) {
+    let Some((segment, rest)) = segments.split_first() else {
+        targets.push(Target::Found(value));
+        return;
+    };
+
+    match (segment, &value.kind) {
+        (Segment::Key(key), ValueKind::Map(entries)) => {
+            match entries.iter().find(|entry| entry.key == *key) {
+                Some(Entry {
+                    value: Some(child), ..
+                }) => resolve(child, rest, targets),
+                Some(entry) => {
+                    if let Some(keys) = keys(rest) {
+                        targets.push(Target::Empty { entry, keys });
+                    }
+                }
+                None => {
+                    if let Some(keys) = keys(segments) {
+                        targets.push(Target::Missing { map: value, keys });
+                    }
+                }
+            }
+        }
+        (Segment::Index(index), ValueKind::Seq(items)) => {
+            if let Some(item) = items.get(*index) {
+                resolve(item, rest, targets);
+            }
+        }
+        (Segment::Match(key, expected), ValueKind::Seq(items)) => {
+            for item in items {
+                if item.get(key).and_then(Value::as_str) == Some(expected) {
+                    resolve(item, rest, targets);
+                }
+            }
+        }
+        (Segment::All, ValueKind::Seq(items)) => {
+            for item in items {
+                resolve(item, rest, targets);
+            }
+        }
+        (Segment::All, ValueKind::Map(entries)) => {
+            for entry in entries {
+                match &entry.value {
+                    Some(child) => resolve(child, rest, targets),
+                    None => {
+                        if let Some(keys) = keys(rest) {
+                            targets.push(Target::Empty { entry, keys });
+                        }
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
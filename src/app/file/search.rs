@@ -0,0 +1,130 @@
+use rowan::{TextRange, TextSize};
+use yaml_parser::{SyntaxKind, SyntaxNode, SyntaxToken};
+
+use super::utils::selectable_kind;
+
+/// Which tokens a search looks at.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchScope {
+    #[default]
+    All,
+    Keys,
+    Values,
+    /// Any token of the document the search started in
+    Document,
+}
+
+impl SearchScope {
+    /// The scope after this one, for cycling through them with a single key.
+    pub fn next(self) -> Self {
+        match self {
+            SearchScope::All => SearchScope::Keys,
+            SearchScope::Keys => SearchScope::Values,
+            SearchScope::Values => SearchScope::Document,
+            SearchScope::Document => SearchScope::All,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SearchScope::All => "all",
+            SearchScope::Keys => "keys",
+            SearchScope::Values => "values",
+            SearchScope::Document => "document",
+        }
+    }
+}
+
+/// The last search of a file and where it matches.
+#[derive(Debug, Clone)]
+pub(crate) struct Search {
+    pub pattern: String,
+    pub backward: bool,
+    /// In text order
+    pub matches: Vec<TextRange>,
+}
+
+impl Search {
+    /// Find `pattern` in the text of scalars and comments. Patterns without capitals ignore
+    /// case. `document` limits the matches for [`SearchScope::Document`].
+    pub fn new(
+        tree: &SyntaxNode,
+        pattern: &str,
+        backward: bool,
+        scope: SearchScope,
+        document: Option<TextRange>,
+    ) -> Self {
+        let ignore_case = !pattern.chars().any(char::is_uppercase);
+        // ASCII lowercasing keeps byte offsets intact
+        let needle = if ignore_case {
+            pattern.to_ascii_lowercase()
+        } else {
+            pattern.to_string()
+        };
+
+        let mut matches = vec![];
+        if !needle.is_empty() {
+            for token in tree
+                .descendants_with_tokens()
+                .filter_map(rowan::NodeOrToken::into_token)
+                .filter(|token| in_scope(token, scope, document))
+            {
+                let text = if ignore_case {
+                    token.text().to_ascii_lowercase()
+                } else {
+                    token.text().to_string()
+                };
+                let start = token.text_range().start();
+                matches.extend(text.match_indices(&needle).map(|(i, found)| {
+                    let offset = start + TextSize::try_from(i).expect("Offsets fit in u32");
+                    TextRange::at(offset, TextSize::of(found))
+                }));
+            }
+        }
+
+        Search {
+            pattern: pattern.to_string(),
+            backward,
+            matches,
+        }
+    }
+
+    /// Start of the match after `offset`, or before it when `backward`. Wraps around the file.
+    pub fn find(&self, offset: TextSize, backward: bool) -> Option<TextSize> {
+        let mut starts = self.matches.iter().map(|m| m.start());
+        if backward {
+            starts
+                .clone()
+                .rev()
+                .find(|start| *start < offset)
+                .or_else(|| starts.next_back())
+        } else {
+            starts
+                .clone()
+                .find(|start| *start > offset)
+                .or_else(|| starts.next())
+        }
+    }
+}
+
+fn in_scope(token: &SyntaxToken, scope: SearchScope, document: Option<TextRange>) -> bool {
+    if !selectable_kind(token.kind()) {
+        return false;
+    }
+    let is_key = || {
+        token.parent_ancestors().any(|node| {
+            matches!(
+                node.kind(),
+                SyntaxKind::BLOCK_MAP_KEY | SyntaxKind::FLOW_MAP_KEY
+            )
+        })
+    };
+    match scope {
+        SearchScope::All => true,
+        SearchScope::Keys => token.kind() != SyntaxKind::COMMENT && is_key(),
+        SearchScope::Values => token.kind() != SyntaxKind::COMMENT && !is_key(),
+        SearchScope::Document => {
+            document.is_none_or(|range| range.contains_range(token.text_range()))
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use super::edit::scalar_text;
+use crate::api_client::{SchemaRef, SchemaType};
+use crate::config::EditorConfig;
+
+// Top level fields that are always written, or never
+const GENERATED: [&str; 3] = ["apiVersion", "kind", "metadata"];
+const SKIPPED: [&str; 1] = ["status"];
+
+/// A minimal manifest for `schema`, with every required field set to a placeholder.
+///
+/// With `optional`, the other fields are added as comments, each below its description.
+pub fn skeleton(
+    schema: SchemaRef<'_>,
+    api_version: &str,
+    kind: &str,
+    name: &str,
+    config: &EditorConfig,
+    optional: bool,
+) -> String {
+    let mut generator = Generator {
+        config,
+        optional,
+        seen: vec![],
+    };
+
+    let mut lines = vec![
+        format!("apiVersion: {}", scalar_text(api_version, false)),
+        format!("kind: {}", scalar_text(kind, false)),
+        "metadata:".to_string(),
+    ];
+    let pad = " ".repeat(config.indent);
+    lines.push(format!("{pad}name: {}", scalar_text(name, false)));
+    if let Some(metadata) = schema.property("metadata") {
+        lines.extend(generator.fields(metadata, config.indent, &["name"]));
+    }
+
+    for (key, child) in schema.properties() {
+        if GENERATED.contains(&key) || SKIPPED.contains(&key) {
+            continue;
+        }
+        // Kubernetes schemas do not require `spec`, but a manifest without one is rarely useful
+        if key == "spec" || schema.required().iter().any(|r| r == key) {
+            lines.extend(generator.entry(key, child, 0));
+        } else if optional {
+            lines.extend(Generator::commented(key, child, 0));
+        }
+    }
+
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+struct Generator<'a> {
+    config: &'a EditorConfig,
+    optional: bool,
+    // Schemas being generated, so recursive ones stop
+    seen: Vec<&'a str>,
+}
+
+impl<'a> Generator<'a> {
+    // Lines of the fields of an object at `column`, apart from `skip`
+    fn fields(&mut self, schema: SchemaRef<'a>, column: usize, skip: &[&str]) -> Vec<String> {
+        let mut lines = vec![];
+        for (key, child) in schema.properties() {
+            if skip.contains(&key) {
+                continue;
+            }
+            if schema.required().iter().any(|r| r == key) {
+                lines.extend(self.entry(key, child, column));
+            } else if self.optional {
+                lines.extend(Self::commented(key, child, column));
+            }
+        }
+        lines
+    }
+
+    fn entry(&mut self, key: &str, schema: SchemaRef<'a>, column: usize) -> Vec<String> {
+        let pad = " ".repeat(column);
+        let key = scalar_text(key, false);
+
+        match self.nested(schema, column) {
+            Some(lines) => {
+                let mut entry = vec![format!("{pad}{key}:")];
+                entry.extend(lines);
+                entry
+            }
+            None => vec![format!("{pad}{key}: {}", placeholder(schema))],
+        }
+    }
+
+    // Lines below the key of objects with required fields, or of sequences of them. Everything
+    // else gets a placeholder.
+    fn nested(&mut self, schema: SchemaRef<'a>, column: usize) -> Option<Vec<String>> {
+        let schema = match schema.schema_type() {
+            SchemaType::OneOf(alternatives) => *alternatives.first()?,
+            _ => schema,
+        };
+        if let Some(name) = schema.name() {
+            if self.seen.contains(&name) {
+                return None;
+            }
+            self.seen.push(name);
+        }
+
+        let nested = match schema.schema_type() {
+            SchemaType::Object if !schema.required().is_empty() => {
+                Some(self.fields(schema, column + self.config.indent, &[]))
+            }
+            SchemaType::Array => {
+                let items = schema.items().filter(|i| !i.required().is_empty());
+                items.map(|items| {
+                    let dash = if self.config.indent_sequences {
+                        column + self.config.indent
+                    } else {
+                        column
+                    };
+                    // The first field goes on the `- ` line
+                    let mut lines = self.fields(items, dash + 2, &[]);
+                    if let Some(first) = lines.first_mut() {
+                        *first = format!("{}- {}", " ".repeat(dash), &first[dash + 2..]);
+                    }
+                    lines
+                })
+            }
+            _ => None,
+        };
+
+        if schema.name().is_some() {
+            self.seen.pop();
+        }
+        nested.filter(|lines| !lines.is_empty())
+    }
+
+    fn commented(key: &str, schema: SchemaRef<'_>, column: usize) -> Vec<String> {
+        let pad = " ".repeat(column);
+        let mut lines = vec![];
+        if let Some(description) = schema.description().map(first_sentence) {
+            lines.push(format!("{pad}# {description}"));
+        }
+        lines.push(format!(
+            "{pad}# {}: {}",
+            scalar_text(key, false),
+            placeholder(schema)
+        ));
+        lines
+    }
+}
+
+fn placeholder(schema: SchemaRef<'_>) -> String {
+    match schema.schema_type() {
+        SchemaType::String(allowed) => {
+            scalar_text(allowed.first().map_or("", String::as_str), false)
+        }
+        SchemaType::Integer | SchemaType::Number | SchemaType::IntOrString => "0".to_string(),
+        SchemaType::Quantity => "\"0\"".to_string(),
+        SchemaType::Boolean => "false".to_string(),
+        SchemaType::Array => "[]".to_string(),
+        SchemaType::OneOf(alternatives) => alternatives
+            .first()
+            .map_or_else(|| "{}".to_string(), |first| placeholder(*first)),
+        SchemaType::Object | SchemaType::Any => "{}".to_string(),
+    }
+}
+
+// Descriptions can run for paragraphs, the first sentence is enough for a comment
+fn first_sentence(description: &str) -> String {
+    let line = description.lines().next().unwrap_or_default();
+    let end = line.find(". ").map_or(line.len(), |i| i + 1);
+    line[..end].trim().to_string()
+}
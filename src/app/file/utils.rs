@@ -1,6 +1,8 @@
 use rowan::{NodeOrToken, WalkEvent};
 use yaml_parser::{SyntaxKind, SyntaxNode, SyntaxToken};
 
+use super::fold::Folds;
+
 pub(crate) fn ancestor_not_kind(node: SyntaxNode, kind: SyntaxKind) -> Option<SyntaxNode> {
     if node.kind() == kind {
         let parent = node
@@ -23,7 +25,8 @@ pub(crate) fn selectable_kind(kind: SyntaxKind) -> bool {
     )
 }
 
-pub(crate) fn node_dimensions(tree: &SyntaxNode) -> (usize, usize) {
+/// Number of line breaks and the widest line, leaving out folded text.
+pub(crate) fn node_dimensions(tree: &SyntaxNode, folds: &Folds) -> (usize, usize) {
     let mut max_width = 0;
     let mut line_count = 0;
 
@@ -32,6 +35,7 @@ pub(crate) fn node_dimensions(tree: &SyntaxNode) -> (usize, usize) {
     for event in tree.preorder_with_tokens() {
         if let WalkEvent::Enter(element) = &event
             && let NodeOrToken::Token(token) = element
+            && !folds.hides(token)
         {
             let token_text = token.text();
             let mut split_newlines = token_text.split('\n').peekable();
@@ -0,0 +1,299 @@
+use rowan::{TextRange, TextSize};
+use yaml_parser::SyntaxError;
+
+use super::value::{ScalarType, Value, ValueKind};
+use crate::api_client::{AdditionalSchema, SchemaRef, SchemaType};
+use crate::config::{Severity, ValidationConfig};
+
+/// The check that produced a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    Syntax,
+    UnknownKind,
+    UnknownField,
+    MissingRequired,
+    TypeMismatch,
+    InvalidEnum,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 6] = [
+        Rule::Syntax,
+        Rule::UnknownKind,
+        Rule::UnknownField,
+        Rule::MissingRequired,
+        Rule::TypeMismatch,
+        Rule::InvalidEnum,
+    ];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            Rule::Syntax => "syntax",
+            Rule::UnknownKind => "unknown-kind",
+            Rule::UnknownField => "unknown-field",
+            Rule::MissingRequired => "missing-required",
+            Rule::TypeMismatch => "type-mismatch",
+            Rule::InvalidEnum => "invalid-enum",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Rule::Syntax => "The file is not valid YAML",
+            Rule::UnknownKind => "No schema is known for the document's apiVersion and kind",
+            Rule::UnknownField => "The field is not part of the schema",
+            Rule::MissingRequired => "A required field is missing",
+            Rule::TypeMismatch => "The value does not match the schema type",
+            Rule::InvalidEnum => "The value is not one of the allowed values",
+        }
+    }
+
+    fn severity(self, config: &ValidationConfig) -> Severity {
+        match self {
+            Rule::Syntax => Severity::Error,
+            Rule::UnknownKind => config.unknown_kind,
+            Rule::UnknownField => config.unknown_fields,
+            Rule::MissingRequired => config.missing_required,
+            Rule::TypeMismatch => config.type_mismatch,
+            Rule::InvalidEnum => config.invalid_enum,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub range: TextRange,
+    pub severity: Severity,
+    pub rule: Rule,
+    /// Index of the document in the file
+    pub document: usize,
+    /// Location in the document, e.g. `Deployment.spec.template.spec.containers[0].image`
+    pub path: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// A file that could not be parsed, nothing else can be checked in it.
+    pub fn syntax(error: &SyntaxError) -> Self {
+        let offset = TextSize::try_from(error.offset()).unwrap_or_default();
+        let message = match error.message() {
+            "" => "invalid YAML".to_string(),
+            message => message.replace('\n', " "),
+        };
+
+        Diagnostic {
+            range: TextRange::empty(offset),
+            severity: Severity::Error,
+            rule: Rule::Syntax,
+            document: 0,
+            path: String::new(),
+            message,
+        }
+    }
+}
+
+/// Collects diagnostics for one document, dropping those whose rule is turned off.
+pub(crate) struct Validator<'a> {
+    config: &'a ValidationConfig,
+    document: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Validator<'a> {
+    pub fn new(config: &'a ValidationConfig, document: usize) -> Self {
+        Validator {
+            config,
+            document,
+            diagnostics: vec![],
+        }
+    }
+
+    pub fn finish(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+
+    pub fn push(&mut self, rule: Rule, range: TextRange, path: &str, message: String) {
+        let severity = rule.severity(self.config);
+        if severity == Severity::Off {
+            return;
+        }
+
+        self.diagnostics.push(Diagnostic {
+            range,
+            severity,
+            rule,
+            document: self.document,
+            path: path.to_string(),
+            message,
+        });
+    }
+
+    /// Check `value` against `schema`. Maps and sequences report type errors on `anchor`, which
+    /// is the key holding them, so that diagnostics stay on a single line.
+    pub fn validate(
+        &mut self,
+        value: &Value,
+        schema: SchemaRef<'_>,
+        path: &str,
+        anchor: TextRange,
+    ) {
+        let range = match value.kind {
+            ValueKind::Map(_) | ValueKind::Seq(_) => anchor,
+            _ => value.node.text_range(),
+        };
+
+        match (&value.kind, schema.schema_type()) {
+            // Nulls clear a field, aliases are resolved by the API server
+            (ValueKind::Null | ValueKind::Alias, _) | (_, SchemaType::Any) => {}
+            (ValueKind::Scalar(s), _) if s.scalar_type() == ScalarType::Null => {}
+            (_, SchemaType::OneOf(alternatives)) => {
+                self.validate_alternatives(value, &alternatives, path, anchor);
+            }
+            (ValueKind::Map(_), SchemaType::Object) => {
+                self.validate_map(value, schema, path, anchor);
+            }
+            (ValueKind::Seq(items), SchemaType::Array) => {
+                let Some(item_schema) = schema.items() else {
+                    return;
+                };
+                for (i, item) in items.iter().enumerate() {
+                    let item_anchor = match item.kind {
+                        ValueKind::Map(_) | ValueKind::Seq(_) => first_line(item, anchor),
+                        _ => item.node.text_range(),
+                    };
+                    self.validate(item, item_schema, &format!("{path}[{i}]"), item_anchor);
+                }
+            }
+            (ValueKind::Scalar(scalar), SchemaType::String(allowed)) => {
+                if scalar.scalar_type() != ScalarType::String {
+                    self.type_mismatch(value, schema, path, range);
+                } else if !allowed.is_empty() && !allowed.contains(&scalar.text) {
+                    self.push(
+                        Rule::InvalidEnum,
+                        range,
+                        path,
+                        format!("`{}` is not one of: {}", scalar.text, allowed.join(", ")),
+                    );
+                }
+            }
+            (ValueKind::Scalar(scalar), expected) => {
+                let valid = match (scalar.scalar_type(), expected) {
+                    (ScalarType::Integer, SchemaType::Integer | SchemaType::Number)
+                    | (ScalarType::Float, SchemaType::Number)
+                    | (ScalarType::Boolean, SchemaType::Boolean)
+                    | (ScalarType::Integer | ScalarType::String, SchemaType::IntOrString) => true,
+                    (t, SchemaType::Quantity) => t != ScalarType::Boolean,
+                    _ => false,
+                };
+                if !valid {
+                    self.type_mismatch(value, schema, path, range);
+                }
+            }
+            _ => self.type_mismatch(value, schema, path, range),
+        }
+    }
+
+    fn validate_map(
+        &mut self,
+        value: &Value,
+        schema: SchemaRef<'_>,
+        path: &str,
+        anchor: TextRange,
+    ) {
+        let ValueKind::Map(entries) = &value.kind else {
+            return;
+        };
+
+        let additional = schema.additional_properties();
+        // Objects without any properties are free form, e.g. `RawExtension`
+        let free_form = schema.preserves_unknown_fields()
+            || matches!(additional, Some(AdditionalSchema::Any))
+            || (additional.is_none() && schema.properties().is_empty());
+
+        for entry in entries {
+            let entry_path = format!("{path}.{}", entry.key);
+            let key_range = entry.key_node.text_range();
+
+            let child = schema.property(&entry.key).or(match additional {
+                Some(AdditionalSchema::Schema(values)) => Some(values),
+                _ => None,
+            });
+
+            match (child, &entry.value) {
+                (Some(child), Some(child_value)) => {
+                    self.validate(child_value, child, &entry_path, key_range);
+                }
+                (None, _) if !free_form => self.push(
+                    Rule::UnknownField,
+                    key_range,
+                    &entry_path,
+                    format!("unknown field `{}`", entry.key),
+                ),
+                _ => {}
+            }
+        }
+
+        for required in schema.required() {
+            if !entries.iter().any(|entry| entry.key == *required) {
+                self.push(
+                    Rule::MissingRequired,
+                    anchor,
+                    path,
+                    format!("missing required field `{required}`"),
+                );
+            }
+        }
+    }
+
+    // Valid if any alternative is. Otherwise the diagnostics of the first alternative are kept.
+    fn validate_alternatives(
+        &mut self,
+        value: &Value,
+        alternatives: &[SchemaRef<'_>],
+        path: &str,
+        anchor: TextRange,
+    ) {
+        let mut results = alternatives.iter().map(|alternative| {
+            let mut validator = Validator::new(self.config, self.document);
+            validator.validate(value, *alternative, path, anchor);
+            validator.finish()
+        });
+
+        let Some(first) = results.next() else {
+            return;
+        };
+        if first.is_empty() || results.any(|diagnostics| diagnostics.is_empty()) {
+            return;
+        }
+        self.diagnostics.extend(first);
+    }
+
+    fn type_mismatch(
+        &mut self,
+        value: &Value,
+        schema: SchemaRef<'_>,
+        path: &str,
+        range: TextRange,
+    ) {
+        self.push(
+            Rule::TypeMismatch,
+            range,
+            path,
+            format!(
+                "expected {}, found {}",
+                schema.type_name(),
+                value.type_name()
+            ),
+        );
+    }
+}
+
+// The first key of a map item, so sequence items are reported on their `- ` line
+fn first_line(value: &Value, fallback: TextRange) -> TextRange {
+    match &value.kind {
+        ValueKind::Map(entries) => entries
+            .first()
+            .map_or(fallback, |entry| entry.key_node.text_range()),
+        _ => fallback,
+    }
+}
@@ -0,0 +1,484 @@
+use yaml_parser::ast::{AstNode, Block, BlockScalar, Document, Flow, FlowMapKey, FlowMapValue};
+use yaml_parser::{SyntaxNode, SyntaxToken};
+
+use super::edit::column;
+
+/// A YAML value read from the CST. The node is kept so that positions can be reported.
+#[derive(Debug, Clone)]
+pub(crate) struct Value {
+    pub node: SyntaxNode,
+    pub kind: ValueKind,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum ValueKind {
+    Map(Vec<Entry>),
+    Seq(Vec<Value>),
+    Scalar(Scalar),
+    Alias,
+    /// An empty value, e.g. `key:` or `- `
+    Null,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Entry {
+    /// The whole `key: value` node
+    pub node: SyntaxNode,
+    pub key: String,
+    pub key_node: SyntaxNode,
+    pub value: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ScalarStyle {
+    Plain,
+    SingleQuoted,
+    DoubleQuoted,
+    Block,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Scalar {
+    /// Content with quotes, escapes and indentation resolved
+    pub text: String,
+    pub style: ScalarStyle,
+}
+
+/// Type of a scalar, resolved with the YAML 1.2 core schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ScalarType {
+    Null,
+    Boolean,
+    Integer,
+    Float,
+    String,
+}
+
+impl ScalarType {
+    pub fn of_plain(text: &str) -> Self {
+        match text {
+            "~" | "null" | "Null" | "NULL" => ScalarType::Null,
+            "true" | "True" | "TRUE" | "false" | "False" | "FALSE" => ScalarType::Boolean,
+            _ if is_integer(text) => ScalarType::Integer,
+            _ if is_number(text) => ScalarType::Float,
+            _ => ScalarType::String,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ScalarType::Null => "null",
+            ScalarType::Boolean => "boolean",
+            ScalarType::Integer => "integer",
+            ScalarType::Float => "number",
+            ScalarType::String => "string",
+        }
+    }
+}
+
+fn is_integer(text: &str) -> bool {
+    if let Some(hex) = text.strip_prefix("0x") {
+        return !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+    if let Some(octal) = text.strip_prefix("0o") {
+        return !octal.is_empty() && octal.chars().all(|c| c.is_digit(8));
+    }
+
+    let unsigned = text.strip_prefix(['-', '+']).unwrap_or(text);
+    !unsigned.is_empty() && unsigned.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_number(text: &str) -> bool {
+    let unsigned = text.trim_start_matches(['-', '+']);
+
+    if matches!(unsigned, ".inf" | ".Inf" | ".INF") || matches!(text, ".nan" | ".NaN" | ".NAN") {
+        return true;
+    }
+
+    // Rust also accepts `inf`, `NaN` etc. which YAML does not
+    unsigned.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        && !unsigned.contains(|c: char| c.is_alphabetic() && c != 'e' && c != 'E')
+        && text.parse::<f64>().is_ok()
+}
+
+impl Scalar {
+    pub fn scalar_type(&self) -> ScalarType {
+        match self.style {
+            ScalarStyle::Plain => ScalarType::of_plain(&self.text),
+            _ => ScalarType::String,
+        }
+    }
+
+    // Numbers JSON can not hold, e.g. `.inf` or very large integers, stay strings
+    fn to_json(&self) -> serde_json::Value {
+        let text = self.text.as_str();
+        let number = match self.scalar_type() {
+            ScalarType::Null => return serde_json::Value::Null,
+            ScalarType::Boolean => {
+                return serde_json::Value::Bool(text.eq_ignore_ascii_case("true"));
+            }
+            ScalarType::Integer => parse_integer(text).map(serde_json::Number::from),
+            ScalarType::Float => text
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64),
+            ScalarType::String => None,
+        };
+
+        number.map_or_else(
+            || serde_json::Value::String(text.to_string()),
+            serde_json::Value::Number,
+        )
+    }
+}
+
+fn parse_integer(text: &str) -> Option<i64> {
+    if let Some(hex) = text.strip_prefix("0x") {
+        return i64::from_str_radix(hex, 16).ok();
+    }
+    if let Some(octal) = text.strip_prefix("0o") {
+        return i64::from_str_radix(octal, 8).ok();
+    }
+    text.parse().ok()
+}
+
+impl Value {
+    /// The root value of a document, `None` if the document has no content.
+    pub fn from_document(document: &Document) -> Option<Value> {
+        document
+            .block()
+            .map(|block| Value::from_block(&block))
+            .or_else(|| document.flow().map(|flow| Value::from_flow(&flow)))
+    }
+
+    fn new(node: &SyntaxNode, kind: ValueKind) -> Self {
+        Value {
+            node: node.clone(),
+            kind,
+        }
+    }
+
+    fn from_block(block: &Block) -> Self {
+        let kind = if let Some(map) = block.block_map() {
+            ValueKind::Map(
+                map.entries()
+                    .filter_map(|entry| {
+                        let key = entry.key()?;
+                        let value = entry.value().and_then(|value| {
+                            value
+                                .block()
+                                .map(|block| Value::from_block(&block))
+                                .or_else(|| value.flow().map(|flow| Value::from_flow(&flow)))
+                        });
+                        Some(Entry {
+                            node: entry.syntax().clone(),
+                            key: key.flow().map_or_else(
+                                || key.syntax().text().to_string(),
+                                |flow| Value::from_flow(&flow).key_text(),
+                            ),
+                            key_node: key.syntax().clone(),
+                            value,
+                        })
+                    })
+                    .collect(),
+            )
+        } else if let Some(seq) = block.block_seq() {
+            ValueKind::Seq(
+                seq.entries()
+                    .map(|entry| {
+                        entry
+                            .block()
+                            .map(|block| Value::from_block(&block))
+                            .or_else(|| entry.flow().map(|flow| Value::from_flow(&flow)))
+                            .unwrap_or_else(|| Value::new(entry.syntax(), ValueKind::Null))
+                    })
+                    .collect(),
+            )
+        } else if let Some(scalar) = block.block_scalar() {
+            ValueKind::Scalar(block_scalar(&scalar))
+        } else {
+            ValueKind::Null
+        };
+
+        Value::new(block.syntax(), kind)
+    }
+
+    fn from_flow(flow: &Flow) -> Self {
+        let kind = if let Some(token) = flow.plain_scalar() {
+            ValueKind::Scalar(Scalar {
+                text: fold_lines(token.text()),
+                style: ScalarStyle::Plain,
+            })
+        } else if let Some(token) = flow.double_qouted_scalar() {
+            ValueKind::Scalar(Scalar {
+                text: double_quoted(&token),
+                style: ScalarStyle::DoubleQuoted,
+            })
+        } else if let Some(token) = flow.single_quoted_scalar() {
+            ValueKind::Scalar(Scalar {
+                text: single_quoted(&token),
+                style: ScalarStyle::SingleQuoted,
+            })
+        } else if let Some(map) = flow.flow_map() {
+            ValueKind::Map(
+                map.entries()
+                    .into_iter()
+                    .flat_map(|entries| entries.entries())
+                    .filter_map(|entry| flow_entry(entry.syntax(), entry.key(), entry.value()))
+                    .collect(),
+            )
+        } else if let Some(seq) = flow.flow_seq() {
+            ValueKind::Seq(
+                seq.entries()
+                    .into_iter()
+                    .flat_map(|entries| entries.entries())
+                    .filter_map(|entry| match (entry.flow(), entry.flow_pair()) {
+                        (Some(flow), _) => Some(Value::from_flow(&flow)),
+                        // `[a: b]` is a sequence holding a single entry map
+                        (None, Some(pair)) => Some(Value::new(
+                            pair.syntax(),
+                            ValueKind::Map(
+                                flow_entry(pair.syntax(), pair.key(), pair.value())
+                                    .into_iter()
+                                    .collect(),
+                            ),
+                        )),
+                        (None, None) => None,
+                    })
+                    .collect(),
+            )
+        } else if flow.alias().is_some() {
+            ValueKind::Alias
+        } else {
+            ValueKind::Null
+        };
+
+        Value::new(flow.syntax(), kind)
+    }
+
+    // Keys are compared by their content, non-scalar keys by their source text
+    fn key_text(&self) -> String {
+        match &self.kind {
+            ValueKind::Scalar(scalar) => scalar.text.clone(),
+            _ => self.node.text().to_string(),
+        }
+    }
+
+    /// Name of the value type, as used in diagnostics.
+    pub fn type_name(&self) -> &'static str {
+        match &self.kind {
+            ValueKind::Map(_) => "object",
+            ValueKind::Seq(_) => "array",
+            ValueKind::Scalar(scalar) => scalar.scalar_type().name(),
+            ValueKind::Alias => "alias",
+            ValueKind::Null => "null",
+        }
+    }
+
+    /// Value of `key` if this is a map.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match &self.kind {
+            ValueKind::Map(entries) => entries
+                .iter()
+                .find(|entry| entry.key == key)
+                .and_then(|entry| entry.value.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Text of a scalar value.
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.kind {
+            ValueKind::Scalar(scalar) => Some(&scalar.text),
+            _ => None,
+        }
+    }
+
+    /// Source text of the value, with the indentation of its lines after the first removed.
+    pub fn source(&self) -> String {
+        if matches!(self.kind, ValueKind::Null) {
+            return "null".to_string();
+        }
+
+        // Lines after the first are dedented by the column the node starts at, which keeps
+        // nested blocks indented relative to it
+        let root = self
+            .node
+            .ancestors()
+            .last()
+            .unwrap_or_else(|| self.node.clone());
+        let column = column(&root.to_string(), self.node.text_range().start().into());
+
+        let text = self.node.to_string();
+        let mut lines = text.split('\n');
+        let mut source = lines.next().unwrap_or_default().to_string();
+        for line in lines {
+            source.push('\n');
+            let spaces = line.len() - line.trim_start_matches(' ').len();
+            source.push_str(&line[spaces.min(column)..]);
+        }
+        source
+    }
+
+    /// The value as JSON, with scalars typed by the YAML 1.2 core schema. Aliases are kept as
+    /// their source text, `*name`.
+    pub fn to_json(&self) -> serde_json::Value {
+        match &self.kind {
+            ValueKind::Map(entries) => serde_json::Value::Object(
+                entries
+                    .iter()
+                    .map(|entry| {
+                        let value = entry
+                            .value
+                            .as_ref()
+                            .map_or(serde_json::Value::Null, Value::to_json);
+                        (entry.key.clone(), value)
+                    })
+                    .collect(),
+            ),
+            ValueKind::Seq(items) => {
+                serde_json::Value::Array(items.iter().map(Value::to_json).collect())
+            }
+            ValueKind::Scalar(scalar) => scalar.to_json(),
+            ValueKind::Alias => serde_json::Value::String(self.node.to_string().trim().to_string()),
+            ValueKind::Null => serde_json::Value::Null,
+        }
+    }
+}
+
+fn flow_entry(
+    node: &SyntaxNode,
+    key: Option<FlowMapKey>,
+    value: Option<FlowMapValue>,
+) -> Option<Entry> {
+    let key = key?;
+    Some(Entry {
+        node: node.clone(),
+        key: key
+            .flow()
+            .map_or_else(String::new, |flow| Value::from_flow(&flow).key_text()),
+        key_node: key.syntax().clone(),
+        value: value
+            .and_then(|value| value.flow())
+            .map(|flow| Value::from_flow(&flow)),
+    })
+}
+
+// Multi line flow scalars are folded: line breaks become spaces, empty lines become newlines
+fn fold_lines(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    let mut pending_space = false;
+
+    for (i, line) in text.split('\n').enumerate() {
+        let line = if i == 0 { line.trim_end() } else { line.trim() };
+        if line.is_empty() && i > 0 {
+            folded.push('\n');
+            pending_space = false;
+            continue;
+        }
+        if pending_space {
+            folded.push(' ');
+        }
+        folded.push_str(line);
+        pending_space = true;
+    }
+
+    folded
+}
+
+fn single_quoted(token: &SyntaxToken) -> String {
+    let text = token.text();
+    let inner = text
+        .strip_prefix('\'')
+        .and_then(|t| t.strip_suffix('\''))
+        .unwrap_or(text);
+    fold_lines(&inner.replace("''", "'"))
+}
+
+fn double_quoted(token: &SyntaxToken) -> String {
+    let text = token.text();
+    let inner = text
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .unwrap_or(text);
+
+    let mut unescaped = String::with_capacity(inner.len());
+    let mut chars = fold_lines(inner).chars().collect::<Vec<_>>().into_iter();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        let escaped = match chars.next() {
+            Some('0') => '\0',
+            Some('a') => '\u{7}',
+            Some('b') => '\u{8}',
+            Some('t' | '\t') => '\t',
+            Some('n') => '\n',
+            Some('v') => '\u{b}',
+            Some('f') => '\u{c}',
+            Some('r') => '\r',
+            Some('e') => '\u{1b}',
+            Some('N') => '\u{85}',
+            Some('_') => '\u{a0}',
+            Some(hex @ ('x' | 'u' | 'U')) => {
+                let len = match hex {
+                    'x' => 2,
+                    'u' => 4,
+                    _ => 8,
+                };
+                let code: String = chars.by_ref().take(len).collect();
+                u32::from_str_radix(&code, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .unwrap_or(char::REPLACEMENT_CHARACTER)
+            }
+            Some(other) => other,
+            None => break,
+        };
+        unescaped.push(escaped);
+    }
+
+    unescaped
+}
+
+// Strip the indentation of a `|` or `>` scalar and apply its chomping indicator
+fn block_scalar(scalar: &BlockScalar) -> Scalar {
+    let text = scalar
+        .text()
+        .map(|t| t.text().to_string())
+        .unwrap_or_default();
+
+    let lines: Vec<&str> = text.trim_start_matches(['\r', '\n']).split('\n').collect();
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches(' ').len())
+        .min()
+        .unwrap_or(0);
+    let lines: Vec<&str> = lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or(""))
+        .collect();
+
+    let mut content = if scalar.bar().is_some() {
+        lines.join("\n")
+    } else {
+        fold_lines(&lines.join("\n"))
+    };
+
+    let chomping = scalar.chomping_indicator();
+    let keep = chomping.as_ref().is_some_and(|c| c.plus().is_some());
+    let strip = chomping.as_ref().is_some_and(|c| c.minus().is_some());
+    if !keep {
+        content.truncate(content.trim_end_matches('\n').len());
+        if !strip && !content.is_empty() {
+            content.push('\n');
+        }
+    }
+
+    Scalar {
+        text: content,
+        style: ScalarStyle::Block,
+    }
+}
@@ -0,0 +1,859 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::Direction;
+use std::fmt::Write as _;
+
+use crate::config::{EditorConfig, KeymapConfig};
+
+use super::{AppEvent, AppMode, Delta, FoldAction, Operator, PaneFocus};
+
+#[derive(thiserror::Error, Debug)]
+pub enum KeymapError {
+    #[error("Unknown keymap preset: {0}")]
+    UnknownPreset(String),
+    #[error("Invalid key sequence: {0}")]
+    InvalidKeys(String),
+    #[error("Unknown action `{1}` bound to {0}")]
+    UnknownAction(String, String),
+    #[error("{0} is a prefix of {1}, which could never be reached. Bind one of them to \"none\"")]
+    PrefixConflict(String, String),
+}
+
+/// Named actions that keys can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Load,
+    Write,
+    LoadSpec,
+    Validate,
+    Info,
+    DumpDebug,
+    InputMode,
+    NormalMode,
+    Submit,
+    CursorUp,
+    CursorDown,
+    CursorLeft,
+    CursorRight,
+    CursorTop,
+    CursorBottom,
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
+    PageUp,
+    PageDown,
+    PageLeft,
+    PageRight,
+    ToggleFold,
+    CloseFold,
+    OpenFold,
+    ToggleOutline,
+    FocusNext,
+    CopyPath,
+    SearchForward,
+    SearchBackward,
+    SearchNext,
+    SearchPrevious,
+    SearchScope,
+    CommandLine,
+    Complete,
+    VisualMode,
+    ExpandSelection,
+    ShrinkSelection,
+    DeleteSelection,
+    YankSelection,
+    CommentSelection,
+    IndentSelection,
+    DedentSelection,
+    SelectRegister,
+    Yank,
+    Paste,
+    PasteChild,
+    NextBuffer,
+    PreviousBuffer,
+    BufferList,
+    SplitBelow,
+    SplitRight,
+    NextPane,
+    PaneLeft,
+    PaneRight,
+    PaneUp,
+    PaneDown,
+    ClosePane,
+    OnlyPane,
+    Help,
+}
+
+impl Action {
+    const ALL: [Action; 62] = [
+        Action::Quit,
+        Action::Load,
+        Action::Write,
+        Action::LoadSpec,
+        Action::Validate,
+        Action::Info,
+        Action::DumpDebug,
+        Action::InputMode,
+        Action::NormalMode,
+        Action::Submit,
+        Action::CursorUp,
+        Action::CursorDown,
+        Action::CursorLeft,
+        Action::CursorRight,
+        Action::CursorTop,
+        Action::CursorBottom,
+        Action::ScrollUp,
+        Action::ScrollDown,
+        Action::ScrollLeft,
+        Action::ScrollRight,
+        Action::PageUp,
+        Action::PageDown,
+        Action::PageLeft,
+        Action::PageRight,
+        Action::ToggleFold,
+        Action::CloseFold,
+        Action::OpenFold,
+        Action::ToggleOutline,
+        Action::FocusNext,
+        Action::CopyPath,
+        Action::SearchForward,
+        Action::SearchBackward,
+        Action::SearchNext,
+        Action::SearchPrevious,
+        Action::SearchScope,
+        Action::CommandLine,
+        Action::Complete,
+        Action::VisualMode,
+        Action::ExpandSelection,
+        Action::ShrinkSelection,
+        Action::DeleteSelection,
+        Action::YankSelection,
+        Action::CommentSelection,
+        Action::IndentSelection,
+        Action::DedentSelection,
+        Action::SelectRegister,
+        Action::Yank,
+        Action::Paste,
+        Action::PasteChild,
+        Action::NextBuffer,
+        Action::PreviousBuffer,
+        Action::BufferList,
+        Action::SplitBelow,
+        Action::SplitRight,
+        Action::NextPane,
+        Action::PaneLeft,
+        Action::PaneRight,
+        Action::PaneUp,
+        Action::PaneDown,
+        Action::ClosePane,
+        Action::OnlyPane,
+        Action::Help,
+    ];
+
+    /// Name used to refer to the action in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Load => "load",
+            Action::Write => "write",
+            Action::LoadSpec => "load_spec",
+            Action::Validate => "validate",
+            Action::Info => "info",
+            Action::DumpDebug => "dump_debug",
+            Action::InputMode => "input_mode",
+            Action::NormalMode => "normal_mode",
+            Action::Submit => "submit",
+            Action::CursorUp => "cursor_up",
+            Action::CursorDown => "cursor_down",
+            Action::CursorLeft => "cursor_left",
+            Action::CursorRight => "cursor_right",
+            Action::CursorTop => "cursor_top",
+            Action::CursorBottom => "cursor_bottom",
+            Action::ScrollUp => "scroll_up",
+            Action::ScrollDown => "scroll_down",
+            Action::ScrollLeft => "scroll_left",
+            Action::ScrollRight => "scroll_right",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+            Action::PageLeft => "page_left",
+            Action::PageRight => "page_right",
+            Action::ToggleFold => "toggle_fold",
+            Action::CloseFold => "close_fold",
+            Action::OpenFold => "open_fold",
+            Action::ToggleOutline => "toggle_outline",
+            Action::FocusNext => "focus_next",
+            Action::CopyPath => "copy_path",
+            Action::SearchForward => "search_forward",
+            Action::SearchBackward => "search_backward",
+            Action::SearchNext => "search_next",
+            Action::SearchPrevious => "search_previous",
+            Action::SearchScope => "search_scope",
+            Action::CommandLine => "command_line",
+            Action::Complete => "complete",
+            Action::VisualMode => "visual_mode",
+            Action::ExpandSelection => "expand_selection",
+            Action::ShrinkSelection => "shrink_selection",
+            Action::DeleteSelection => "delete_selection",
+            Action::YankSelection => "yank_selection",
+            Action::CommentSelection => "comment_selection",
+            Action::IndentSelection => "indent_selection",
+            Action::DedentSelection => "dedent_selection",
+            Action::SelectRegister => "select_register",
+            Action::Yank => "yank",
+            Action::Paste => "paste",
+            Action::PasteChild => "paste_child",
+            Action::NextBuffer => "next_buffer",
+            Action::PreviousBuffer => "previous_buffer",
+            Action::BufferList => "buffer_list",
+            Action::SplitBelow => "split_below",
+            Action::SplitRight => "split_right",
+            Action::NextPane => "next_pane",
+            Action::PaneLeft => "pane_left",
+            Action::PaneRight => "pane_right",
+            Action::PaneUp => "pane_up",
+            Action::PaneDown => "pane_down",
+            Action::ClosePane => "close_pane",
+            Action::OnlyPane => "only_pane",
+            Action::Help => "help",
+        }
+    }
+
+    /// Short human readable description, used by the help text.
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Load => "load file",
+            Action::Write => "write file",
+            Action::LoadSpec => "load spec",
+            Action::Validate => "validate",
+            Action::Info => "log cursor info",
+            Action::DumpDebug => "dump debug state",
+            Action::InputMode => "input mode",
+            Action::NormalMode => "normal mode",
+            Action::Submit => "submit",
+            Action::CursorUp => "up",
+            Action::CursorDown => "down",
+            Action::CursorLeft => "left",
+            Action::CursorRight => "right",
+            Action::CursorTop => "first line",
+            Action::CursorBottom => "last line",
+            Action::ScrollUp => "scroll up",
+            Action::ScrollDown => "scroll down",
+            Action::ScrollLeft => "scroll left",
+            Action::ScrollRight => "scroll right",
+            Action::PageUp => "page up",
+            Action::PageDown => "page down",
+            Action::PageLeft => "page left",
+            Action::PageRight => "page right",
+            Action::ToggleFold => "toggle fold",
+            Action::CloseFold => "fold",
+            Action::OpenFold => "unfold",
+            Action::ToggleOutline => "outline",
+            Action::FocusNext => "switch panel",
+            Action::CopyPath => "copy path",
+            Action::SearchForward => "search",
+            Action::SearchBackward => "search backward",
+            Action::SearchNext => "next match",
+            Action::SearchPrevious => "previous match",
+            Action::SearchScope => "search keys/values/document",
+            Action::CommandLine => "command",
+            Action::Complete => "complete",
+            Action::VisualMode => "select",
+            Action::ExpandSelection => "expand",
+            Action::ShrinkSelection => "shrink",
+            Action::DeleteSelection => "delete",
+            Action::YankSelection => "yank",
+            Action::CommentSelection => "toggle comment",
+            Action::IndentSelection => "indent",
+            Action::DedentSelection => "dedent",
+            Action::SelectRegister => "register",
+            Action::Yank => "yank entry",
+            Action::Paste => "paste",
+            Action::PasteChild => "paste as child",
+            Action::NextBuffer => "next buffer",
+            Action::PreviousBuffer => "previous buffer",
+            Action::BufferList => "buffers",
+            Action::SplitBelow => "split below",
+            Action::SplitRight => "split right",
+            Action::NextPane => "next pane",
+            Action::PaneLeft => "pane left",
+            Action::PaneRight => "pane right",
+            Action::PaneUp => "pane above",
+            Action::PaneDown => "pane below",
+            Action::ClosePane => "close pane",
+            Action::OnlyPane => "close other panes",
+            Action::Help => "help",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    fn to_event(self, page: usize) -> AppEvent {
+        match self {
+            Action::Quit => AppEvent::Exit { force: false },
+            Action::Load => AppEvent::Load { force: false },
+            Action::Write => AppEvent::Write { force: false },
+            Action::LoadSpec => AppEvent::LoadSpec,
+            Action::Validate => AppEvent::Validate,
+            Action::Info => AppEvent::Info,
+            Action::DumpDebug => AppEvent::DumpDebug,
+            Action::InputMode => AppEvent::ChangeMode(AppMode::Input),
+            Action::NormalMode => AppEvent::ChangeMode(AppMode::Normal),
+            Action::Submit => AppEvent::Submit,
+            Action::CursorUp => AppEvent::CursorY(Delta::Dec(1)),
+            Action::CursorDown => AppEvent::CursorY(Delta::Inc(1)),
+            Action::CursorLeft => AppEvent::CursorX(Delta::Dec(1)),
+            Action::CursorRight => AppEvent::CursorX(Delta::Inc(1)),
+            Action::CursorTop => AppEvent::CursorTop,
+            Action::CursorBottom => AppEvent::CursorBottom,
+            Action::ScrollUp => AppEvent::ScrollY(Delta::Dec(1)),
+            Action::ScrollDown => AppEvent::ScrollY(Delta::Inc(1)),
+            Action::ScrollLeft => AppEvent::ScrollX(Delta::Dec(1)),
+            Action::ScrollRight => AppEvent::ScrollX(Delta::Inc(1)),
+            Action::PageUp => AppEvent::ScrollY(Delta::Dec(page)),
+            Action::PageDown => AppEvent::ScrollY(Delta::Inc(page)),
+            Action::PageLeft => AppEvent::ScrollX(Delta::Dec(page)),
+            Action::PageRight => AppEvent::ScrollX(Delta::Inc(page)),
+            Action::ToggleFold => AppEvent::Fold(FoldAction::Toggle),
+            Action::CloseFold => AppEvent::Fold(FoldAction::Close),
+            Action::OpenFold => AppEvent::Fold(FoldAction::Open),
+            Action::ToggleOutline => AppEvent::ToggleOutline,
+            Action::FocusNext => AppEvent::FocusNext,
+            Action::CopyPath => AppEvent::CopyPath,
+            Action::SearchForward => AppEvent::Search { backward: false },
+            Action::SearchBackward => AppEvent::Search { backward: true },
+            Action::SearchNext => AppEvent::SearchNext { reverse: false },
+            Action::SearchPrevious => AppEvent::SearchNext { reverse: true },
+            Action::SearchScope => AppEvent::SearchScope,
+            Action::CommandLine => AppEvent::CommandLine,
+            Action::Complete => AppEvent::Complete,
+            Action::VisualMode => AppEvent::ChangeMode(AppMode::Visual),
+            Action::ExpandSelection => AppEvent::ExpandSelection,
+            Action::ShrinkSelection => AppEvent::ShrinkSelection,
+            Action::DeleteSelection => AppEvent::Operator(Operator::Delete),
+            Action::YankSelection => AppEvent::Operator(Operator::Yank),
+            Action::CommentSelection => AppEvent::Operator(Operator::Comment),
+            Action::IndentSelection => AppEvent::Operator(Operator::Indent),
+            Action::DedentSelection => AppEvent::Operator(Operator::Dedent),
+            // Picked up by the event loop, which reads the register name from the next key
+            Action::SelectRegister => AppEvent::Register('"'),
+            Action::Yank => AppEvent::Yank,
+            Action::Paste => AppEvent::Paste { child: false },
+            Action::PasteChild => AppEvent::Paste { child: true },
+            Action::NextBuffer => AppEvent::NextBuffer { reverse: false },
+            Action::PreviousBuffer => AppEvent::NextBuffer { reverse: true },
+            Action::BufferList => AppEvent::BufferList,
+            Action::SplitBelow => AppEvent::Split(Direction::Vertical),
+            Action::SplitRight => AppEvent::Split(Direction::Horizontal),
+            Action::NextPane => AppEvent::FocusPane(PaneFocus::Next),
+            Action::PaneLeft => AppEvent::FocusPane(PaneFocus::Left),
+            Action::PaneRight => AppEvent::FocusPane(PaneFocus::Right),
+            Action::PaneUp => AppEvent::FocusPane(PaneFocus::Up),
+            Action::PaneDown => AppEvent::FocusPane(PaneFocus::Down),
+            Action::ClosePane => AppEvent::ClosePane,
+            Action::OnlyPane => AppEvent::OnlyPane,
+            Action::Help => AppEvent::Help,
+        }
+    }
+}
+
+/// A single key press, including modifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Shift is already part of the character for printable keys
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers.difference(KeyModifiers::SHIFT),
+            _ => modifiers,
+        };
+        KeyChord { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(event: KeyEvent) -> Self {
+        KeyChord::new(event.code, event.modifiers)
+    }
+}
+
+const NAMED_KEYS: [(&str, KeyCode); 17] = [
+    ("Enter", KeyCode::Enter),
+    ("Esc", KeyCode::Esc),
+    ("Tab", KeyCode::Tab),
+    ("BS", KeyCode::Backspace),
+    ("Del", KeyCode::Delete),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("Space", KeyCode::Char(' ')),
+    ("lt", KeyCode::Char('<')),
+    ("gt", KeyCode::Char('>')),
+    ("Insert", KeyCode::Insert),
+];
+
+impl std::fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let named = NAMED_KEYS
+            .iter()
+            .find(|(_, code)| *code == self.code)
+            .map(|(name, _)| (*name).to_string());
+
+        let key = match (named, self.code) {
+            (Some(name), _) => name,
+            (None, KeyCode::Char(c)) => c.to_string(),
+            (None, KeyCode::F(n)) => format!("F{n}"),
+            (None, code) => format!("{code:?}"),
+        };
+
+        let mut prefix = String::new();
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "C-"),
+            (KeyModifiers::ALT, "A-"),
+            (KeyModifiers::SHIFT, "S-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                prefix.push_str(name);
+            }
+        }
+
+        if prefix.is_empty() && key.chars().count() == 1 {
+            write!(f, "{key}")
+        } else {
+            write!(f, "<{prefix}{key}>")
+        }
+    }
+}
+
+/// Parse a key sequence such as `gg`, `<C-x><C-s>` or `<S-PageUp>`.
+pub fn parse_keys(input: &str) -> Result<Vec<KeyChord>, KeymapError> {
+    let invalid = || KeymapError::InvalidKeys(input.to_string());
+    let mut keys = vec![];
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '<' {
+            keys.push(KeyChord::new(KeyCode::Char(c), KeyModifiers::NONE));
+            continue;
+        }
+
+        let mut inner = String::new();
+        for c in chars.by_ref() {
+            if c == '>' {
+                break;
+            }
+            inner.push(c);
+        }
+
+        let mut modifiers = KeyModifiers::NONE;
+        let mut name = inner.as_str();
+        while let Some((prefix, rest)) = name.split_once('-').filter(|(_, rest)| !rest.is_empty()) {
+            modifiers |= match prefix {
+                "C" => KeyModifiers::CONTROL,
+                "A" | "M" => KeyModifiers::ALT,
+                "S" => KeyModifiers::SHIFT,
+                _ => Err(invalid())?,
+            };
+            name = rest;
+        }
+
+        let code = match NAMED_KEYS
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        {
+            Some((_, code)) => *code,
+            None if name.chars().count() == 1 => KeyCode::Char(name.chars().next().unwrap()),
+            None => match name.strip_prefix('F').map(str::parse::<u8>) {
+                Some(Ok(n)) => KeyCode::F(n),
+                _ => Err(invalid())?,
+            },
+        };
+        keys.push(KeyChord::new(code, modifiers));
+    }
+
+    if keys.is_empty() {
+        Err(invalid())?;
+    }
+    Ok(keys)
+}
+
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub keys: Vec<KeyChord>,
+    pub action: Action,
+}
+
+impl Binding {
+    /// Key sequence in the same notation as the config file.
+    pub fn keys_text(&self) -> String {
+        self.keys.iter().fold(String::new(), |mut s, k| {
+            let _ = write!(s, "{k}");
+            s
+        })
+    }
+}
+
+/// Result of feeding a key into the keymap.
+pub enum KeyResult {
+    /// The pending sequence completed a binding
+    Action(Action),
+    /// The pending sequence is a prefix of at least one binding
+    Pending,
+    /// Nothing is bound to the key
+    Unbound,
+}
+
+/// Key bindings for each mode.
+#[derive(Debug)]
+pub struct Keymap {
+    normal: Vec<Binding>,
+    input: Vec<Binding>,
+    command: Vec<Binding>,
+    visual: Vec<Binding>,
+    page: usize,
+}
+
+impl Keymap {
+    pub fn from_config(config: &KeymapConfig, editor: &EditorConfig) -> Result<Self, KeymapError> {
+        let mut keymap = match config.preset.as_str() {
+            "vim" => Keymap::vim(),
+            "emacs" => Keymap::emacs(),
+            preset => Err(KeymapError::UnknownPreset(preset.to_string()))?,
+        };
+        keymap.page = editor.scroll_step;
+
+        for (mode, overrides) in [
+            (AppMode::Normal, &config.normal),
+            (AppMode::Input, &config.input),
+            (AppMode::Command, &config.command),
+            (AppMode::Visual, &config.visual),
+        ] {
+            // Sorted so that the resulting help text is stable
+            let mut overrides = overrides.iter().collect::<Vec<_>>();
+            overrides.sort();
+
+            let mut bound = vec![];
+            let bindings = keymap.bindings_mut(&mode);
+            for (keys, action) in overrides {
+                let parsed = parse_keys(keys)?;
+                bindings.retain(|b| b.keys != parsed);
+
+                // `none` unbinds the keys from the preset
+                if action == "none" {
+                    continue;
+                }
+                let action = Action::from_name(action)
+                    .ok_or(KeymapError::UnknownAction(keys.clone(), action.clone()))?;
+                bound.push((keys, parsed.clone()));
+                bindings.push(Binding {
+                    keys: parsed,
+                    action,
+                });
+            }
+
+            // An exact match resolves right away, so a binding that is a prefix of another one
+            // would hide it. Only overrides are checked, the presets are free of such conflicts.
+            for (keys, parsed) in bound {
+                let conflict = keymap.bindings(&mode).iter().find(|b| {
+                    b.keys != parsed && (b.keys.starts_with(&parsed) || parsed.starts_with(&b.keys))
+                });
+                if let Some(other) = conflict {
+                    let (prefix, longer) = if other.keys.len() > parsed.len() {
+                        (keys.clone(), other.keys_text())
+                    } else {
+                        (other.keys_text(), keys.clone())
+                    };
+                    Err(KeymapError::PrefixConflict(prefix, longer))?;
+                }
+            }
+        }
+
+        Ok(keymap)
+    }
+
+    fn from_table(
+        normal: &[(&str, Action)],
+        input: &[(&str, Action)],
+        command: &[(&str, Action)],
+        visual: &[(&str, Action)],
+    ) -> Self {
+        let bindings = |table: &[(&str, Action)]| {
+            table
+                .iter()
+                .map(|(keys, action)| Binding {
+                    keys: parse_keys(keys).expect("Preset key sequences are valid"),
+                    action: *action,
+                })
+                .collect()
+        };
+
+        Keymap {
+            normal: bindings(normal),
+            input: bindings(input),
+            command: bindings(command),
+            visual: bindings(visual),
+            page: 10,
+        }
+    }
+
+    fn vim() -> Self {
+        Keymap::from_table(
+            &[
+                ("<Enter>", Action::InputMode),
+                ("q", Action::Quit),
+                ("<C-l>", Action::Load),
+                ("<C-s>", Action::Write),
+                ("S", Action::LoadSpec),
+                ("!", Action::Validate),
+                ("i", Action::Info),
+                ("<C-d>", Action::DumpDebug),
+                ("k", Action::CursorUp),
+                ("<Up>", Action::CursorUp),
+                ("j", Action::CursorDown),
+                ("<Down>", Action::CursorDown),
+                ("h", Action::CursorLeft),
+                ("<Left>", Action::CursorLeft),
+                ("l", Action::CursorRight),
+                ("<Right>", Action::CursorRight),
+                ("gg", Action::CursorTop),
+                ("G", Action::CursorBottom),
+                ("K", Action::ScrollUp),
+                ("J", Action::ScrollDown),
+                ("H", Action::ScrollLeft),
+                ("L", Action::ScrollRight),
+                ("<PageUp>", Action::PageUp),
+                ("<PageDown>", Action::PageDown),
+                ("<S-PageUp>", Action::PageLeft),
+                ("<S-PageDown>", Action::PageRight),
+                ("za", Action::ToggleFold),
+                ("zc", Action::CloseFold),
+                ("zo", Action::OpenFold),
+                ("gO", Action::ToggleOutline),
+                ("yp", Action::CopyPath),
+                ("/", Action::SearchForward),
+                ("<C-r>", Action::SearchBackward),
+                ("n", Action::SearchNext),
+                ("N", Action::SearchPrevious),
+                (":", Action::CommandLine),
+                ("v", Action::VisualMode),
+                ("\"", Action::SelectRegister),
+                ("yy", Action::Yank),
+                ("p", Action::Paste),
+                ("P", Action::PasteChild),
+                ("]b", Action::NextBuffer),
+                ("[b", Action::PreviousBuffer),
+                ("gb", Action::BufferList),
+                ("<C-w>s", Action::SplitBelow),
+                ("<C-w>v", Action::SplitRight),
+                ("<C-w>w", Action::NextPane),
+                ("<C-w>h", Action::PaneLeft),
+                ("<C-w>l", Action::PaneRight),
+                ("<C-w>k", Action::PaneUp),
+                ("<C-w>j", Action::PaneDown),
+                ("<C-w>c", Action::ClosePane),
+                ("<C-w>o", Action::OnlyPane),
+                ("?", Action::Help),
+                ("<F1>", Action::Help),
+                ("<Tab>", Action::FocusNext),
+            ],
+            &[("<Esc>", Action::NormalMode), ("<Enter>", Action::Submit)],
+            &[
+                ("<Esc>", Action::NormalMode),
+                ("<Enter>", Action::Submit),
+                ("<C-t>", Action::SearchScope),
+                ("<Tab>", Action::Complete),
+            ],
+            &[
+                ("<Esc>", Action::NormalMode),
+                ("v", Action::ExpandSelection),
+                ("+", Action::ExpandSelection),
+                ("_", Action::ShrinkSelection),
+                ("k", Action::CursorUp),
+                ("<Up>", Action::CursorUp),
+                ("j", Action::CursorDown),
+                ("<Down>", Action::CursorDown),
+                ("h", Action::CursorLeft),
+                ("<Left>", Action::CursorLeft),
+                ("l", Action::CursorRight),
+                ("<Right>", Action::CursorRight),
+                ("d", Action::DeleteSelection),
+                ("x", Action::DeleteSelection),
+                ("\"", Action::SelectRegister),
+                ("y", Action::YankSelection),
+                ("gc", Action::CommentSelection),
+                (">", Action::IndentSelection),
+                ("<lt>", Action::DedentSelection),
+            ],
+        )
+    }
+
+    fn emacs() -> Self {
+        Keymap::from_table(
+            &[
+                ("<Enter>", Action::InputMode),
+                ("<C-x><C-c>", Action::Quit),
+                ("<C-x><C-f>", Action::Load),
+                ("<C-x><C-s>", Action::Write),
+                ("<C-c>s", Action::LoadSpec),
+                ("<C-c>v", Action::Validate),
+                ("<C-c>i", Action::Info),
+                ("<C-c>d", Action::DumpDebug),
+                ("<C-p>", Action::CursorUp),
+                ("<Up>", Action::CursorUp),
+                ("<C-n>", Action::CursorDown),
+                ("<Down>", Action::CursorDown),
+                ("<C-b>", Action::CursorLeft),
+                ("<Left>", Action::CursorLeft),
+                ("<C-f>", Action::CursorRight),
+                ("<Right>", Action::CursorRight),
+                ("<A-lt>", Action::CursorTop),
+                ("<A-gt>", Action::CursorBottom),
+                ("<A-p>", Action::ScrollUp),
+                ("<A-n>", Action::ScrollDown),
+                ("<C-v>", Action::PageDown),
+                ("<A-v>", Action::PageUp),
+                ("<PageUp>", Action::PageUp),
+                ("<PageDown>", Action::PageDown),
+                ("<S-PageUp>", Action::PageLeft),
+                ("<S-PageDown>", Action::PageRight),
+                ("<C-c>@<C-c>", Action::ToggleFold),
+                ("<C-c>@<C-h>", Action::CloseFold),
+                ("<C-c>@<C-s>", Action::OpenFold),
+                ("<C-c>o", Action::ToggleOutline),
+                ("<C-c>p", Action::CopyPath),
+                ("<C-s>", Action::SearchForward),
+                ("<C-r>", Action::SearchBackward),
+                ("<C-c>n", Action::SearchNext),
+                ("<C-c>N", Action::SearchPrevious),
+                ("<A-x>", Action::CommandLine),
+                ("<C-Space>", Action::VisualMode),
+                ("<C-x>r", Action::SelectRegister),
+                ("<A-w>", Action::Yank),
+                ("<C-y>", Action::Paste),
+                ("<C-c><C-y>", Action::PasteChild),
+                ("<C-x><Right>", Action::NextBuffer),
+                ("<C-x><Left>", Action::PreviousBuffer),
+                ("<C-x><C-b>", Action::BufferList),
+                ("<C-x>2", Action::SplitBelow),
+                ("<C-x>3", Action::SplitRight),
+                ("<C-x>o", Action::NextPane),
+                ("<S-Left>", Action::PaneLeft),
+                ("<S-Right>", Action::PaneRight),
+                ("<S-Up>", Action::PaneUp),
+                ("<S-Down>", Action::PaneDown),
+                ("<C-x>0", Action::ClosePane),
+                ("<C-x>1", Action::OnlyPane),
+                ("<C-c>?", Action::Help),
+                ("<F1>", Action::Help),
+                ("<Tab>", Action::FocusNext),
+            ],
+            &[
+                ("<C-g>", Action::NormalMode),
+                ("<Esc>", Action::NormalMode),
+                ("<Enter>", Action::Submit),
+            ],
+            &[
+                ("<C-g>", Action::NormalMode),
+                ("<Esc>", Action::NormalMode),
+                ("<Enter>", Action::Submit),
+                ("<C-t>", Action::SearchScope),
+                ("<Tab>", Action::Complete),
+            ],
+            &[
+                ("<C-g>", Action::NormalMode),
+                ("<Esc>", Action::NormalMode),
+                ("<C-Space>", Action::ExpandSelection),
+                ("<BS>", Action::ShrinkSelection),
+                ("<C-p>", Action::CursorUp),
+                ("<Up>", Action::CursorUp),
+                ("<C-n>", Action::CursorDown),
+                ("<Down>", Action::CursorDown),
+                ("<C-b>", Action::CursorLeft),
+                ("<Left>", Action::CursorLeft),
+                ("<C-f>", Action::CursorRight),
+                ("<Right>", Action::CursorRight),
+                ("<C-w>", Action::DeleteSelection),
+                ("<C-x>r", Action::SelectRegister),
+                ("<A-w>", Action::YankSelection),
+                ("<A-;>", Action::CommentSelection),
+                ("<C-c>>", Action::IndentSelection),
+                ("<C-c><lt>", Action::DedentSelection),
+            ],
+        )
+    }
+
+    pub fn bindings(&self, mode: &AppMode) -> &[Binding] {
+        match mode {
+            AppMode::Normal => &self.normal,
+            AppMode::Input => &self.input,
+            AppMode::Command => &self.command,
+            AppMode::Visual => &self.visual,
+        }
+    }
+
+    fn bindings_mut(&mut self, mode: &AppMode) -> &mut Vec<Binding> {
+        match mode {
+            AppMode::Normal => &mut self.normal,
+            AppMode::Input => &mut self.input,
+            AppMode::Command => &mut self.command,
+            AppMode::Visual => &mut self.visual,
+        }
+    }
+
+    /// Bindings grouped by action, in the order the actions were first bound.
+    pub fn help(&self, mode: &AppMode) -> Vec<(Vec<String>, Action)> {
+        let mut help: Vec<(Vec<String>, Action)> = vec![];
+        for binding in self.bindings(mode) {
+            match help
+                .iter_mut()
+                .find(|(_, action)| *action == binding.action)
+            {
+                Some((keys, _)) => keys.push(binding.keys_text()),
+                None => help.push((vec![binding.keys_text()], binding.action)),
+            }
+        }
+        help
+    }
+
+    /// Resolve `pending` against the bindings of `mode`. Keys are cleared from `pending` once
+    /// they either complete a binding or can no longer match one.
+    pub fn resolve(&self, mode: &AppMode, pending: &mut Vec<KeyChord>) -> KeyResult {
+        let bindings = self.bindings(mode);
+
+        if let Some(binding) = bindings.iter().find(|b| b.keys == *pending) {
+            pending.clear();
+            return KeyResult::Action(binding.action);
+        }
+
+        if bindings.iter().any(|b| b.keys.starts_with(pending)) {
+            return KeyResult::Pending;
+        }
+
+        // A broken sequence restarts from its last key, e.g. `gj` is treated as `j`
+        if pending.len() > 1 {
+            let last = pending.pop().expect("Pending has more than one key");
+            pending.clear();
+            pending.push(last);
+            return self.resolve(mode, pending);
+        }
+
+        pending.clear();
+        KeyResult::Unbound
+    }
+
+    pub fn event(&self, action: Action) -> AppEvent {
+        action.to_event(self.page)
+    }
+}
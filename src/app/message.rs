@@ -0,0 +1,48 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How long a message stays on the message line.
+pub const TOAST: Duration = Duration::from_secs(5);
+
+/// Messages kept for `:messages`, older ones are dropped.
+pub const HISTORY: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Notification shown on the message line, and kept for `:messages`.
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub severity: Severity,
+    pub text: String,
+    pub time: Instant,
+}
+
+impl Message {
+    pub fn new(severity: Severity, text: String) -> Self {
+        Self {
+            severity,
+            text,
+            time: Instant::now(),
+        }
+    }
+
+    /// Whether it is still shown on the message line.
+    pub fn is_toast(&self) -> bool {
+        self.time.elapsed() < TOAST
+    }
+}
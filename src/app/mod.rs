@@ -1,17 +1,30 @@
 #[allow(clippy::module_inception)]
 mod app;
+mod buffer;
+mod clipboard;
+mod command;
 mod components;
 mod error;
 mod event;
-mod file;
+pub(crate) mod file;
+mod keymap;
+mod message;
+pub(crate) mod theme;
 mod traits;
+mod watcher;
 
-pub use app::{App, AppState};
+pub use app::{App, AppState, State};
+pub use buffer::{Buffer, View};
 pub use error::AppError;
+pub use file::{
+    Context, Diagnostic, Error as FileError, File, FoldAction, Focus, Query, Rule, Selector,
+    context_at, skeleton, split_assignment,
+};
+pub(crate) use file::Value;
 pub use traits::AppComponent;
 
-use event::{AppEvent, Delta};
-use file::File;
+use event::{AppEvent, Delta, MouseAction, Operator, PaneFocus};
+use message::Severity;
 
 #[derive(Default, Debug, Clone, PartialEq)]
 pub enum AppMode {
@@ -19,6 +32,8 @@ pub enum AppMode {
     Normal,
     Input,
     Command,
+    /// A node is selected for an operator
+    Visual,
 }
 
 impl AppMode {
@@ -27,6 +42,7 @@ impl AppMode {
             AppMode::Normal => "NORMAL",
             AppMode::Input => "INPUT",
             AppMode::Command => "COMMAND",
+            AppMode::Visual => "VISUAL",
         }
     }
 }
@@ -0,0 +1,251 @@
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use std::env;
+
+use super::message;
+use crate::config::Severity;
+
+/// Syntax groups that tokens are highlighted as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    Text,
+    Key,
+    String,
+    Number,
+    Boolean,
+    Null,
+    Comment,
+    Anchor,
+    Tag,
+    DocumentMarker,
+    Directive,
+    Punctuation,
+}
+
+#[derive(Debug, Clone)]
+pub struct Theme {
+    key: Style,
+    string: Style,
+    number: Style,
+    boolean: Style,
+    null: Style,
+    comment: Style,
+    anchor: Style,
+    tag: Style,
+    document_marker: Style,
+    directive: Style,
+    punctuation: Style,
+    diagnostic_error: Style,
+    diagnostic_warning: Style,
+    message_error: Style,
+    message_warning: Style,
+    /// Line number gutter
+    pub gutter: Style,
+    /// Line number of the line with the cursor
+    pub gutter_cursor: Style,
+    /// Airline background
+    pub airline: Style,
+    /// Mode indicator in the airline
+    pub airline_mode: Style,
+    /// Text matching the current search
+    pub search_match: Style,
+    /// Text selected in visual mode
+    pub selection: Style,
+    /// Lines only on disk and only in the buffer, in the diff of a file changed by another program
+    pub diff_added: Style,
+    pub diff_removed: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    /// Names accepted by [`Theme::from_name`].
+    pub const NAMES: [&'static str; 5] = ["dark", "light", "high-contrast", "monochrome", "auto"];
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            "monochrome" => Some(Theme::monochrome()),
+            "auto" => Some(Theme::auto()),
+            _ => None,
+        }
+    }
+
+    pub fn style(&self, highlight: Highlight) -> Style {
+        match highlight {
+            Highlight::Text => Style::default(),
+            Highlight::Key => self.key,
+            Highlight::String => self.string,
+            Highlight::Number => self.number,
+            Highlight::Boolean => self.boolean,
+            Highlight::Null => self.null,
+            Highlight::Comment => self.comment,
+            Highlight::Anchor => self.anchor,
+            Highlight::Tag => self.tag,
+            Highlight::DocumentMarker => self.document_marker,
+            Highlight::Directive => self.directive,
+            Highlight::Punctuation => self.punctuation,
+        }
+    }
+
+    /// Style patched onto tokens that have a validation diagnostic.
+    pub fn diagnostic(&self, severity: Severity) -> Style {
+        match severity {
+            Severity::Error => self.diagnostic_error,
+            Severity::Warning => self.diagnostic_warning,
+            Severity::Off => Style::default(),
+        }
+    }
+
+    /// Style of a notification on the message line.
+    pub fn message(&self, severity: message::Severity) -> Style {
+        match severity {
+            message::Severity::Error => self.message_error,
+            message::Severity::Warning => self.message_warning,
+            message::Severity::Info => Style::default(),
+        }
+    }
+
+    fn dark() -> Self {
+        Theme {
+            key: Style::new().bold().fg(Color::Yellow),
+            string: Style::new().fg(Color::Green),
+            number: Style::new().fg(Color::Cyan),
+            boolean: Style::new().fg(Color::Magenta),
+            null: Style::new().fg(Color::DarkGray),
+            comment: Style::new().italic().fg(Color::DarkGray),
+            anchor: Style::new().fg(Color::LightRed),
+            tag: Style::new().fg(Color::LightBlue),
+            document_marker: Style::new().bold().fg(Color::LightMagenta),
+            directive: Style::new().fg(Color::Blue),
+            punctuation: Style::new().fg(Color::Gray),
+            diagnostic_error: Style::new().underlined().underline_color(Color::Red),
+            diagnostic_warning: Style::new().underlined().underline_color(Color::Yellow),
+            message_error: Style::new().bold().fg(Color::Red),
+            message_warning: Style::new().fg(Color::Yellow),
+            gutter: Style::new().bg(Color::Indexed(22)),
+            gutter_cursor: Style::new().bg(Color::Indexed(236)),
+            airline: Style::new().fg(Color::Black).bg(Color::Indexed(54)),
+            airline_mode: Style::new().bold().bg(Color::Green),
+            search_match: Style::new().fg(Color::Black).bg(Color::Yellow),
+            selection: Style::new().bg(Color::Indexed(238)),
+            diff_added: Style::new().fg(Color::Green),
+            diff_removed: Style::new().fg(Color::Red),
+        }
+    }
+
+    fn light() -> Self {
+        Theme {
+            key: Style::new().bold().fg(Color::Blue),
+            string: Style::new().fg(Color::Indexed(28)),
+            number: Style::new().fg(Color::Indexed(130)),
+            boolean: Style::new().fg(Color::Magenta),
+            null: Style::new().fg(Color::Gray),
+            comment: Style::new().italic().fg(Color::Indexed(244)),
+            anchor: Style::new().fg(Color::Red),
+            tag: Style::new().fg(Color::Indexed(25)),
+            document_marker: Style::new().bold().fg(Color::Magenta),
+            directive: Style::new().fg(Color::Indexed(25)),
+            punctuation: Style::new().fg(Color::Indexed(240)),
+            diagnostic_error: Style::new().underlined().underline_color(Color::Red),
+            diagnostic_warning: Style::new()
+                .underlined()
+                .underline_color(Color::Indexed(130)),
+            message_error: Style::new().bold().fg(Color::Red),
+            message_warning: Style::new().fg(Color::Indexed(130)),
+            gutter: Style::new().fg(Color::Indexed(240)).bg(Color::Indexed(254)),
+            gutter_cursor: Style::new().fg(Color::Black).bg(Color::Indexed(250)),
+            airline: Style::new().fg(Color::Black).bg(Color::Indexed(153)),
+            airline_mode: Style::new().bold().fg(Color::White).bg(Color::Blue),
+            search_match: Style::new().fg(Color::Black).bg(Color::Indexed(222)),
+            selection: Style::new().bg(Color::Indexed(252)),
+            diff_added: Style::new().fg(Color::Indexed(28)),
+            diff_removed: Style::new().fg(Color::Red),
+        }
+    }
+
+    fn high_contrast() -> Self {
+        Theme {
+            key: Style::new().bold().fg(Color::LightYellow),
+            string: Style::new().fg(Color::LightGreen),
+            number: Style::new().fg(Color::LightCyan),
+            boolean: Style::new().bold().fg(Color::LightMagenta),
+            null: Style::new().bold().fg(Color::LightRed),
+            comment: Style::new().italic().fg(Color::White),
+            anchor: Style::new().underlined().fg(Color::LightRed),
+            tag: Style::new().bold().fg(Color::LightBlue),
+            document_marker: Style::new().bold().fg(Color::Black).bg(Color::White),
+            directive: Style::new().bold().fg(Color::LightBlue),
+            punctuation: Style::new().bold().fg(Color::White),
+            diagnostic_error: Style::new()
+                .bold()
+                .underlined()
+                .fg(Color::White)
+                .bg(Color::Red),
+            diagnostic_warning: Style::new()
+                .bold()
+                .underlined()
+                .fg(Color::Black)
+                .bg(Color::Yellow),
+            message_error: Style::new().bold().fg(Color::White).bg(Color::Red),
+            message_warning: Style::new().bold().fg(Color::Black).bg(Color::Yellow),
+            gutter: Style::new().fg(Color::White).bg(Color::Black),
+            gutter_cursor: Style::new().bold().fg(Color::Black).bg(Color::LightYellow),
+            airline: Style::new().fg(Color::Black).bg(Color::White),
+            airline_mode: Style::new().bold().fg(Color::Black).bg(Color::LightYellow),
+            search_match: Style::new().bold().fg(Color::Black).bg(Color::LightCyan),
+            selection: Style::new().bg(Color::Blue),
+            diff_added: Style::new().bold().fg(Color::LightGreen),
+            diff_removed: Style::new().bold().fg(Color::LightRed),
+        }
+    }
+
+    // Modifiers only, for terminals without (or with unreliable) colour support
+    fn monochrome() -> Self {
+        Theme {
+            key: Style::new().bold(),
+            string: Style::new(),
+            number: Style::new(),
+            boolean: Style::new(),
+            null: Style::new().dim(),
+            comment: Style::new().italic().dim(),
+            anchor: Style::new().underlined(),
+            tag: Style::new().italic(),
+            document_marker: Style::new().bold(),
+            directive: Style::new().italic(),
+            punctuation: Style::new().dim(),
+            diagnostic_error: Style::new().underlined().bold(),
+            diagnostic_warning: Style::new().underlined(),
+            message_error: Style::new().bold().underlined(),
+            message_warning: Style::new().bold(),
+            gutter: Style::new().dim(),
+            gutter_cursor: Style::new().bold(),
+            airline: Style::new().add_modifier(Modifier::REVERSED),
+            airline_mode: Style::new().bold(),
+            search_match: Style::new().underlined().bold(),
+            selection: Style::new().add_modifier(Modifier::REVERSED),
+            diff_added: Style::new().bold(),
+            diff_removed: Style::new().dim(),
+        }
+    }
+
+    // Follows the terminal background, as reported through `COLORFGBG` (`<fg>;<bg>`). Backgrounds
+    // 0-6 and 8 are the dark ANSI colours. Falls back to the dark theme when unset.
+    fn auto() -> Self {
+        let light_background = env::var("COLORFGBG")
+            .ok()
+            .and_then(|v| v.rsplit(';').next().and_then(|bg| bg.parse::<u8>().ok()))
+            .is_some_and(|bg| !matches!(bg, 0..=6 | 8));
+
+        if light_background {
+            Theme::light()
+        } else {
+            Theme::dark()
+        }
+    }
+}
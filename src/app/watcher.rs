@@ -0,0 +1,58 @@
+use log::warn;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, channel};
+
+/// Watches the files of open buffers for changes by other programs. Their directories are
+/// watched rather than the files, as tools like git replace a file instead of writing to it.
+pub struct Watcher {
+    notify: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    /// Watched files, as absolute paths
+    files: HashSet<PathBuf>,
+    directories: HashSet<PathBuf>,
+}
+
+impl Watcher {
+    pub fn new() -> notify::Result<Self> {
+        let (sender, events) = channel();
+        Ok(Self {
+            notify: notify::recommended_watcher(sender)?,
+            events,
+            files: HashSet::new(),
+            directories: HashSet::new(),
+        })
+    }
+
+    pub fn watch(&mut self, path: &Path) -> notify::Result<()> {
+        let path = std::path::absolute(path)?;
+        if let Some(directory) = path.parent()
+            && !self.directories.contains(directory)
+        {
+            self.notify.watch(directory, RecursiveMode::NonRecursive)?;
+            self.directories.insert(directory.to_path_buf());
+        }
+        self.files.insert(path);
+        Ok(())
+    }
+
+    /// Watched files written, created or moved into place since the last call.
+    pub fn changed(&self) -> Vec<PathBuf> {
+        let mut changed = vec![];
+        for event in self.events.try_iter() {
+            match event {
+                Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                    for path in event.paths {
+                        if self.files.contains(&path) && !changed.contains(&path) {
+                            changed.push(path);
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Could not watch files: {e}"),
+            }
+        }
+        changed
+    }
+}
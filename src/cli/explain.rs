@@ -0,0 +1,157 @@
+use std::fmt::Write as _;
+use std::process::ExitCode;
+
+use crate::api_client::{self, QueryPath, SchemaRef, SchemaType};
+use crate::config::Config;
+use crate::error::Error;
+
+const WIDTH: usize = 80;
+
+pub async fn run(
+    config: &Config,
+    path: &str,
+    recursive: bool,
+    api_version: Option<&str>,
+) -> Result<ExitCode, Error> {
+    let (kind, field_path) = match path.split_once('.') {
+        Some((kind, fields)) => (kind, QueryPath::parse(fields)),
+        None => (path, None),
+    };
+
+    let mut api_client = api_client::from_config(config).await?;
+    let spec = match api_version {
+        Some(api_version) => api_client.get_group_spec(&api_version.into()).await?,
+        None => api_client.find_kind(kind).await?,
+    };
+
+    let schema = match &field_path {
+        Some(field_path) => spec.get_kind_path(kind, field_path)?,
+        None => spec.get_kind(kind)?,
+    };
+
+    let mut output = String::new();
+    let _ = writeln!(output, "KIND:       {}", spec.kind_name(kind)?);
+    let _ = writeln!(output, "VERSION:    {}", spec.group().api_version());
+    if let Some(field) = field_path.as_ref().and_then(|p| p.keys().last()) {
+        let _ = writeln!(output, "\nFIELD: {field} <{}>", schema.type_name());
+    }
+    if let SchemaType::String(allowed) = schema.schema_type()
+        && !allowed.is_empty()
+    {
+        let _ = writeln!(output, "ENUM: {}", allowed.join(", "));
+    }
+
+    output.push_str("\nDESCRIPTION:\n");
+    output.push_str(&wrap(schema.description().unwrap_or("<empty>"), 4));
+
+    let fields = fields(schema);
+    if !fields.is_empty() {
+        output.push_str("\nFIELDS:\n");
+        if recursive {
+            let mut seen = vec![];
+            write_tree(&mut output, schema, 1, &mut seen);
+        } else {
+            write_fields(&mut output, schema);
+        }
+    }
+
+    print!("{output}");
+    Ok(ExitCode::SUCCESS)
+}
+
+// Fields of an object, or of the items of an array
+fn fields(schema: SchemaRef<'_>) -> Vec<(&str, SchemaRef<'_>)> {
+    match schema.schema_type() {
+        SchemaType::Array => schema.items().map(fields).unwrap_or_default(),
+        _ => schema.properties(),
+    }
+}
+
+fn required(schema: SchemaRef<'_>) -> &[String] {
+    match schema.schema_type() {
+        SchemaType::Array => schema.items().map_or(&[], |items| items.required()),
+        _ => schema.required(),
+    }
+}
+
+fn field_line(name: &str, field: SchemaRef<'_>, required: bool) -> String {
+    let marker = if required { " -required-" } else { "" };
+    format!("{name}\t<{}>{marker}", field.type_name())
+}
+
+// One entry per field with its enum values and description
+fn write_fields(output: &mut String, schema: SchemaRef<'_>) {
+    let required = required(schema);
+    for (name, field) in fields(schema) {
+        let _ = writeln!(
+            output,
+            "  {}",
+            field_line(name, field, required.iter().any(|r| r == name))
+        );
+        if let SchemaType::String(allowed) = field.schema_type()
+            && !allowed.is_empty()
+        {
+            let _ = writeln!(output, "  enum: {}", allowed.join(", "));
+        }
+        output.push_str(&wrap(field.description().unwrap_or("<no description>"), 4));
+        output.push('\n');
+    }
+}
+
+// Field names and types of all nested objects. `seen` holds the schemas on the current branch, so
+// recursive schemas such as `JSONSchemaProps` stop after one level.
+fn write_tree<'a>(
+    output: &mut String,
+    schema: SchemaRef<'a>,
+    depth: usize,
+    seen: &mut Vec<&'a str>,
+) {
+    let required = required(schema);
+    for (name, field) in fields(schema) {
+        let _ = writeln!(
+            output,
+            "{}{}",
+            "  ".repeat(depth),
+            field_line(name, field, required.iter().any(|r| r == name))
+        );
+
+        let child = match field.schema_type() {
+            SchemaType::Array => field.items().unwrap_or(field),
+            _ => field,
+        };
+        if child.name().is_some_and(|n| seen.contains(&n)) {
+            continue;
+        }
+
+        seen.extend(child.name());
+        write_tree(output, field, depth + 1, seen);
+        if child.name().is_some() {
+            seen.pop();
+        }
+    }
+}
+
+// Wrap each paragraph of `text` to `WIDTH` columns, indented by `indent` spaces
+fn wrap(text: &str, indent: usize) -> String {
+    let prefix = " ".repeat(indent);
+    let mut wrapped = String::new();
+
+    for paragraph in text.lines() {
+        let mut line = prefix.clone();
+        for word in paragraph.split_whitespace() {
+            if line.len() > indent && line.len() + word.len() + 1 > WIDTH {
+                wrapped.push_str(&line);
+                wrapped.push('\n');
+                line.clone_from(&prefix);
+            }
+            if line.len() > indent {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        wrapped.push_str(&line);
+        wrapped.push('\n');
+    }
+
+    wrapped
+}
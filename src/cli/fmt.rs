@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use super::{EXIT_PROBLEMS, collect_files};
+use crate::app::File;
+use crate::config::Config;
+use crate::error::Error;
+
+pub fn run(config: &Config, paths: &[PathBuf], check: bool) -> Result<ExitCode, Error> {
+    let mut unformatted = 0;
+    let mut failed = false;
+
+    for path in collect_files(paths)? {
+        // Keep going, so that one broken file does not hide the others
+        let formatted = File::from_path(path.clone()).and_then(|mut file| {
+            let changed = file.format(&config.editor)?;
+            Ok((file, changed))
+        });
+        let mut file = match formatted {
+            Ok((_, false)) => continue,
+            Ok((file, true)) => file,
+            Err(e) => {
+                eprintln!("{}: {e}", path.display());
+                failed = true;
+                continue;
+            }
+        };
+
+        unformatted += 1;
+        if check {
+            println!("Would reformat {}", path.display());
+        } else {
+            file.write(false)?;
+            println!("Reformatted {}", path.display());
+        }
+    }
+
+    if failed {
+        return Ok(ExitCode::from(crate::EXIT_ERROR));
+    }
+    if check && unformatted > 0 {
+        return Ok(ExitCode::from(EXIT_PROBLEMS));
+    }
+    Ok(ExitCode::SUCCESS)
+}
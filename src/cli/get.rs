@@ -0,0 +1,48 @@
+use std::path::Path;
+use std::process::ExitCode;
+
+use super::{EXIT_PROBLEMS, print};
+use crate::app::{File, FileError, Query, Selector, Value};
+use crate::config::{SelectorArgs, ValueFormat};
+use crate::error::Error;
+
+pub fn run(
+    path: &Path,
+    query: &str,
+    selector: &SelectorArgs,
+    format: ValueFormat,
+) -> Result<ExitCode, Error> {
+    let file = File::from_path(path.to_path_buf())?;
+    let query = Query::parse(query).map_err(FileError::from)?;
+    let values = file.get(&query, &Selector::from(selector));
+
+    let mut output = String::new();
+    match format {
+        ValueFormat::Yaml => {
+            let fragments: Vec<String> = values.iter().map(Value::source).collect();
+            // Multi line fragments are split into documents so they can be told apart
+            let separator = if fragments.iter().any(|f| f.contains('\n')) {
+                "\n---\n"
+            } else {
+                "\n"
+            };
+            output.push_str(&fragments.join(separator));
+            if !fragments.is_empty() {
+                output.push('\n');
+            }
+        }
+        ValueFormat::Json => {
+            for value in &values {
+                output.push_str(&serde_json::to_string(&value.to_json())?);
+                output.push('\n');
+            }
+        }
+    }
+
+    print(&output)?;
+
+    if values.is_empty() {
+        return Ok(ExitCode::from(EXIT_PROBLEMS));
+    }
+    Ok(ExitCode::SUCCESS)
+}
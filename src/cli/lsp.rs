@@ -0,0 +1,479 @@
+use log::{info, warn};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value as JsonValue, json};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::rc::Rc;
+
+use super::validate::describe;
+use crate::api_client::{self, ApiClient, GroupSpec, SchemaRef, SchemaType};
+use crate::app::{Context, Diagnostic, File, FileError, Focus, context_at};
+use crate::config::{Config, Severity, ValidationConfig};
+use crate::error::Error;
+
+// Protocol constants, see the LSP specification
+const SYNC_FULL: u8 = 1;
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+const COMPLETION_FIELD: u8 = 5;
+const COMPLETION_ENUM_MEMBER: u8 = 20;
+const SYMBOL_OBJECT: u8 = 19;
+
+/// Methods of the requests answered.
+const REQUESTS: [&str; 3] = [
+    "textDocument/hover",
+    "textDocument/completion",
+    "textDocument/documentSymbol",
+];
+
+/// Position in a document. Characters are counted in UTF-16 code units.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+struct Position {
+    line: u32,
+    character: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct TextDocument {
+    uri: String,
+    text: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ContentChange {
+    text: String,
+}
+
+/// Parameters of the requests and notifications handled, each uses a subset of the fields.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Params {
+    text_document: TextDocument,
+    position: Option<Position>,
+    #[serde(default)]
+    content_changes: Vec<ContentChange>,
+}
+
+/// Open documents and the schemas they are checked against.
+struct Server<'a> {
+    connection: Connection,
+    api_client: ApiClient,
+    config: &'a ValidationConfig,
+    documents: HashMap<String, String>,
+}
+
+pub async fn run(config: &Config) -> Result<ExitCode, Error> {
+    let api_client = api_client::from_config(config).await?;
+
+    let (connection, io_threads) = Connection::stdio();
+    connection.initialize(json!({
+        "textDocumentSync": SYNC_FULL,
+        "hoverProvider": true,
+        "completionProvider": {},
+        "documentSymbolProvider": true,
+    }))?;
+    info!("Language server initialized");
+
+    let mut server = Server {
+        connection,
+        api_client,
+        config: &config.validation,
+        documents: HashMap::new(),
+    };
+    server.serve().await?;
+
+    // The writer thread only stops once the connection is gone
+    drop(server);
+    io_threads.join()?;
+    Ok(ExitCode::SUCCESS)
+}
+
+impl Server<'_> {
+    async fn serve(&mut self) -> Result<(), Error> {
+        while let Ok(message) = self.connection.receiver.recv() {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.request(request).await;
+                    self.send(response)?;
+                }
+                Message::Notification(notification) => self.notification(notification).await?,
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn send(&self, message: impl Into<Message>) -> Result<(), Error> {
+        self.connection
+            .sender
+            .send(message.into())
+            .map_err(std::io::Error::other)?;
+        Ok(())
+    }
+
+    async fn request(&mut self, request: Request) -> Response {
+        // The method is checked first, params are only known for the supported ones
+        let method = request.method.as_str();
+        if !REQUESTS.contains(&method) {
+            let code = ErrorCode::MethodNotFound as i32;
+            let message = format!("Unsupported request `{method}`");
+            return Response::new_err(request.id, code, message);
+        }
+
+        let params: Params = match serde_json::from_value(request.params) {
+            Ok(params) => params,
+            Err(e) => {
+                let code = ErrorCode::InvalidParams as i32;
+                return Response::new_err(request.id, code, e.to_string());
+            }
+        };
+
+        let result = match request.method.as_str() {
+            "textDocument/hover" => self.hover(&params).await,
+            "textDocument/completion" => self.completion(&params).await,
+            "textDocument/documentSymbol" => self.document_symbols(&params),
+            method => unreachable!("Unsupported request `{method}` was answered above"),
+        };
+        Response::new_ok(request.id, result)
+    }
+
+    async fn notification(&mut self, notification: Notification) -> Result<(), Error> {
+        let method = notification.method.as_str();
+        if !method.starts_with("textDocument/") {
+            return Ok(());
+        }
+        // A malformed notification is dropped, there is no response to carry the error
+        let params: Params = match serde_json::from_value(notification.params) {
+            Ok(params) => params,
+            Err(e) => {
+                warn!("Invalid params for `{method}`: {e}");
+                return Ok(());
+            }
+        };
+        let uri = params.text_document.uri;
+
+        match method {
+            "textDocument/didOpen" => {
+                let text = params.text_document.text.unwrap_or_default();
+                self.documents.insert(uri.clone(), text);
+                self.publish_diagnostics(&uri).await?;
+            }
+            // Documents are synced in full, the last change holds the whole text
+            "textDocument/didChange" => {
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents.insert(uri.clone(), change.text);
+                    self.publish_diagnostics(&uri).await?;
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                self.send_diagnostics(&uri, &[])?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn publish_diagnostics(&mut self, uri: &str) -> Result<(), Error> {
+        let Some(text) = self.documents.get(uri) else {
+            return Ok(());
+        };
+
+        let diagnostics = match File::from_text(path(uri), text) {
+            Ok(file) => match file.validate(&mut self.api_client, self.config).await {
+                Ok(diagnostics) => diagnostics,
+                Err(e) => {
+                    warn!("Could not validate {uri}: {e}");
+                    return Ok(());
+                }
+            },
+            Err(FileError::YamlParseError(e)) => vec![Diagnostic::syntax(&e)],
+            Err(e) => {
+                warn!("Could not read {uri}: {e}");
+                return Ok(());
+            }
+        };
+
+        let text = &self.documents[uri];
+        let diagnostics: Vec<JsonValue> = diagnostics
+            .iter()
+            .map(|d| {
+                let severity = match d.severity {
+                    Severity::Warning => SEVERITY_WARNING,
+                    Severity::Error | Severity::Off => SEVERITY_ERROR,
+                };
+                json!({
+                    "range": range(text, d.range.start().into(), d.range.end().into()),
+                    "severity": severity,
+                    "code": d.rule.id(),
+                    "source": "m7s",
+                    "message": describe(d),
+                })
+            })
+            .collect();
+        self.send_diagnostics(uri, &diagnostics)
+    }
+
+    fn send_diagnostics(&self, uri: &str, diagnostics: &[JsonValue]) -> Result<(), Error> {
+        let params = json!({ "uri": uri, "diagnostics": diagnostics });
+        self.send(Notification::new(
+            "textDocument/publishDiagnostics".to_string(),
+            params,
+        ))
+    }
+
+    // The text and context at the position of a request
+    fn context(&self, params: &Params) -> Option<(String, Context)> {
+        let text = self.documents.get(&params.text_document.uri)?;
+        let context = context_at(text, offset(text, params.position?))?;
+        Some((text.clone(), context))
+    }
+
+    async fn spec(&mut self, context: &Context) -> Option<Rc<GroupSpec>> {
+        let api_version = context.api_version.as_deref()?;
+        match self.api_client.get_group_spec(&api_version.into()).await {
+            Ok(spec) => Some(spec),
+            Err(e) => {
+                warn!("Could not load spec for {api_version}: {e}");
+                None
+            }
+        }
+    }
+
+    // Type, enum values and description of the field under the cursor
+    async fn hover(&mut self, params: &Params) -> JsonValue {
+        let Some((text, context)) = self.context(params) else {
+            return JsonValue::Null;
+        };
+        let (Focus::Key(key) | Focus::Value { key, .. }) = &context.focus;
+        if key.is_empty() {
+            return JsonValue::Null;
+        }
+        let Some(spec) = self.spec(&context).await else {
+            return JsonValue::Null;
+        };
+        let keys = context.parents.iter().chain([key]);
+        let Some(schema) = context
+            .kind
+            .as_deref()
+            .and_then(|kind| field(&spec, kind, keys))
+        else {
+            return JsonValue::Null;
+        };
+
+        let mut contents = format!("**{key}** `{}`", schema.type_name());
+        if let SchemaType::String(allowed) = schema.schema_type()
+            && !allowed.is_empty()
+        {
+            let allowed: Vec<String> = allowed.iter().map(|a| format!("`{a}`")).collect();
+            let _ = write!(contents, "\n\nEnum: {}", allowed.join(", "));
+        }
+        if let Some(description) = schema.description() {
+            let _ = write!(contents, "\n\n{description}");
+        }
+
+        let start = match context.focus {
+            Focus::Key(_) => context.start,
+            Focus::Value { .. } => text[..context.start].rfind(key.as_str()).unwrap_or(0),
+        };
+        json!({
+            "contents": { "kind": "markdown", "value": contents },
+            "range": range(&text, start, start + key.len()),
+        })
+    }
+
+    // Missing keys of the map at the cursor, or the values allowed after `key:`
+    async fn completion(&mut self, params: &Params) -> JsonValue {
+        let Some((text, context)) = self.context(params) else {
+            return JsonValue::Null;
+        };
+        let Some(spec) = self.spec(&context).await else {
+            return JsonValue::Null;
+        };
+        let Some(kind) = context.kind.as_deref() else {
+            return JsonValue::Null;
+        };
+
+        let items: Vec<JsonValue> = match &context.focus {
+            Focus::Key(_) => {
+                let Some(schema) = field(&spec, kind, context.parents.iter()) else {
+                    return JsonValue::Null;
+                };
+                // Keys that already have their `:` are replaced by name only
+                let line = text[context.start..].split('\n').next().unwrap_or_default();
+                let has_colon = line.contains(':');
+                let required = match schema.schema_type() {
+                    SchemaType::Array => schema.items().map_or(&[][..], |i| i.required()),
+                    _ => schema.required(),
+                };
+
+                properties(schema)
+                    .into_iter()
+                    .filter(|(name, _)| !context.siblings.iter().any(|s| s == name))
+                    .map(|(name, field)| {
+                        let is_required = required.iter().any(|r| r == name);
+                        key_item(name, field, is_required, has_colon)
+                    })
+                    .collect()
+            }
+            Focus::Value { key, .. } => {
+                let keys = context.parents.iter().chain([key]);
+                let Some(schema) = field(&spec, kind, keys) else {
+                    return JsonValue::Null;
+                };
+                let values = match schema.schema_type() {
+                    SchemaType::String(allowed) => allowed,
+                    SchemaType::Boolean => vec!["true".to_string(), "false".to_string()],
+                    _ => vec![],
+                };
+
+                values
+                    .into_iter()
+                    .map(|value| json!({ "label": value, "kind": COMPLETION_ENUM_MEMBER }))
+                    .collect()
+            }
+        };
+        json!(items)
+    }
+
+    // One symbol per resource, e.g. `Deployment/web`
+    fn document_symbols(&self, params: &Params) -> JsonValue {
+        let uri = &params.text_document.uri;
+        let Some(file) = self
+            .documents
+            .get(uri)
+            .and_then(|text| File::from_text(path(uri), text).ok())
+        else {
+            return JsonValue::Null;
+        };
+        let text = &self.documents[uri];
+
+        let symbols: Vec<JsonValue> = file
+            .resources()
+            .into_iter()
+            .enumerate()
+            .map(|(index, (document, details))| {
+                let (name, detail) = match &details {
+                    Some(details) => {
+                        let name = details.display_name();
+                        let mut detail = details.api_version().api_version();
+                        if let Some(namespace) = details.namespace() {
+                            let _ = write!(detail, " in {namespace}");
+                        }
+                        (name, Some(detail))
+                    }
+                    None => (format!("Document {}", index + 1), None),
+                };
+
+                // The name is picked on the first line after any `---`
+                let (start, end) = (document.start().into(), document.end().into());
+                let first_line = text[start..end]
+                    .split_inclusive('\n')
+                    .take_while(|line| line.starts_with("---") || line.trim().is_empty())
+                    .map(str::len)
+                    .sum::<usize>();
+                let selection_start = (start + first_line).min(end);
+                let selection_end = text[selection_start..end]
+                    .find('\n')
+                    .map_or(end, |i| selection_start + i);
+
+                let mut symbol = json!({
+                    "name": name,
+                    "kind": SYMBOL_OBJECT,
+                    "range": range(text, start, end),
+                    "selectionRange": range(text, selection_start, selection_end),
+                });
+                if let Some(detail) = detail {
+                    symbol["detail"] = json!(detail);
+                }
+                symbol
+            })
+            .collect();
+        json!(symbols)
+    }
+}
+
+// Files are only read from the editor, the path is used in messages
+fn path(uri: &str) -> PathBuf {
+    PathBuf::from(uri.strip_prefix("file://").unwrap_or(uri))
+}
+
+// Schema of the field at `keys` in `kind`, looking through arrays to their items
+fn field<'a, 'k>(
+    spec: &'a GroupSpec,
+    kind: &str,
+    keys: impl Iterator<Item = &'k String>,
+) -> Option<SchemaRef<'a>> {
+    let mut schema = spec.get_kind(kind).ok()?;
+    for key in keys {
+        schema = schema.child(key)?;
+    }
+    Some(schema)
+}
+
+// Fields of an object, or of the items of an array
+fn properties(schema: SchemaRef<'_>) -> Vec<(&str, SchemaRef<'_>)> {
+    match schema.schema_type() {
+        SchemaType::Array => schema.items().map(properties).unwrap_or_default(),
+        _ => schema.properties(),
+    }
+}
+
+fn key_item(name: &str, field: SchemaRef<'_>, required: bool, has_colon: bool) -> JsonValue {
+    let insert_text = if has_colon {
+        name.to_string()
+    } else {
+        format!("{name}: ")
+    };
+    let mut item = json!({
+        "label": name,
+        "kind": COMPLETION_FIELD,
+        "detail": field.type_name(),
+        // Required fields are listed first
+        "sortText": format!("{}{name}", u8::from(!required)),
+        "insertText": insert_text,
+    });
+    if let Some(description) = field.description() {
+        item["documentation"] = json!({ "kind": "markdown", "value": description });
+    }
+    item
+}
+
+fn position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line = before.matches('\n').count();
+    let character = before[line_start..].encode_utf16().count();
+    Position {
+        line: u32::try_from(line).unwrap_or(u32::MAX),
+        character: u32::try_from(character).unwrap_or(u32::MAX),
+    }
+}
+
+fn range(text: &str, start: usize, end: usize) -> JsonValue {
+    json!({ "start": position(text, start), "end": position(text, end) })
+}
+
+fn offset(text: &str, position: Position) -> usize {
+    let line_start = text
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(str::len)
+        .sum::<usize>();
+    let line = text[line_start..].split('\n').next().unwrap_or_default();
+
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= position.character as usize {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    line_start + line.len()
+}
@@ -0,0 +1,119 @@
+use std::io::{ErrorKind, Write as _};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use crate::app::Selector;
+use crate::config::{Command, Config, SelectorArgs};
+use crate::error::Error;
+
+mod explain;
+mod fmt;
+mod get;
+mod lsp;
+mod new;
+mod set;
+mod validate;
+
+/// Exit code for commands that ran, but found problems, e.g. invalid manifests.
+const EXIT_PROBLEMS: u8 = 1;
+
+impl From<&SelectorArgs> for Selector {
+    fn from(args: &SelectorArgs) -> Self {
+        Selector {
+            kind: args.kind.clone(),
+            name: args.name.clone(),
+            namespace: args.namespace.clone(),
+        }
+    }
+}
+
+/// Files given on the command line, with directories searched recursively for `.yaml` and `.yml`
+/// files. Hidden entries are skipped.
+fn collect_files(paths: &[PathBuf]) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for path in paths {
+        if path.is_dir() {
+            walk_dir(path, &mut files)?;
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+fn walk_dir(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    for path in entries {
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if hidden {
+            continue;
+        }
+
+        if path.is_dir() {
+            walk_dir(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext == "yaml" || ext == "yml")
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Write `output` to stdout. Output piped into e.g. `head` can close early, which is not an
+/// error.
+fn print(output: &str) -> std::io::Result<()> {
+    match std::io::stdout().lock().write_all(output.as_bytes()) {
+        Err(e) if e.kind() != ErrorKind::BrokenPipe => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Run a subcommand without starting the editor.
+pub async fn run(command: &Command, config: &Config) -> Result<ExitCode, Error> {
+    match command {
+        Command::Validate { paths, format } => validate::run(config, paths, *format).await,
+        Command::Explain {
+            path,
+            recursive,
+            api_version,
+        } => explain::run(config, path, *recursive, api_version.as_deref()).await,
+        Command::New {
+            kind,
+            name,
+            output,
+            api_version,
+            optional,
+        } => {
+            let options = new::Options {
+                kind,
+                name,
+                output: output.as_deref(),
+                api_version: api_version.as_deref(),
+                optional: *optional,
+            };
+            new::run(config, &options).await
+        }
+        Command::Fmt { paths, check } => fmt::run(config, paths, *check),
+        Command::Get {
+            file,
+            path,
+            selector,
+            output,
+        } => get::run(file, path, selector, *output),
+        Command::Set {
+            file,
+            assignments,
+            selector,
+            dry_run,
+        } => set::run(config, file, assignments, selector, *dry_run).await,
+        Command::Lsp { stdio: _ } => lsp::run(config).await,
+    }
+}
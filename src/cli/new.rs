@@ -0,0 +1,56 @@
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use crate::api_client;
+use crate::app::{File, FileError, skeleton};
+use crate::config::Config;
+use crate::error::Error;
+
+pub struct Options<'a> {
+    pub kind: &'a str,
+    pub name: &'a str,
+    pub output: Option<&'a Path>,
+    pub api_version: Option<&'a str>,
+    pub optional: bool,
+}
+
+pub async fn run(config: &Config, options: &Options<'_>) -> Result<ExitCode, Error> {
+    let mut api_client = api_client::from_config(config).await?;
+    // Groups are searched with their preferred version first
+    let spec = match options.api_version {
+        Some(api_version) => api_client.get_group_spec(&api_version.into()).await?,
+        None => api_client.find_kind(options.kind).await?,
+    };
+
+    let kind = spec.kind_name(options.kind)?;
+    let text = skeleton(
+        spec.get_kind(options.kind)?,
+        &spec.group().api_version(),
+        kind,
+        options.name,
+        &config.editor,
+        options.optional,
+    );
+
+    // Unsaved files open in the editor, `:w` writes them next to where m7s was started
+    let path = match options.output {
+        Some(path) if path == Path::new("-") => {
+            print!("{text}");
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(path) => path.to_path_buf(),
+        None => PathBuf::from(format!("{}-{}.yaml", kind.to_lowercase(), options.name)),
+    };
+    if path.exists() {
+        Err(FileError::PathExists(path.clone()))?;
+    }
+
+    let mut file = File::from_text(path, &text)?;
+    if options.output.is_some() {
+        file.write(false)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    crate::edit(config, vec![file]).await?;
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,44 @@
+use log::debug;
+use std::path::Path;
+use std::process::ExitCode;
+
+use crate::api_client;
+use crate::app::{File, FileError, Query, Selector, split_assignment};
+use crate::config::{Config, SelectorArgs};
+use crate::error::Error;
+
+pub async fn run(
+    config: &Config,
+    path: &Path,
+    assignments: &[String],
+    selector: &SelectorArgs,
+    dry_run: bool,
+) -> Result<ExitCode, Error> {
+    let selector = Selector::from(selector);
+    let mut file = File::from_path(path.to_path_buf())?;
+    // Schemas type the values when they can be loaded, the edit itself needs no cluster
+    let mut api_client = match config.kube {
+        Some(_) => api_client::from_config(config)
+            .await
+            .inspect_err(|e| debug!("Setting values without schemas: {e}"))
+            .ok(),
+        None => None,
+    };
+
+    // Each assignment sees the result of the previous ones
+    for assignment in assignments {
+        let (query, value) = split_assignment(assignment).map_err(FileError::from)?;
+        let query = Query::parse(query).map_err(FileError::from)?;
+        let api_client = api_client.as_mut();
+        file.set(&query, value, &selector, config.editor.indent, api_client)
+            .await?;
+    }
+
+    if dry_run {
+        print!("{}", file.text());
+    } else {
+        file.write(false)?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
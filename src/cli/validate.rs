@@ -0,0 +1,324 @@
+use serde_json::json;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use super::{EXIT_PROBLEMS, collect_files, print};
+use crate::api_client::{self, ApiClient};
+use crate::app::{Diagnostic, File, FileError, Rule};
+use crate::config::{Config, OutputFormat, Severity, ValidationConfig};
+use crate::error::Error;
+
+/// Diagnostics of one file, with positions resolved to 1-based lines and columns.
+struct Report {
+    path: PathBuf,
+    documents: usize,
+    diagnostics: Vec<(Diagnostic, Position, Position)>,
+}
+
+#[derive(Clone, Copy)]
+struct Position {
+    line: usize,
+    column: usize,
+}
+
+impl Report {
+    fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|(d, _, _)| d.severity == severity)
+            .count()
+    }
+}
+
+pub async fn run(
+    config: &Config,
+    paths: &[PathBuf],
+    format: OutputFormat,
+) -> Result<ExitCode, Error> {
+    let files = collect_files(paths)?;
+    let mut api_client = api_client::from_config(config).await?;
+
+    let mut reports = Vec::with_capacity(files.len());
+    for path in files {
+        reports.push(validate_file(path, &mut api_client, &config.validation).await?);
+    }
+
+    let output = match format {
+        OutputFormat::Human => human(&reports),
+        OutputFormat::Json => serde_json::to_string_pretty(&to_json(&reports))?,
+        OutputFormat::Sarif => serde_json::to_string_pretty(&to_sarif(&reports))?,
+        OutputFormat::Junit => junit(&reports),
+    };
+    print(&format!("{output}\n"))?;
+
+    if reports.iter().any(|r| r.count(Severity::Error) > 0) {
+        Ok(ExitCode::from(EXIT_PROBLEMS))
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+// Files are taken as given, directories are searched for YAML files. Hidden entries are skipped.
+
+async fn validate_file(
+    path: PathBuf,
+    api_client: &mut ApiClient,
+    config: &ValidationConfig,
+) -> Result<Report, Error> {
+    let (text, documents, diagnostics) = match File::from_path(path.clone()) {
+        Ok(file) => (
+            file.text(),
+            file.document_count(),
+            file.validate(api_client, config).await?,
+        ),
+        Err(FileError::YamlParseError(e)) => (
+            std::fs::read_to_string(&path)?,
+            0,
+            vec![Diagnostic::syntax(&e)],
+        ),
+        Err(e) => Err(e)?,
+    };
+
+    let mut diagnostics = diagnostics;
+    diagnostics.sort_by_key(|d| d.range.start());
+    let diagnostics = diagnostics
+        .into_iter()
+        .map(|d| {
+            let start = position(&text, d.range.start().into());
+            let end = position(&text, d.range.end().into());
+            (d, start, end)
+        })
+        .collect();
+
+    Ok(Report {
+        path,
+        documents,
+        diagnostics,
+    })
+}
+
+fn position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position {
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+    }
+}
+
+// The message, prefixed with the location in the document when there is one
+pub(super) fn describe(diagnostic: &Diagnostic) -> String {
+    if diagnostic.path.is_empty() {
+        diagnostic.message.clone()
+    } else {
+        format!("{}: {}", diagnostic.path, diagnostic.message)
+    }
+}
+
+// `path:line:column: severity[rule] path: message`, followed by a summary
+fn human(reports: &[Report]) -> String {
+    let mut output = String::new();
+    for report in reports {
+        for (d, start, _) in &report.diagnostics {
+            let _ = writeln!(
+                output,
+                "{}:{}:{}: {}[{}] {}",
+                report.path.display(),
+                start.line,
+                start.column,
+                d.severity.name(),
+                d.rule.id(),
+                describe(d),
+            );
+        }
+    }
+
+    let errors: usize = reports.iter().map(|r| r.count(Severity::Error)).sum();
+    let warnings: usize = reports.iter().map(|r| r.count(Severity::Warning)).sum();
+    let documents: usize = reports.iter().map(|r| r.documents).sum();
+    let _ = write!(
+        output,
+        "{errors} error(s), {warnings} warning(s) in {} file(s), {documents} document(s)",
+        reports.len()
+    );
+    output
+}
+
+fn to_json(reports: &[Report]) -> serde_json::Value {
+    let position = |p: &Position| json!({ "line": p.line, "column": p.column });
+
+    json!({
+        "files": reports.iter().map(|report| json!({
+            "path": report.path,
+            "documents": report.documents,
+            "diagnostics": report.diagnostics.iter().map(|(d, start, end)| json!({
+                "severity": d.severity.name(),
+                "rule": d.rule.id(),
+                "document": d.document,
+                "path": d.path,
+                "message": d.message,
+                "start": position(start),
+                "end": position(end),
+            })).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+        "errors": reports.iter().map(|r| r.count(Severity::Error)).sum::<usize>(),
+        "warnings": reports.iter().map(|r| r.count(Severity::Warning)).sum::<usize>(),
+    })
+}
+
+// SARIF 2.1.0, as understood by GitHub code scanning and most CI annotators
+fn to_sarif(reports: &[Report]) -> serde_json::Value {
+    let rules = Rule::ALL
+        .iter()
+        .map(|rule| {
+            json!({
+                "id": rule.id(),
+                "shortDescription": { "text": rule.description() },
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let results = reports
+        .iter()
+        .flat_map(|report| {
+            report.diagnostics.iter().map(|(d, start, end)| {
+                json!({
+                    "ruleId": d.rule.id(),
+                    "ruleIndex": Rule::ALL.iter().position(|r| *r == d.rule),
+                    "level": d.severity.name(),
+                    "message": { "text": describe(d) },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": uri(&report.path) },
+                            "region": {
+                                "startLine": start.line,
+                                "startColumn": start.column,
+                                "endLine": end.line,
+                                "endColumn": end.column,
+                            },
+                        },
+                    }],
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "m7s",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                },
+            },
+            "results": results,
+        }],
+    })
+}
+
+fn uri(path: &Path) -> String {
+    path.to_string_lossy()
+        .trim_start_matches("./")
+        .replace('\\', "/")
+}
+
+// One test suite per file and one test case per document. Errors fail the test case, warnings
+// are listed in its output.
+fn junit(reports: &[Report]) -> String {
+    let documents: usize = reports.iter().map(test_case_count).sum();
+    let failures: usize = reports.iter().map(failed_documents).sum();
+
+    let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        output,
+        "<testsuites name=\"m7s validate\" tests=\"{documents}\" failures=\"{failures}\">"
+    );
+
+    for report in reports {
+        let name = xml_escape(&report.path.display().to_string());
+        let _ = writeln!(
+            output,
+            "  <testsuite name=\"{name}\" tests=\"{}\" failures=\"{}\">",
+            test_case_count(report),
+            failed_documents(report),
+        );
+
+        for document in 0..test_case_count(report) {
+            let _ = writeln!(
+                output,
+                "    <testcase classname=\"{name}\" name=\"document {}\">",
+                document + 1
+            );
+            let diagnostics = report
+                .diagnostics
+                .iter()
+                .filter(|(d, _, _)| d.document == document);
+
+            let mut warnings = String::new();
+            for (d, start, _) in diagnostics {
+                let text = xml_escape(&format!(
+                    "{}:{}:{}: {}",
+                    report.path.display(),
+                    start.line,
+                    start.column,
+                    describe(d),
+                ));
+                if d.severity == Severity::Error {
+                    let _ = writeln!(
+                        output,
+                        "      <failure type=\"{}\" message=\"{}\">{text}</failure>",
+                        d.rule.id(),
+                        xml_escape(&d.message),
+                    );
+                } else {
+                    let _ = writeln!(warnings, "warning[{}] {text}", d.rule.id());
+                }
+            }
+            if !warnings.is_empty() {
+                let _ = writeln!(output, "      <system-out>{warnings}</system-out>");
+            }
+
+            output.push_str("    </testcase>\n");
+        }
+        output.push_str("  </testsuite>\n");
+    }
+
+    output.push_str("</testsuites>");
+    output
+}
+
+// Files that failed to parse have no documents, but still need a test case to fail
+fn test_case_count(report: &Report) -> usize {
+    report.documents.max(
+        report
+            .diagnostics
+            .iter()
+            .map(|(d, _, _)| d.document + 1)
+            .max()
+            .unwrap_or(0),
+    )
+}
+
+fn failed_documents(report: &Report) -> usize {
+    (0..test_case_count(report))
+        .filter(|document| {
+            report
+                .diagnostics
+                .iter()
+                .any(|(d, _, _)| d.document == *document && d.severity == Severity::Error)
+        })
+        .count()
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -0,0 +1,318 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+use toml::Table;
+
+use super::ConfigError;
+
+const PROJECT_CONFIG_NAME: &str = ".m7s";
+
+/// Contents of an m7s config file.
+///
+/// Every field has a default so that partial files (and no file at all) are valid. Files are
+/// layered as tables before being deserialized, see [`load`].
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    /// Kubernetes context to use when `--context` is not given
+    pub context: Option<String>,
+    pub schema: SchemaConfig,
+    pub theme: String,
+    pub keymap: KeymapConfig,
+    pub validation: ValidationConfig,
+    pub editor: EditorConfig,
+    pub airline: AirlineConfig,
+}
+
+impl Default for ConfigFile {
+    fn default() -> Self {
+        Self {
+            context: None,
+            schema: SchemaConfig::default(),
+            theme: "dark".to_string(),
+            keymap: KeymapConfig::default(),
+            validation: ValidationConfig::default(),
+            editor: EditorConfig::default(),
+            airline: AirlineConfig::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaSource {
+    /// Fetch schemas from the cluster, using the cache when it is fresh
+    #[default]
+    Cluster,
+    /// Only ever read schemas from the cache
+    Offline,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SchemaConfig {
+    pub source: SchemaSource,
+    /// Persist schema responses to `cache_dir`
+    pub cache: bool,
+    /// Defaults to `$XDG_CACHE_HOME/m7s`
+    pub cache_dir: Option<PathBuf>,
+    /// Seconds before a cached response is fetched again from the cluster
+    pub cache_ttl: u64,
+}
+
+impl Default for SchemaConfig {
+    fn default() -> Self {
+        Self {
+            source: SchemaSource::Cluster,
+            cache: true,
+            cache_dir: None,
+            cache_ttl: 60 * 60 * 24,
+        }
+    }
+}
+
+/// Key bindings per mode, on top of a named preset. Keys are key sequences (e.g. `gg`,
+/// `<C-s>`) and values are action names.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeymapConfig {
+    pub preset: String,
+    pub normal: HashMap<String, String>,
+    pub input: HashMap<String, String>,
+    pub command: HashMap<String, String>,
+    pub visual: HashMap<String, String>,
+}
+
+impl Default for KeymapConfig {
+    fn default() -> Self {
+        Self {
+            preset: "vim".to_string(),
+            normal: HashMap::new(),
+            input: HashMap::new(),
+            command: HashMap::new(),
+            visual: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Error,
+    Warning,
+    Off,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Off => "off",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ValidationConfig {
+    /// Fields that are not part of the schema
+    pub unknown_fields: Severity,
+    /// Required fields that are missing
+    pub missing_required: Severity,
+    /// Values that do not match the schema type
+    pub type_mismatch: Severity,
+    /// Values that are not one of the schema's enum values
+    pub invalid_enum: Severity,
+    /// Documents whose `apiVersion`/`kind` has no schema
+    pub unknown_kind: Severity,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            unknown_fields: Severity::Error,
+            missing_required: Severity::Error,
+            type_mismatch: Severity::Error,
+            invalid_enum: Severity::Error,
+            unknown_kind: Severity::Warning,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct EditorConfig {
+    /// Number of spaces per indentation level
+    pub indent: usize,
+    /// Indent sequences under their parent key (`false` keeps `- ` flush with the key)
+    pub indent_sequences: bool,
+    /// Lines moved by page up/down
+    pub scroll_step: usize,
+    /// Capture the mouse for clicks, scrolling and dragging. Turn off to select text with the
+    /// terminal instead.
+    pub mouse: bool,
+}
+
+impl Default for EditorConfig {
+    fn default() -> Self {
+        Self {
+            indent: 2,
+            indent_sequences: true,
+            scroll_step: 10,
+            mouse: true,
+        }
+    }
+}
+
+/// Segments of the status line, like vim-airline sections. `left` is aligned to the left edge and
+/// `right` to the right edge, both in the order given.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct AirlineConfig {
+    pub left: Vec<AirlineSegment>,
+    pub right: Vec<AirlineSegment>,
+}
+
+impl Default for AirlineConfig {
+    fn default() -> Self {
+        Self {
+            left: vec![
+                AirlineSegment::Mode,
+                AirlineSegment::File,
+                AirlineSegment::Modified,
+                AirlineSegment::Resource,
+                AirlineSegment::Path,
+            ],
+            right: vec![
+                AirlineSegment::Search,
+                AirlineSegment::Diagnostics,
+                AirlineSegment::Context,
+                AirlineSegment::Namespace,
+                AirlineSegment::Schemas,
+                AirlineSegment::Position,
+                AirlineSegment::Percent,
+            ],
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AirlineSegment {
+    /// Current mode, e.g. `NORMAL`
+    Mode,
+    /// Path of the open file
+    File,
+    /// `[+]` when the file has unsaved changes
+    Modified,
+    /// `Kind/name` of the document under the cursor
+    Resource,
+    /// Path of the cursor within its document
+    Path,
+    /// Last search and which of its matches the cursor is on
+    Search,
+    /// Number of errors and warnings
+    Diagnostics,
+    /// Kubernetes context
+    Context,
+    /// Namespace of the Kubernetes context
+    Namespace,
+    /// Where schemas come from: online, cached or offline
+    Schemas,
+    /// Line and column of the cursor
+    Position,
+    /// How far through the file the cursor is
+    Percent,
+}
+
+/// `$XDG_CONFIG_HOME/m7s/config`, falling back to `~/.config/m7s/config`.
+pub fn default_config_path() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config").map(|dir| dir.join("m7s").join("config"))
+}
+
+/// `$XDG_CACHE_HOME/m7s`, falling back to `~/.cache/m7s`.
+pub fn default_cache_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CACHE_HOME", ".cache").map(|dir| dir.join("m7s"))
+}
+
+fn xdg_dir(var: &str, home_fallback: &str) -> Option<PathBuf> {
+    match env::var(var) {
+        Ok(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => env::home_dir().map(|home| home.join(home_fallback)),
+    }
+}
+
+/// Expands a leading `~` to the home directory.
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), env::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// The `.m7s` file in the root of the repository containing `dir`. Outside of a repository only
+/// `dir` itself is checked.
+pub fn project_config_path(dir: &Path) -> Option<PathBuf> {
+    let root = dir
+        .ancestors()
+        .find(|d| d.join(".git").exists())
+        .unwrap_or(dir);
+
+    Some(root.join(PROJECT_CONFIG_NAME)).filter(|p| p.is_file())
+}
+
+/// Loads the global config file, overlaid with the project config file when there is one.
+///
+/// `explicit` replaces the global config file and must exist.
+pub fn load(explicit: Option<&Path>, cwd: &Path) -> Result<ConfigFile, ConfigError> {
+    let global = match explicit {
+        Some(path) if !path.is_file() => Err(ConfigError::MissingConfigFile(path.to_path_buf()))?,
+        Some(path) => Some(path.to_path_buf()),
+        None => default_config_path().filter(|p| p.is_file()),
+    };
+
+    let mut table = Table::new();
+    for path in global.into_iter().chain(project_config_path(cwd)) {
+        log::debug!("Loading config file {}", path.display());
+        merge_tables(&mut table, read_table(&path)?);
+    }
+
+    table
+        .try_into()
+        .map_err(|e| ConfigError::ConfigFileParseError(PathBuf::from("<merged>"), e))
+}
+
+fn read_table(path: &Path) -> Result<Table, ConfigError> {
+    let raw = std::fs::read_to_string(path)
+        .map_err(|e| ConfigError::ConfigFileReadError(path.to_path_buf(), e))?;
+
+    let table: Table = toml::from_str(&raw)
+        .map_err(|e| ConfigError::ConfigFileParseError(path.to_path_buf(), e))?;
+
+    // Validate each layer on its own so that errors point to the offending file
+    let _: ConfigFile = table
+        .clone()
+        .try_into()
+        .map_err(|e| ConfigError::ConfigFileParseError(path.to_path_buf(), e))?;
+
+    Ok(table)
+}
+
+// Deep merge `overlay` into `base`. Tables are merged key by key, everything else is replaced.
+fn merge_tables(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => {
+                merge_tables(base, overlay);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
@@ -1,7 +1,15 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use kube_client::config::Kubeconfig;
+use log::debug;
 use std::{env, path::PathBuf};
 
+mod file;
+
+pub use file::{
+    AirlineConfig, AirlineSegment, EditorConfig, KeymapConfig, SchemaConfig, SchemaSource,
+    Severity, ValidationConfig,
+};
+
 fn get_default_kube_config_path() -> PathBuf {
     if let Ok(kube_config) = env::var("KUBECONFIG") {
         PathBuf::from(kube_config)
@@ -14,30 +22,212 @@ fn get_default_kube_config_path() -> PathBuf {
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
-struct CliConfig {
+pub struct CliConfig {
     /// Path to m7s config file
-    #[arg(short, long, value_name = "FILE")]
+    #[arg(short, long, value_name = "FILE", global = true)]
     config: Option<PathBuf>,
 
     /// Kubernetes context to use
-    #[arg(long, value_name = "context")]
+    #[arg(long, value_name = "context", global = true)]
     context: Option<String>,
 
+    /// Only read schemas from the local cache
+    #[arg(long, global = true)]
+    offline: bool,
+
     /// The path to kubeconfig
-    #[arg(long, value_name = "PATH", default_value = get_default_kube_config_path().into_os_string())]
+    #[arg(long, value_name = "PATH", global = true, default_value = get_default_kube_config_path().into_os_string())]
     kube_config: PathBuf,
 
-    /// File to edit
+    /// Files to edit, each in its own buffer
     #[arg(value_name = "FILE")]
-    pub file: Option<PathBuf>,
+    pub files: Vec<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Subcommands that run without the editor.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Validate manifests against the cluster's schemas
+    ///
+    /// Exits with 0 when all documents are valid, 1 when any has errors and 2 when validation
+    /// could not run.
+    Validate {
+        /// Files or directories to validate. Directories are searched recursively for `.yaml` and
+        /// `.yml` files.
+        #[arg(value_name = "PATH", required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Human)]
+        format: OutputFormat,
+    },
+    /// Describe the fields of a kind, e.g. `deployment.spec.strategy`
+    Explain {
+        /// Kind, optionally followed by the path of a field
+        #[arg(value_name = "KIND[.PATH]")]
+        path: String,
+
+        /// Show the fields of all nested objects
+        #[arg(short, long)]
+        recursive: bool,
+
+        /// Group and version serving the kind, e.g. `apps/v1`. All groups are searched when not
+        /// given.
+        #[arg(long, value_name = "GROUP/VERSION")]
+        api_version: Option<String>,
+    },
+    /// Generate a manifest with every required field set to a placeholder
+    ///
+    /// The manifest opens in the editor unless `--output` is given.
+    New {
+        /// Kind of the manifest, e.g. `Deployment`
+        #[arg(value_name = "KIND")]
+        kind: String,
+
+        /// Value of `metadata.name`
+        #[arg(long)]
+        name: String,
+
+        /// File to write the manifest to, `-` for stdout. It must not exist yet.
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Group and version serving the kind, e.g. `apps/v1`. The preferred version of the
+        /// first group serving the kind is used when not given.
+        #[arg(long, value_name = "GROUP/VERSION")]
+        api_version: Option<String>,
+
+        /// Add the optional fields as comments, each below its description
+        #[arg(long)]
+        optional: bool,
+    },
+    /// Normalise indentation and whitespace, keeping comments and scalar styles
+    ///
+    /// Indentation follows the `[editor]` config. With `--check`, files are not written and the
+    /// exit code is 1 when any would change.
+    Fmt {
+        /// Files or directories to format. Directories are searched recursively for `.yaml` and
+        /// `.yml` files.
+        #[arg(value_name = "PATH", required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Only report the files that would change
+        #[arg(long)]
+        check: bool,
+    },
+    /// Print the values at a path in every matching document
+    ///
+    /// Exits with 1 when no document has the path.
+    Get {
+        /// File to read
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Path of the values, e.g. `spec.template.spec.containers[*].image`
+        #[arg(value_name = "PATH")]
+        path: String,
+
+        #[command(flatten)]
+        selector: SelectorArgs,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = ValueFormat::Yaml)]
+        output: ValueFormat,
+    },
+    /// Set values in a file, keeping its comments and formatting
+    ///
+    /// Missing keys are added. Values keep the quoting of the ones they replace, unless the schema
+    /// of the document asks for another type. Schemas are only used when a kube context is set.
+    Set {
+        /// File to edit
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Values to set, e.g. `spec.template.spec.containers[name=web].image=nginx:1.27`
+        #[arg(value_name = "PATH=VALUE", required = true)]
+        assignments: Vec<String>,
+
+        #[command(flatten)]
+        selector: SelectorArgs,
+
+        /// Print the result instead of writing the file
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Run a language server with hover, completion, diagnostics and document symbols
+    Lsp {
+        /// Talk over stdin and stdout. This is the only transport, the flag is accepted as
+        /// editors pass it.
+        #[arg(long)]
+        stdio: bool,
+    },
+}
+
+impl Command {
+    // Commands that only edit text, they get by without a cluster
+    fn is_local(&self) -> bool {
+        matches!(
+            self,
+            Command::Fmt { .. } | Command::Get { .. } | Command::Set { .. }
+        )
+    }
+}
+
+/// How `get` prints values.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ValueFormat {
+    /// The YAML source of each value, as written in the file
+    Yaml,
+    /// Each value as a line of JSON
+    Json,
+}
+
+/// Picks documents in files holding several manifests.
+#[derive(Args, Debug, Clone)]
+pub struct SelectorArgs {
+    /// Only use documents of this kind
+    #[arg(long)]
+    pub kind: Option<String>,
+
+    /// Only use documents with this `metadata.name`
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Only use documents with this `metadata.namespace`
+    #[arg(short, long)]
+    pub namespace: Option<String>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Human,
+    Json,
+    Sarif,
+    Junit,
 }
 
-#[allow(clippy::struct_field_names)]
 #[derive(Debug)]
 pub struct Config {
+    /// Only missing for commands that work without a cluster, when none could be resolved
+    pub kube: Option<Kube>,
+    pub files: Vec<PathBuf>,
+    pub command: Option<Command>,
+    pub schema: SchemaConfig,
+    pub theme: String,
+    pub keymap: KeymapConfig,
+    pub validation: ValidationConfig,
+    pub editor: EditorConfig,
+    pub airline: AirlineConfig,
+}
+
+/// The kubeconfig and the context of it in use.
+#[derive(Debug)]
+pub struct Kube {
     pub context: String,
-    pub kube_config: Kubeconfig,
-    pub file: Option<PathBuf>,
+    pub config: Kubeconfig,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -48,46 +238,104 @@ pub enum ConfigError {
     KubeConfigReadError(#[from] kube_client::config::KubeconfigError),
     #[error("Invalid context: {0}")]
     InvalidContext(String),
+    #[error("Config file not found: {0}")]
+    MissingConfigFile(PathBuf),
+    #[error("Failed to read config file {0}: {1}")]
+    ConfigFileReadError(PathBuf, std::io::Error),
+    #[error("Failed to parse config file {0}: {1}")]
+    ConfigFileParseError(PathBuf, toml::de::Error),
 }
 
-pub fn parse() -> Result<Config, ConfigError> {
-    let cli = CliConfig::parse();
+impl CliConfig {
+    pub fn has_command(&self) -> bool {
+        self.command.is_some()
+    }
+}
+
+/// Command line arguments, see [`load`].
+pub fn args() -> CliConfig {
+    CliConfig::parse()
+}
+
+/// The config of a run, from the command line `cli` and the m7s and kube config files.
+pub fn load(cli: CliConfig) -> Result<Config, ConfigError> {
+    let cwd = env::current_dir().expect("Could not get current directory");
+
+    // Load m7s config files, CLI flags take precedence over them
+    let config_file = file::load(cli.config.as_deref(), &cwd)?;
+
+    let mut schema = config_file.schema;
+    if cli.offline {
+        schema.source = SchemaSource::Offline;
+    }
 
     // Load kube config
     let kube_config_path = if cli.kube_config.is_relative() {
-        env::current_dir()
-            .expect("Could not get current directory")
-            .join(cli.kube_config)
+        cwd.join(cli.kube_config)
     } else {
         cli.kube_config
     };
+    let context = cli.context.or(config_file.context);
+    let kube = match kube(kube_config_path, context, schema.source) {
+        Ok(kube) => Some(kube),
+        Err(e) if cli.command.as_ref().is_some_and(Command::is_local) => {
+            debug!("Running without a cluster: {e}");
+            None
+        }
+        Err(e) => Err(e)?,
+    };
+
+    schema.cache_dir = schema
+        .cache_dir
+        .map(|dir| file::expand_home(&dir))
+        .or_else(file::default_cache_dir);
 
-    if !kube_config_path.exists() {
-        Err(ConfigError::MissingKubeConfig(kube_config_path.clone()))?;
+    Ok(Config {
+        kube,
+        files: cli.files,
+        command: cli.command,
+        schema,
+        theme: config_file.theme,
+        keymap: config_file.keymap,
+        validation: config_file.validation,
+        editor: config_file.editor,
+        airline: config_file.airline,
+    })
+}
+
+fn kube(path: PathBuf, context: Option<String>, source: SchemaSource) -> Result<Kube, ConfigError> {
+    // Offline schemas are read from the cache of a context, which can be named with `--context`
+    // when there is no kubeconfig, e.g. in CI
+    let kube_config_missing = !path.exists();
+    if kube_config_missing && source == SchemaSource::Cluster {
+        Err(ConfigError::MissingKubeConfig(path.clone()))?;
     }
 
-    let kube_config = Kubeconfig::read_from(kube_config_path)?;
+    let kube_config = if kube_config_missing {
+        Kubeconfig::default()
+    } else {
+        Kubeconfig::read_from(path)?
+    };
 
-    let context =
-        if let Some(ctx) = cli.context {
+    let context = if let Some(ctx) = context {
+        if !kube_config_missing {
             kube_config.contexts.iter().find(|c| c.name == ctx).ok_or(
                 ConfigError::InvalidContext(format!("'{ctx}' not found in kubeconfig")),
             )?;
+        }
 
-            ctx
-        } else {
-            kube_config
-                .current_context
-                .clone()
-                .ok_or(ConfigError::InvalidContext(
-                    "Could not read current_context".to_string(),
-                ))?
-                .to_string()
-        };
+        ctx
+    } else {
+        kube_config
+            .current_context
+            .clone()
+            .ok_or(ConfigError::InvalidContext(
+                "Could not read current_context, pass one with --context".to_string(),
+            ))?
+    };
 
-    Ok(Config {
+    Ok(Kube {
         context,
-        kube_config,
-        file: cli.file,
+        config: kube_config,
     })
 }
@@ -1,5 +1,5 @@
 use crate::api_client::Error as ApiError;
-use crate::app::AppError;
+use crate::app::{AppError, FileError};
 use crate::config::ConfigError;
 
 #[allow(clippy::enum_variant_names)]
@@ -11,4 +11,12 @@ pub enum Error {
     ApplicationError(#[from] AppError),
     #[error("ApiError")]
     ApiError(#[from] ApiError),
+    #[error("FileError")]
+    FileError(#[from] FileError),
+    #[error("IoError")]
+    IoError(#[from] std::io::Error),
+    #[error("LspError")]
+    LspError(#[from] lsp_server::ProtocolError),
+    #[error("SerializationError")]
+    SerializationError(#[from] serde_json::Error),
 }
@@ -1,38 +1,67 @@
 mod api_client;
 mod app;
+mod cli;
 mod config;
 mod error;
 mod logging;
 
 use error::Error;
 use std::error::Error as _;
+use std::process::ExitCode;
+
+/// Exit code when a command could not do its job. `1` is left for commands that report problems,
+/// e.g. invalid manifests. The editor fails with `1`.
+const EXIT_ERROR: u8 = 2;
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     logging::init_logging();
 
-    match run().await {
-        Ok(()) => {}
+    let args = config::args();
+    // Config errors of commands count as failures too, e.g. a missing kubeconfig in CI
+    let failure = if args.has_command() {
+        ExitCode::from(EXIT_ERROR)
+    } else {
+        ExitCode::FAILURE
+    };
+
+    match run(args).await {
+        Ok(code) => code,
         Err(e) => {
             eprintln!("Error: {e}");
             if let Some(source) = e.source() {
                 eprintln!("  {source}");
             }
-            std::process::exit(1);
+            failure
         }
     }
 }
 
-async fn run() -> Result<(), Error> {
-    let config = config::parse()?;
+async fn run(args: config::CliConfig) -> Result<ExitCode, Error> {
+    let config = config::load(args)?;
+
+    if let Some(command) = &config.command {
+        return cli::run(command, &config).await;
+    }
+
+    let files = config
+        .files
+        .iter()
+        .cloned()
+        .map(app::File::from_path)
+        .collect::<Result<Vec<_>, _>>()?;
+    edit(&config, files).await?;
+    Ok(ExitCode::SUCCESS)
+}
 
-    let client = api_client::from_config(&config).await?;
+/// Run the editor on `files` until it quits. The first one is shown.
+async fn edit(config: &config::Config, files: Vec<app::File>) -> Result<(), Error> {
+    let client = api_client::from_config(config).await?;
 
-    let mut app = app::App::new(client);
-    let terminal = app.startup(config.file)?;
+    let mut app = app::App::new(client, config)?;
+    let terminal = app.startup(files)?;
     let result = app.run(terminal).await;
     app.shutdown();
 
-    result?;
-    Ok(())
+    Ok(result?)
 }